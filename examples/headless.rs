@@ -0,0 +1,44 @@
+extern crate platinum;
+
+use platinum::engine::{EngineEventHandler, EngineContext};
+use platinum::headless::HeadlessEngineBuilder;
+use platinum::femtovg::{Color, Paint, Path};
+use platinum::winit::dpi::PhysicalSize;
+
+
+
+fn main() {
+  let mut engine = HeadlessEngineBuilder::new(PhysicalSize::new(320, 240))
+    .create(Handler { i: 0.0 });
+
+  let frames = engine.run_frames(60);
+  println!("rendered {frames} frames, square at {}", engine.handler().i);
+
+  if let Some(path) = std::env::args_os().nth(1) {
+    let screenshot = engine.capture_screenshot().expect("failed to capture screenshot");
+    screenshot.save(&path).expect("failed to save screenshot");
+    println!("saved screenshot to {}", path.display());
+  };
+
+  engine.exit();
+}
+
+struct Handler {
+  i: f32
+}
+
+impl EngineEventHandler for Handler {
+  fn update(&mut self, context: EngineContext) {
+    let dt = context.window_state.delta_time().as_secs_f32();
+    self.i += dt * 60.0;
+  }
+
+  fn render(&mut self, context: EngineContext) {
+    let (width, height) = context.window_state.window_size().into();
+    context.canvas.clear_rect(0, 0, width, height, Color::rgb(22, 33, 44));
+
+    let mut path = Path::new();
+    path.rect(self.i, self.i, 64.0, 64.0);
+    context.canvas.fill_path(&path, &Paint::color(Color::rgb(63, 127, 255)));
+  }
+}
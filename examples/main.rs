@@ -1,7 +1,5 @@
 extern crate platinum;
 
-use std::time::{Duration, Instant};
-
 use platinum::engine::{EngineBuilder, EngineEventHandler, EngineContext};
 use platinum::femtovg::{Color, Paint, Path};
 use platinum::winit::window::Window;
@@ -17,24 +15,16 @@ fn main() {
 
   EngineBuilder::new_without_user_event()
     .with_window_attributes(Some(window_attributes))
-    .create(Handler { last_update: None, i: 0.0 }).run();
+    .create(Handler { i: 0.0 }).run();
 }
 
 struct Handler {
-  last_update: Option<Instant>,
   i: f32
 }
 
-impl Handler {
-  pub fn swap_delta_time(&mut self, now: Instant) -> Duration {
-    self.last_update.replace(now)
-      .map_or(Duration::ZERO, |last_update| now.duration_since(last_update))
-  }
-}
-
 impl EngineEventHandler for Handler {
   fn update(&mut self, context: EngineContext) {
-    let dt = self.swap_delta_time(Instant::now()).as_secs_f32();
+    let dt = context.window_state.delta_time().as_secs_f32();
     let (width, height) = context.window_state.window_size().into();
     let p = u32::min(width, height);
 
@@ -1,26 +1,47 @@
+use ahash::AHashMap;
 use femtovg::renderer::OpenGl;
 use femtovg::Canvas;
-use glutin::surface::{Surface, SurfaceAttributesBuilder, WindowSurface};
-use glutin_winit::{ApiPreference, DisplayBuilder, GlWindow};
+use image::RgbaImage;
+use glutin::surface::{PbufferSurface, Surface, SurfaceAttributesBuilder, SwapInterval, WindowSurface};
+use glutin_winit::{ApiPreference, GlWindow};
 use glutin::config::{Config, ConfigTemplateBuilder};
 use glutin::context::{ContextApi, ContextAttributesBuilder, NotCurrentContext, PossiblyCurrentContext, Version};
-use glutin::display::{Display, GetGlDisplay};
+use glutin::display::{Display, DisplayApiPreference, GetGlDisplay};
 use glutin::prelude::*;
 use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::error::{EventLoopError, OsError};
 use winit::event::{DeviceEvent, DeviceId, ElementState, Ime, KeyEvent, MouseButton, MouseScrollDelta, Touch};
-use winit::event_loop::{EventLoop, EventLoopBuilder};
-use winit::raw_window_handle::HasWindowHandle;
-use winit::window::{Window, WindowAttributes};
+use winit::event_loop::{ActiveEventLoop, EventLoop, EventLoopBuilder};
+use winit::raw_window_handle::{HandleError, HasDisplayHandle, HasWindowHandle, RawWindowHandle};
+use winit::window::{Window, WindowAttributes, WindowId};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
 
-use crate::windowing::{Application, AxisMotion, EventHandler, Gesture, WindowState};
+use crate::gamepad::{GamepadAxis, GamepadButton, GamepadId, GamepadSource};
+use crate::gestures::GestureConfig;
+use crate::misc::{ImageBufferExt, OptionExt};
+use crate::plugins::{EngineCallback, EnginePlugin};
+use crate::proxy::EngineProxy;
+use crate::recording::{FrameRecorder, Recording, RecordingError};
+#[cfg(feature = "serde")]
+use crate::replay::{InputRecorder, InputReplay};
+use crate::windowing::{Application, AxisMotion, EventHandler, FixedTimestep, FrameTiming, Gesture, RedrawMode, SyntheticKeyEvent, WindowState};
 
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::error::Error;
 use std::fmt;
+use std::mem::take;
 use std::num::NonZero;
+use std::ops::ControlFlow;
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::time::Duration;
 
 
 
+/// Picks the config with the most samples, preferring ones which support transparency.
+/// Config pickers are only called if there is at least one config to pick from.
 pub fn default_gl_config_picker(configs: Box<dyn Iterator<Item = Config> + '_>) -> Config {
   configs
     .reduce(|prev_config, config| {
@@ -33,17 +54,68 @@ pub fn default_gl_config_picker(configs: Box<dyn Iterator<Item = Config> + '_>)
         prev_config
       }
     })
-    .unwrap()
+    .unwrap_unreachable()
 }
 
 pub type WindowRef = Rc<Window>;
 
+/// Creates the OpenGL display with the same API preferences as [`DisplayBuilder`][glutin_winit::DisplayBuilder].
+fn create_display<T: 'static>(
+  event_loop: &EventLoop<T>,
+  api_preference: ApiPreference,
+  raw_window_handle: Option<RawWindowHandle>
+) -> Result<Display, EngineError> {
+  #[cfg(windows)]
+  let preference = match api_preference {
+    ApiPreference::PreferEgl => DisplayApiPreference::EglThenWgl(raw_window_handle),
+    ApiPreference::FallbackEgl => DisplayApiPreference::WglThenEgl(raw_window_handle)
+  };
+  #[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+  let preference = {
+    use winit::platform::x11::register_xlib_error_hook;
+    let _ = raw_window_handle;
+    match api_preference {
+      ApiPreference::PreferEgl => DisplayApiPreference::EglThenGlx(Box::new(register_xlib_error_hook)),
+      ApiPreference::FallbackEgl => DisplayApiPreference::GlxThenEgl(Box::new(register_xlib_error_hook))
+    }
+  };
+  #[cfg(target_os = "android")]
+  let preference = {
+    let _ = (api_preference, raw_window_handle);
+    DisplayApiPreference::Egl
+  };
+  #[cfg(target_vendor = "apple")]
+  let preference = {
+    let _ = (api_preference, raw_window_handle);
+    DisplayApiPreference::Cgl
+  };
+
+  let display_handle = event_loop.display_handle()
+    .map_err(|error| EngineError::Display(error.into()))?;
+  unsafe { Display::new(display_handle.as_raw(), preference) }
+    .map_err(|error| EngineError::Display(error.into()))
+}
+
+type EngineParts<T> = (EventLoop<T>, PossiblyCurrentContext, Config, Surface<WindowSurface>, Window);
+
 pub struct EngineBuilder<T: 'static = ()> {
   event_loop_builder: EventLoopBuilder<T>,
   window_attributes: Option<WindowAttributes>,
   gl_api_preference: ApiPreference,
   gl_config_template_builder: ConfigTemplateBuilder,
-  gl_config_picker: fn(Box<dyn Iterator<Item = Config> + '_>) -> Config
+  gl_config_picker: fn(Box<dyn Iterator<Item = Config> + '_>) -> Config,
+  fixed_timestep: Option<FixedTimestep>,
+  gesture_config: Option<GestureConfig>,
+  gamepad_source: Option<Box<dyn GamepadSource>>,
+  redraw_mode: RedrawMode,
+  frame_history_len: usize,
+  vsync: Option<VSync>,
+  recording: Option<FrameRecorder>,
+  plugins: Vec<Box<dyn EnginePlugin<T>>>,
+  #[cfg(feature = "serde")]
+  input_recorder: Option<InputRecorder>,
+  #[cfg(feature = "serde")]
+  input_replay: Option<InputReplay>
 }
 
 impl EngineBuilder {
@@ -77,62 +149,180 @@ impl<T: 'static> EngineBuilder<T> {
     self
   }
 
+  /// Configures the function which picks an OpenGL config from those matching the config template.
+  /// If it returns `None`, creating the engine fails with [`EngineError::NoConfig`].
   pub fn with_gl_config_picker(mut self, gl_config_picker: fn(Box<dyn Iterator<Item = Config> + '_>) -> Config) -> Self {
     self.gl_config_picker = gl_config_picker;
     self
   }
 
-  pub fn create<H: EngineEventHandler<T>>(self, handler: H) -> Engine<H, T> {
-    let (event_loop, current_gl_context, gl_display, gl_window_surface, window) = self.build_parts();
-    let window = Rc::new(window);
-    let canvas = create_canvas(&gl_display);
-
-    Engine {
-      event_loop,
-      application: Application::new(
-        window.clone(),
-        EngineHandlerWrapper {
-          current_gl_context,
-          gl_display,
-          gl_window_surface,
-          canvas,
-          handler
-        }
-      ),
-      window
-    }
+  /// Configures [`EngineEventHandler::update`] to be called at a fixed rate rather than once per frame.
+  /// See [`FixedTimestep`].
+  pub fn with_fixed_timestep(mut self, fixed_timestep: Option<FixedTimestep>) -> Self {
+    self.fixed_timestep = fixed_timestep;
+    self
+  }
+
+  /// Configures the engine to recognize gestures from touch and mouse input, delivering them through
+  /// [`EngineEventHandler::on_gesture`]. See [`GestureRecognizer`][crate::gestures::GestureRecognizer].
+  pub fn with_gesture_recognition(mut self, gesture_config: Option<GestureConfig>) -> Self {
+    self.gesture_config = gesture_config;
+    self
   }
 
-  fn build_parts(mut self) -> (EventLoop<T>, PossiblyCurrentContext, Display, Surface<WindowSurface>, Window) {
-    let event_loop = self.event_loop_builder.build()
-      .expect("failed to build event loop");
+  /// Configures the engine to poll the given source for gamepad input once per frame,
+  /// and at least every [`RedrawMode::GAMEPAD_POLL_INTERVAL`] while waiting for events. See the [`gamepad`][crate::gamepad] module.
+  pub fn with_gamepad_source(mut self, gamepad_source: Option<Box<dyn GamepadSource>>) -> Self {
+    self.gamepad_source = gamepad_source;
+    self
+  }
+
+  /// Configures how often the engine runs frames and redraws its windows. See [`RedrawMode`].
+  pub fn with_redraw_mode(mut self, redraw_mode: RedrawMode) -> Self {
+    self.redraw_mode = redraw_mode;
+    self
+  }
+
+  /// Configures the number of frame times each window's [`FrameTiming`] keeps for computing statistics.
+  /// Defaults to [`FrameTiming::DEFAULT_HISTORY_LEN`].
+  pub fn with_frame_history_len(mut self, frame_history_len: usize) -> Self {
+    self.frame_history_len = frame_history_len;
+    self
+  }
 
-    let gl_config_template_builder = self.gl_config_template_builder.with_alpha_size(8);
-    let gl_display_builder = DisplayBuilder::new()
-      .with_preference(self.gl_api_preference)
-      .with_window_attributes(self.window_attributes);
-    let (window, gl_config) = gl_display_builder
-      .build(&event_loop, gl_config_template_builder, self.gl_config_picker)
-      .expect("failed to build display");
+  /// Configures the vsync mode of all windows, applied once the OpenGL context has been made current.
+  /// If `None`, the platform's default swap interval is left in place.
+  pub fn with_vsync(mut self, vsync: Option<VSync>) -> Self {
+    self.vsync = vsync;
+    self
+  }
+
+  /// Configures the engine to start recording the primary window as soon as it is created.
+  /// See [`EngineContext::start_recording`] for starting a recording while the engine is running.
+  pub fn with_recording(mut self, recording: Option<FrameRecorder>) -> Self {
+    self.recording = recording;
+    self
+  }
+
+  /// Adds a plugin which runs around the handler's callbacks, after any plugins added before it.
+  /// See the [`plugins`][crate::plugins] module.
+  pub fn with_plugin(mut self, plugin: impl EnginePlugin<T>) -> Self {
+    self.plugins.push(Box::new(plugin));
+    self
+  }
 
-    let window = window.expect("display builder produced no window");
+  /// Configures the engine to record the input of the primary window to a file.
+  /// See the [`replay`][crate::replay] module.
+  #[cfg(feature = "serde")]
+  pub fn with_input_recorder(mut self, input_recorder: Option<InputRecorder>) -> Self {
+    self.input_recorder = input_recorder;
+    self
+  }
 
-    let not_current_gl_context = create_gl_context(&window, &gl_config);
+  /// Configures the engine to replay recorded input into the primary window, ignoring real input until the replay finishes.
+  /// See the [`replay`][crate::replay] module.
+  #[cfg(feature = "serde")]
+  pub fn with_input_replay(mut self, input_replay: Option<InputReplay>) -> Self {
+    self.input_replay = input_replay;
+    self
+  }
 
-    let gl_surface_attributes = window.build_surface_attributes(SurfaceAttributesBuilder::default())
-      .expect("failed to build window surface attributes");
+  /// Creates the engine, panicking if any stage of window or OpenGL setup fails.
+  /// See [`EngineBuilder::try_create`] for a fallible alternative.
+  pub fn create<H: EngineEventHandler<T>>(self, handler: H) -> Engine<H, T> {
+    self.try_create(handler).unwrap_or_else(|error| panic!("{error}"))
+  }
 
+  /// Creates the engine, returning an [`EngineError`] describing which stage of setup failed.
+  pub fn try_create<H: EngineEventHandler<T>>(mut self, handler: H) -> Result<Engine<H, T>, EngineError> {
+    let (fixed_timestep, gesture_config, redraw_mode, vsync) = (self.fixed_timestep, self.gesture_config, self.redraw_mode, self.vsync);
+    let frame_history_len = self.frame_history_len;
+    let recorder = self.recording.take();
+    let gamepad_source = self.gamepad_source.take();
+    let plugins = take(&mut self.plugins);
+    #[cfg(feature = "serde")]
+    let (input_recorder, input_replay) = (self.input_recorder.take(), self.input_replay.take());
+    let (event_loop, current_gl_context, gl_config, gl_window_surface, window) = self.build_parts()?;
+    let window = Rc::new(window);
     let gl_display = gl_config.display();
+    let canvas = create_canvas(&gl_display)?;
+
+    let mut gl_state = EngineGlState::new(window.id(), canvas, EngineSurface::Window(gl_window_surface), current_gl_context, gl_config);
+
+    if let Some(vsync) = vsync {
+      gl_state.set_vsync_logged(vsync);
+    };
+
+    let recording = recorder
+      .map(|recorder| Recording::start(recorder, window.id()))
+      .transpose()
+      .map_err(EngineError::Recording)?;
+    let requests = EngineRequests::with_proxy(EngineProxy::from(event_loop.create_proxy()));
+    requests.recording_window_id.set(recording.as_ref().map(Recording::window_id));
+
+    let application = Application::new(
+      window,
+      EngineHandlerWrapper {
+        gl_state,
+        requests,
+        recording,
+        plugins,
+        handler
+      }
+    )
+      .with_fixed_timestep(fixed_timestep)
+      .with_gesture_recognition(gesture_config)
+      .with_gamepad_source(gamepad_source)
+      .with_redraw_mode(redraw_mode)
+      .with_frame_history_len(frame_history_len);
+    #[cfg(feature = "serde")]
+    let application = application
+      .with_input_recorder(input_recorder)
+      .with_input_replay(input_replay);
+
+    Ok(Engine { event_loop, application })
+  }
+
+  fn build_parts(self) -> Result<EngineParts<T>, EngineError> {
+    let mut event_loop_builder = self.event_loop_builder;
+    let event_loop = event_loop_builder.build()
+      .map_err(EngineError::EventLoop)?;
 
-    let gl_window_surface = unsafe {
-      gl_display.create_window_surface(&gl_config, &gl_surface_attributes)
-        .expect("failed to create opengl window surface")
+    // this follows `DisplayBuilder::build`, which cannot fail when no configs match since its picker must return one
+    // with WGL, the window is created first so that configs compatible with it can be found
+    let window_attributes = self.window_attributes.ok_or(EngineError::NoWindow)?;
+    #[cfg(windows)]
+    #[allow(deprecated)]
+    let window = event_loop.create_window(window_attributes)
+      .map_err(EngineError::Window)?;
+    #[cfg(windows)]
+    let raw_window_handle = Some(window.window_handle().map_err(EngineError::WindowHandle)?.as_raw());
+    #[cfg(not(windows))]
+    let raw_window_handle = None;
+
+    let gl_display = create_display(&event_loop, self.gl_api_preference, raw_window_handle)?;
+    let mut gl_config_template_builder = self.gl_config_template_builder.with_alpha_size(8);
+    if let Some(raw_window_handle) = raw_window_handle {
+      gl_config_template_builder = gl_config_template_builder.compatible_with_native_window(raw_window_handle);
     };
 
+    let mut gl_configs = unsafe { gl_display.find_configs(gl_config_template_builder.build()) }
+      .map_err(|error| EngineError::Display(error.into()))?
+      .peekable();
+    if gl_configs.peek().is_none() { return Err(EngineError::NoConfig) };
+    let gl_config = (self.gl_config_picker)(Box::new(gl_configs));
+
+    #[cfg(not(windows))]
+    let window = glutin_winit::finalize_window(&event_loop, window_attributes, &gl_config)
+      .map_err(EngineError::Window)?;
+
+    let not_current_gl_context = create_gl_context(&window, &gl_config)?;
+    let gl_window_surface = create_window_surface(&window, &gl_config)?;
+
     let current_gl_context = not_current_gl_context.make_current(&gl_window_surface)
-      .expect("failed to make opengl context current");
+      .map_err(EngineError::MakeCurrent)?;
 
-    (event_loop, current_gl_context, gl_display, gl_window_surface, window)
+    Ok((event_loop, current_gl_context, gl_config, gl_window_surface, window))
   }
 }
 
@@ -143,60 +333,121 @@ impl<T: 'static> Default for EngineBuilder<T> {
       window_attributes: None,
       gl_api_preference: ApiPreference::default(),
       gl_config_template_builder: ConfigTemplateBuilder::new(),
-      gl_config_picker: default_gl_config_picker
+      gl_config_picker: default_gl_config_picker,
+      fixed_timestep: None,
+      gesture_config: None,
+      gamepad_source: None,
+      redraw_mode: RedrawMode::default(),
+      frame_history_len: FrameTiming::DEFAULT_HISTORY_LEN,
+      vsync: None,
+      recording: None,
+      plugins: Vec::new(),
+      #[cfg(feature = "serde")]
+      input_recorder: None,
+      #[cfg(feature = "serde")]
+      input_replay: None
     }
   }
 }
 
-fn create_canvas(gl_display: &Display) -> EngineCanvas {
+pub(crate) fn create_canvas(gl_display: &Display) -> Result<EngineCanvas, EngineError> {
   let renderer = unsafe {
     OpenGl::new_from_function_cstr(|s| gl_display.get_proc_address(s).cast())
-      .expect("failed to create femtovg opengl renderer")
+      .map_err(EngineError::Renderer)?
+  };
+
+  Canvas::new(renderer).map_err(EngineError::Canvas)
+}
+
+/// Flushes the canvas and reads back its framebuffer as an image, top row first and with straight (non-premultiplied) alpha.
+pub fn capture_screenshot(canvas: &mut EngineCanvas) -> Result<RgbaImage, femtovg::ErrorKind> {
+  canvas.flush();
+
+  // femtovg already flips the rows from OpenGL's bottom-up order
+  let screenshot = canvas.screenshot()?;
+  let mut image = RgbaImage::new(screenshot.width() as u32, screenshot.height() as u32);
+  for (pixel, screenshot_pixel) in image.as_imgref_mut().pixels_mut().zip(screenshot.pixels()) {
+    *pixel = unpremultiply(screenshot_pixel);
   };
 
-  let canvas = Canvas::new(renderer)
-    .expect("failed to create femtovg canvas");
+  Ok(image)
+}
+
+/// Converts a pixel with premultiplied alpha, as produced by femtovg's blending, into straight alpha.
+fn unpremultiply(pixel: femtovg::rgb::RGBA8) -> femtovg::rgb::RGBA8 {
+  match pixel.a {
+    0 => femtovg::rgb::RGBA8::new(0, 0, 0, 0),
+    255 => pixel,
+    a => {
+      let channel = |c: u8| ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8;
+      femtovg::rgb::RGBA8::new(channel(pixel.r), channel(pixel.g), channel(pixel.b), a)
+    }
+  }
+}
+
+fn create_window_surface(window: &Window, gl_config: &Config) -> Result<Surface<WindowSurface>, EngineError> {
+  let gl_surface_attributes = window.build_surface_attributes(SurfaceAttributesBuilder::default())
+    .map_err(EngineError::SurfaceAttributes)?;
 
-  canvas
+  unsafe {
+    gl_config.display().create_window_surface(gl_config, &gl_surface_attributes)
+      .map_err(EngineError::Surface)
+  }
 }
 
-fn create_gl_context(window: &Window, gl_config: &Config) -> NotCurrentContext {
+fn create_gl_context(window: &Window, gl_config: &Config) -> Result<NotCurrentContext, EngineError> {
   let raw_window_handle = window.window_handle()
-    .expect("could not get window handle from window")
+    .map_err(EngineError::WindowHandle)?
     .as_raw();
 
+  create_gl_context_for(Some(raw_window_handle), gl_config)
+}
+
+/// Creates an OpenGL context, falling back to OpenGL ES and then legacy OpenGL if necessary.
+pub(crate) fn create_gl_context_for(raw_window_handle: Option<RawWindowHandle>, gl_config: &Config) -> Result<NotCurrentContext, EngineError> {
   let gl_context_attributes = ContextAttributesBuilder::new()
-    .build(Some(raw_window_handle));
+    .build(raw_window_handle);
 
   let fallback_gl_context_attributes = ContextAttributesBuilder::new()
     .with_context_api(ContextApi::Gles(None))
-    .build(Some(raw_window_handle));
+    .build(raw_window_handle);
 
   let legacy_gl_context_attributes = ContextAttributesBuilder::new()
     .with_context_api(ContextApi::OpenGl(Some(Version::new(2, 1))))
-    .build(Some(raw_window_handle));
+    .build(raw_window_handle);
 
   let gl_display = gl_config.display();
 
-  let not_current_gl_context = unsafe {
-    gl_display.create_context(&gl_config, &gl_context_attributes)
-      .or_else(|_| gl_display.create_context(&gl_config, &fallback_gl_context_attributes))
-      .or_else(|_| gl_display.create_context(&gl_config, &legacy_gl_context_attributes))
-      .expect("failed to create opengl context")
-  };
-
-  not_current_gl_context
+  unsafe {
+    gl_display.create_context(gl_config, &gl_context_attributes)
+      .or_else(|_| gl_display.create_context(gl_config, &fallback_gl_context_attributes))
+      .or_else(|_| gl_display.create_context(gl_config, &legacy_gl_context_attributes))
+      .map_err(EngineError::Context)
+  }
 }
 
 impl<T: 'static> fmt::Debug for EngineBuilder<T> {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    f.debug_struct("EngineBuilder")
+    let mut debug_struct = f.debug_struct("EngineBuilder");
+    debug_struct
       .field("event_loop_builder", &format_args!("EventLoopBuilder"))
       .field("window_attributes", &self.window_attributes)
       .field("gl_api_preference", &self.gl_api_preference)
       .field("gl_config_template_builder", &self.gl_config_template_builder)
       .field("gl_config_picker", &self.gl_config_picker)
-      .finish()
+      .field("fixed_timestep", &self.fixed_timestep)
+      .field("gesture_config", &self.gesture_config)
+      .field("gamepad_source", &self.gamepad_source)
+      .field("redraw_mode", &self.redraw_mode)
+      .field("frame_history_len", &self.frame_history_len)
+      .field("vsync", &self.vsync)
+      .field("recording", &self.recording)
+      .field("plugins", &self.plugins);
+    #[cfg(feature = "serde")]
+    debug_struct
+      .field("input_recorder", &self.input_recorder)
+      .field("input_replay", &self.input_replay);
+    debug_struct.finish()
   }
 }
 
@@ -205,104 +456,591 @@ impl<T: 'static> fmt::Debug for EngineBuilder<T> {
 #[derive(Debug)]
 pub struct Engine<H: EngineEventHandler<T>, T: 'static = ()> {
   event_loop: EventLoop<T>,
-  application: Application<WindowRef, EngineHandlerWrapper<H>, T>,
-  window: WindowRef
+  application: Application<WindowRef, EngineHandlerWrapper<H, T>, T>
 }
 
 impl<H: EngineEventHandler<T>, T: 'static> Engine<H, T> {
-  pub fn window(&self) -> &WindowRef {
-    &self.window
+  /// Returns the primary window, which is the oldest window that is still open.
+  pub fn window(&self) -> Option<&WindowRef> {
+    self.application.primary_window_state().map(WindowState::window_holder)
   }
 
   pub fn event_loop(&self) -> &EventLoop<T> {
     &self.event_loop
   }
 
+  /// Returns a proxy for sending user events to the engine from other threads, including before it starts running.
+  /// See the [`proxy`][crate::proxy] module.
+  pub fn proxy(&self) -> EngineProxy<T> {
+    EngineProxy::from(self.event_loop.create_proxy())
+  }
+
+  /// Sets the vsync mode of all windows.
+  /// See [`EngineContext::set_vsync`] for changing it while the engine is running.
+  pub fn set_vsync(&mut self, vsync: VSync) -> Result<(), glutin::error::Error> {
+    let wrapper = self.application.handler_mut().expect_unreachable("engine handler has already exited");
+    wrapper.gl_state.set_vsync(vsync)
+  }
+
+  /// Runs the event loop until exit, panicking if the event loop fails.
+  /// See [`Engine::try_run`] for a fallible alternative.
   pub fn run(self) {
-    let Engine { event_loop, mut application, window } = self;
+    self.try_run().expect("failed to run event loop");
+  }
+
+  /// Runs the event loop until exit, returning any error produced by the event loop.
+  pub fn try_run(self) -> Result<(), EventLoopError> {
+    let Engine { event_loop, mut application } = self;
     event_loop.run_app(&mut application)
-      .expect("failed to run event loop");
-    drop(application);
-    drop(window);
   }
 }
 
 
 
-struct EngineHandlerWrapper<H> {
+/// The vsync mode of a window, which determines whether buffer swaps wait for the display's vertical blank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum VSync {
+  /// Buffer swaps wait for the vertical blank, preventing tearing.
+  On,
+  /// Buffer swaps happen immediately, which may cause tearing.
+  Off
+}
+
+impl VSync {
+  fn swap_interval(self) -> SwapInterval {
+    match self {
+      VSync::On => SwapInterval::Wait(NonZero::<u32>::MIN),
+      VSync::Off => SwapInterval::DontWait
+    }
+  }
+}
+
+/// An error produced by [`EngineBuilder::try_create`], [`HeadlessEngineBuilder::try_create`][crate::headless::HeadlessEngineBuilder::try_create] or when opening an additional window,
+/// identifying which stage of setup failed.
+#[derive(Debug)]
+pub enum EngineError {
+  /// The winit event loop could not be built.
+  EventLoop(EventLoopError),
+  /// No suitable OpenGL display could be found, or the window could not be created.
+  Display(Box<dyn Error>),
+  /// No window was created, since no window attributes were given.
+  NoWindow,
+  /// Headless rendering is not supported on this platform.
+  HeadlessUnsupported,
+  /// No OpenGL display could be created for headless rendering.
+  HeadlessDisplay(glutin::error::Error),
+  /// No OpenGL config matched the config template, or the config picker rejected all of them.
+  NoConfig,
+  /// A window could not be created.
+  Window(OsError),
+  /// A raw window handle could not be obtained from the window.
+  WindowHandle(HandleError),
+  /// Window surface attributes could not be built from the window.
+  SurfaceAttributes(HandleError),
+  /// No OpenGL context could be created with any of the attempted context APIs.
+  Context(glutin::error::Error),
+  /// The OpenGL window surface could not be created.
+  Surface(glutin::error::Error),
+  /// The OpenGL context could not be made current.
+  MakeCurrent(glutin::error::Error),
+  /// The femtovg OpenGL renderer could not be created.
+  Renderer(femtovg::ErrorKind),
+  /// The femtovg canvas could not be created.
+  Canvas(femtovg::ErrorKind),
+  /// The recording configured with [`EngineBuilder::with_recording`] could not be started.
+  Recording(RecordingError)
+}
+
+impl fmt::Display for EngineError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      EngineError::EventLoop(error) => write!(f, "failed to build event loop: {error}"),
+      EngineError::Display(error) => write!(f, "failed to build display: {error}"),
+      EngineError::NoWindow => f.write_str("display builder produced no window"),
+      EngineError::HeadlessUnsupported => f.write_str("headless rendering is not supported on this platform"),
+      EngineError::HeadlessDisplay(error) => write!(f, "failed to create headless opengl display: {error}"),
+      EngineError::NoConfig => f.write_str("no suitable opengl config matched the config template"),
+      EngineError::Window(error) => write!(f, "failed to create window: {error}"),
+      EngineError::WindowHandle(error) => write!(f, "could not get window handle from window: {error}"),
+      EngineError::SurfaceAttributes(error) => write!(f, "failed to build window surface attributes: {error}"),
+      EngineError::Context(error) => write!(f, "failed to create opengl context: {error}"),
+      EngineError::Surface(error) => write!(f, "failed to create opengl window surface: {error}"),
+      EngineError::MakeCurrent(error) => write!(f, "failed to make opengl context current: {error}"),
+      EngineError::Renderer(error) => write!(f, "failed to create femtovg opengl renderer: {error}"),
+      EngineError::Canvas(error) => write!(f, "failed to create femtovg canvas: {error}"),
+      EngineError::Recording(error) => write!(f, "failed to start recording: {error}")
+    }
+  }
+}
+
+impl Error for EngineError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      EngineError::EventLoop(error) => Some(error),
+      EngineError::Display(error) => Some(error.as_ref()),
+      EngineError::NoWindow | EngineError::HeadlessUnsupported | EngineError::NoConfig => None,
+      EngineError::HeadlessDisplay(error) => Some(error),
+      EngineError::Window(error) => Some(error),
+      EngineError::WindowHandle(error) | EngineError::SurfaceAttributes(error) => Some(error),
+      EngineError::Context(error) | EngineError::Surface(error) | EngineError::MakeCurrent(error) => Some(error),
+      EngineError::Renderer(error) | EngineError::Canvas(error) => Some(error),
+      EngineError::Recording(error) => Some(error)
+    }
+  }
+}
+
+
+
+/// An OpenGL surface that a canvas draws to, either belonging to a window or offscreen.
+#[derive(Debug)]
+pub(crate) enum EngineSurface {
+  Window(Surface<WindowSurface>),
+  Pbuffer(Surface<PbufferSurface>)
+}
+
+impl EngineSurface {
+  fn make_current(&self, gl_context: &PossiblyCurrentContext) -> glutin::error::Result<()> {
+    match self {
+      EngineSurface::Window(surface) => gl_context.make_current(surface),
+      EngineSurface::Pbuffer(surface) => gl_context.make_current(surface)
+    }
+  }
+
+  fn swap_buffers(&self, gl_context: &PossiblyCurrentContext) -> glutin::error::Result<()> {
+    match self {
+      EngineSurface::Window(surface) => surface.swap_buffers(gl_context),
+      // pbuffers are single-buffered, so there is nothing to swap
+      EngineSurface::Pbuffer(..) => Ok(())
+    }
+  }
+
+  fn resize(&self, gl_context: &PossiblyCurrentContext, width: NonZero<u32>, height: NonZero<u32>) {
+    match self {
+      EngineSurface::Window(surface) => surface.resize(gl_context, width, height),
+      // pbuffers have a fixed size
+      EngineSurface::Pbuffer(..) => ()
+    }
+  }
+
+  fn set_swap_interval(&self, gl_context: &PossiblyCurrentContext, interval: SwapInterval) -> glutin::error::Result<()> {
+    match self {
+      EngineSurface::Window(surface) => surface.set_swap_interval(gl_context, interval),
+      EngineSurface::Pbuffer(surface) => surface.set_swap_interval(gl_context, interval)
+    }
+  }
+}
+
+struct EngineWindowSurface {
+  // the canvas must be dropped before the surface it draws to
+  canvas: EngineCanvas,
+  gl_surface: EngineSurface
+}
+
+impl fmt::Debug for EngineWindowSurface {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("EngineWindowSurface")
+      .field("canvas", &format_args!("Canvas"))
+      .field("gl_surface", &self.gl_surface)
+      .finish()
+  }
+}
+
+/// The OpenGL context shared between all windows, along with each window's surface and canvas.
+#[derive(Debug)]
+pub(crate) struct EngineGlState {
+  // the window surfaces must be dropped before the context
+  window_surfaces: AHashMap<WindowId, EngineWindowSurface>,
+  current_window_id: Option<WindowId>,
   current_gl_context: PossiblyCurrentContext,
-  #[allow(unused)]
+  gl_config: Config,
   gl_display: Display,
-  gl_window_surface: Surface<WindowSurface>,
-  canvas: EngineCanvas,
+  vsync: Option<VSync>
+}
+
+impl EngineGlState {
+  /// Creates the OpenGL state for a single surface, which the context must currently be made current with.
+  pub(crate) fn new(
+    window_id: WindowId,
+    canvas: EngineCanvas,
+    gl_surface: EngineSurface,
+    current_gl_context: PossiblyCurrentContext,
+    gl_config: Config
+  ) -> Self {
+    let mut window_surfaces = AHashMap::new();
+    window_surfaces.insert(window_id, EngineWindowSurface { canvas, gl_surface });
+    EngineGlState {
+      window_surfaces,
+      current_window_id: Some(window_id),
+      current_gl_context,
+      gl_display: gl_config.display(),
+      gl_config,
+      vsync: None
+    }
+  }
+
+  /// Makes the OpenGL context current with the given window's surface if it is not already, returning that window's canvas.
+  pub(crate) fn canvas_mut(&mut self, window_id: WindowId) -> &mut EngineCanvas {
+    &mut self.activate(window_id).0.canvas
+  }
+
+  /// Makes the OpenGL context current with the given window's surface if it is not already,
+  /// returning that window's surface and canvas along with the context.
+  fn activate(&mut self, window_id: WindowId) -> (&mut EngineWindowSurface, &PossiblyCurrentContext) {
+    let window_surface = self.window_surfaces.get_mut(&window_id)
+      .expect_unreachable("window has no opengl surface");
+    if self.current_window_id != Some(window_id) {
+      window_surface.gl_surface.make_current(&self.current_gl_context)
+        .expect("failed to make opengl context current");
+      self.current_window_id = Some(window_id);
+    };
+
+    (window_surface, &self.current_gl_context)
+  }
+
+  fn create_window(&mut self, event_loop: &ActiveEventLoop, window_attributes: WindowAttributes) -> Result<Window, EngineError> {
+    let window = glutin_winit::finalize_window(event_loop, window_attributes, &self.gl_config)
+      .map_err(EngineError::Window)?;
+    let gl_window_surface = create_window_surface(&window, &self.gl_config)?;
+
+    self.current_window_id = None;
+    self.current_gl_context.make_current(&gl_window_surface)
+      .map_err(EngineError::MakeCurrent)?;
+    let canvas = create_canvas(&self.gl_display)?;
+
+    self.current_window_id = Some(window.id());
+    if let Some(vsync) = self.vsync
+      && let Err(error) = gl_window_surface.set_swap_interval(&self.current_gl_context, vsync.swap_interval()) {
+      log::warn!("failed to set vsync to {vsync:?} for new window, leaving platform default: {error}");
+    };
+
+    self.window_surfaces.insert(window.id(), EngineWindowSurface { canvas, gl_surface: EngineSurface::Window(gl_window_surface) });
+
+    Ok(window)
+  }
+
+  /// Sets the swap interval of every window surface, returning the first error encountered.
+  fn set_vsync(&mut self, vsync: VSync) -> Result<(), glutin::error::Error> {
+    self.vsync = Some(vsync);
+    let window_ids = self.window_surfaces.keys().copied().collect::<Vec<WindowId>>();
+    let mut result = Ok(());
+    for window_id in window_ids {
+      let (window_surface, current_gl_context) = self.activate(window_id);
+      let window_result = window_surface.gl_surface.set_swap_interval(current_gl_context, vsync.swap_interval());
+      result = result.and(window_result);
+    };
+
+    result
+  }
+
+  fn set_vsync_logged(&mut self, vsync: VSync) {
+    if let Err(error) = self.set_vsync(vsync) {
+      log::warn!("failed to set vsync to {vsync:?}, leaving platform default: {error}");
+    };
+  }
+}
+
+/// Requests made through an [`EngineContext`] during a callback, applied once the callback returns.
+#[derive(Debug, Default)]
+pub(crate) struct EngineRequests {
+  vsync: Cell<Option<VSync>>,
+  screenshots: RefCell<Vec<WindowId>>,
+  recording: RefCell<Option<RecordingRequest>>,
+  /// The window being recorded, kept in sync with [`EngineHandlerWrapper::recording`] so contexts can query it.
+  recording_window_id: Cell<Option<WindowId>>,
+  /// The engine's [`EngineProxy`], whose user event type is erased since contexts are not generic over it.
+  proxy: Option<Box<dyn Any>>
+}
+
+#[derive(Debug)]
+enum RecordingRequest {
+  Start(Box<FrameRecorder>, WindowId),
+  Stop
+}
+
+impl EngineRequests {
+  fn with_proxy<T: 'static>(proxy: EngineProxy<T>) -> Self {
+    EngineRequests { proxy: Some(Box::new(proxy)), ..EngineRequests::default() }
+  }
+
+  fn take_screenshot_request(&self, window_id: WindowId) -> bool {
+    let mut screenshots = self.screenshots.borrow_mut();
+    let requested = screenshots.contains(&window_id);
+    screenshots.retain(|&screenshot_window_id| screenshot_window_id != window_id);
+    requested
+  }
+}
+
+pub(crate) struct EngineHandlerWrapper<H, T: 'static> {
+  gl_state: EngineGlState,
+  requests: EngineRequests,
+  recording: Option<Recording>,
+  plugins: Vec<Box<dyn EnginePlugin<T>>>,
   handler: H
 }
 
+impl<H: EngineEventHandler<T>, T: 'static> EngineHandlerWrapper<H, T> {
+  /// Wraps a handler which is not recording and has no plugins yet.
+  pub(crate) fn new(gl_state: EngineGlState, proxy: EngineProxy<T>, handler: H) -> Self {
+    EngineHandlerWrapper {
+      gl_state,
+      requests: EngineRequests::with_proxy(proxy),
+      recording: None,
+      plugins: Vec::new(),
+      handler
+    }
+  }
+
+  #[inline]
+  pub(crate) fn handler(&self) -> &H {
+    &self.handler
+  }
+
+  #[inline]
+  pub(crate) fn handler_mut(&mut self) -> &mut H {
+    &mut self.handler
+  }
+
+  #[inline]
+  pub(crate) fn add_plugin(&mut self, plugin: Box<dyn EnginePlugin<T>>) {
+    self.plugins.push(plugin);
+  }
+
+  #[inline]
+  pub(crate) fn canvas_mut(&mut self, window_id: WindowId) -> &mut EngineCanvas {
+    self.gl_state.canvas_mut(window_id)
+  }
+
+  fn process_requests(&mut self) {
+    if let Some(vsync) = self.requests.vsync.take() {
+      self.gl_state.set_vsync_logged(vsync);
+    };
+
+    let recording_request = self.requests.recording.borrow_mut().take();
+    match recording_request {
+      Some(RecordingRequest::Start(recorder, window_id)) => {
+        self.finish_recording();
+        match Recording::start(*recorder, window_id) {
+          Ok(recording) => self.recording = Some(recording),
+          Err(error) => self.handler.on_recording_finished(Err(error))
+        };
+      },
+      Some(RecordingRequest::Stop) => self.finish_recording(),
+      None => ()
+    };
+
+    self.requests.recording_window_id.set(self.recording.as_ref().map(Recording::window_id));
+  }
+
+  /// Waits for the active recording, if any, to be written, and reports its result to the handler.
+  fn finish_recording(&mut self) {
+    if let Some(recording) = self.recording.take() {
+      self.requests.recording_window_id.set(None);
+      self.handler.on_recording_finished(recording.finish());
+    };
+  }
+
+  /// Runs a handler callback between the before and after hooks of each plugin,
+  /// returning the default output if a plugin short-circuits it.
+  fn run_with_plugins<R: Default>(
+    &mut self,
+    window_state: &EngineWindowState,
+    callback: EngineCallback,
+    call: impl FnOnce(&mut H, EngineContext) -> R
+  ) -> R {
+    self.run_with_hooks(window_state, |plugin, context| plugin.before(context, callback), callback, call)
+  }
+
+  /// Like [`EngineHandlerWrapper::run_with_plugins`], but with a different before hook,
+  /// for callbacks whose arguments are given to plugins in another form.
+  fn run_with_hooks<R: Default>(
+    &mut self,
+    window_state: &EngineWindowState,
+    mut before: impl FnMut(&mut dyn EnginePlugin<T>, EngineContext) -> ControlFlow<()>,
+    callback: EngineCallback,
+    call: impl FnOnce(&mut H, EngineContext) -> R
+  ) -> R {
+    let canvas = &mut self.gl_state.activate(window_state.id()).0.canvas;
+    let requests = &self.requests;
+    let mut plugins_run = 0;
+    let mut output = None;
+    for plugin in self.plugins.iter_mut() {
+      let context = EngineContext::with_requests(window_state, &mut *canvas, requests);
+      if before(plugin.as_mut(), context).is_break() { break };
+      plugins_run += 1;
+    };
+
+    if plugins_run == self.plugins.len() {
+      output = Some(call(&mut self.handler, EngineContext::with_requests(window_state, &mut *canvas, requests)));
+    };
+
+    for plugin in self.plugins[..plugins_run].iter_mut().rev() {
+      plugin.after(EngineContext::with_requests(window_state, &mut *canvas, requests), callback);
+    };
+
+    output.unwrap_or_default()
+  }
+}
+
 macro_rules! delegate_engine {
-  ($vis:vis fn $name:ident(&mut self $(, $arg:ident : $Arg:ty)* $(,)?) $(-> $Ret:ty)?) => (
+  ($vis:vis fn $name:ident(&mut self $(, $arg:ident : $Arg:ty)* $(,)?) $(-> $Ret:ty)? => $callback:expr) => (
     #[inline] $vis fn $name(&mut self, window_state: &EngineWindowState, $($arg: $Arg),*) $(-> $Ret)? {
-      self.handler.$name(EngineContext { window_state, canvas: &mut self.canvas }, $($arg),*)
+      let output = if self.plugins.is_empty() {
+        let canvas = &mut self.gl_state.activate(window_state.id()).0.canvas;
+        self.handler.$name(EngineContext::with_requests(window_state, canvas, &self.requests), $($arg),*)
+      } else {
+        // plugins are given the arguments by reference, so the handler is given its own copy
+        #[allow(clippy::clone_on_copy)]
+        self.run_with_plugins(window_state, $callback, |handler, context| handler.$name(context, $($arg.clone()),*))
+      };
+
+      self.process_requests();
+      output
     }
   );
 }
 
-impl<H: EngineEventHandler<T>, T: 'static> EventHandler<WindowRef, T> for EngineHandlerWrapper<H> {
-  delegate_engine!(fn init(&mut self));
-  delegate_engine!(fn update(&mut self));
+impl<H: EngineEventHandler<T>, T: 'static> EventHandler<WindowRef, T> for EngineHandlerWrapper<H, T> {
+  delegate_engine!(fn init(&mut self) => EngineCallback::Init);
+  delegate_engine!(fn update(&mut self) => EngineCallback::Update);
 
   fn render(&mut self, window_state: &EngineWindowState) {
-    let window = window_state.window();
+    let window = window_state.try_window();
+    let (window_surface, _) = self.gl_state.activate(window_state.id());
+
+    let PhysicalSize { width, height } = window.map_or(window_state.window_size(), Window::inner_size);
+    let scale_factor = window.map_or(window_state.scale_factor(), Window::scale_factor);
+    window_surface.canvas.set_size(width, height, scale_factor as f32);
+
+    self.run_with_plugins(window_state, EngineCallback::Render, H::render);
 
-    let PhysicalSize { width, height } = window.inner_size();
-    self.canvas.set_size(width, height, window.scale_factor() as f32);
+    if let Some(window) = window {
+      window.pre_present_notify();
+    };
+
+    let (window_surface, current_gl_context) = self.gl_state.activate(window_state.id());
 
-    let context = EngineContext { window_state, canvas: &mut self.canvas };
-    self.handler.render(context);
+    window_surface.canvas.flush();
+    if let Some(recording) = &mut self.recording && recording.window_id() == window_state.id() {
+      recording.record_frame(&mut window_surface.canvas);
+    };
 
-    window.pre_present_notify();
+    if self.requests.take_screenshot_request(window_state.id()) {
+      // the back buffer must be read before it is swapped, after which its contents are undefined
+      let screenshot = capture_screenshot(&mut window_surface.canvas);
+      let context = EngineContext::with_requests(window_state, &mut window_surface.canvas, &self.requests);
+      self.handler.on_screenshot_captured(context, screenshot);
+    };
 
-    self.canvas.flush();
-    self.gl_window_surface.swap_buffers(&self.current_gl_context)
+    window_surface.gl_surface.swap_buffers(current_gl_context)
       .expect("failed to swap opengl window surface buffers");
+
+    self.process_requests();
   }
 
   fn on_resized(&mut self, window_state: &EngineWindowState, window_size: PhysicalSize<u32>, scale_factor: f64) {
+    let (window_surface, current_gl_context) = self.gl_state.activate(window_state.id());
+
     let PhysicalSize { width, height } = window_size;
     if let Some(width) = NonZero::new(width) && let Some(height) = NonZero::new(height) {
-      self.gl_window_surface.resize(&self.current_gl_context, width, height);
+      window_surface.gl_surface.resize(current_gl_context, width, height);
+    };
+
+    let callback = EngineCallback::Resized(window_size, scale_factor);
+    self.run_with_plugins(window_state, callback, |handler, context| handler.on_resized(context, window_size, scale_factor));
+    self.process_requests();
+  }
+
+  fn create_window(&mut self, event_loop: &ActiveEventLoop, window_attributes: WindowAttributes) -> Result<WindowRef, Box<dyn Error>> {
+    Ok(Rc::new(self.gl_state.create_window(event_loop, window_attributes)?))
+  }
+
+  delegate_engine!(fn on_window_opened(&mut self) => EngineCallback::WindowOpened);
+
+  fn on_window_open_failed(&mut self, error: Box<dyn Error>) {
+    for plugin in self.plugins.iter_mut() {
+      plugin.on_window_open_failed(error.as_ref());
+    };
+
+    self.handler.on_window_open_failed(error);
+  }
+
+  fn on_window_closed(&mut self, window_state: &EngineWindowState) {
+    let window_id = window_state.id();
+    self.run_with_plugins(window_state, EngineCallback::WindowClosed, H::on_window_closed);
+
+    if self.recording.as_ref().is_some_and(|recording| recording.window_id() == window_id) {
+      self.finish_recording();
     };
 
-    let context = EngineContext { window_state, canvas: &mut self.canvas };
-    self.handler.on_resized(context, window_size, scale_factor);
-  }
-
-  delegate_engine!(fn on_user_event(&mut self, event: T));
-  delegate_engine!(fn on_device_event(&mut self, id: DeviceId, event: DeviceEvent));
-  delegate_engine!(fn on_keyboard_input(&mut self, event: KeyEvent));
-  delegate_engine!(fn on_text_input(&mut self, event: Ime));
-  delegate_engine!(fn on_cursor_moved(&mut self, pos: PhysicalPosition<f32>));
-  delegate_engine!(fn on_mouse_input(&mut self, state: ElementState, button: MouseButton));
-  delegate_engine!(fn on_mouse_scroll(&mut self, delta: MouseScrollDelta));
-  delegate_engine!(fn on_gesture(&mut self, gesture: Gesture));
-  delegate_engine!(fn on_touch(&mut self, touch: Touch));
-  delegate_engine!(fn on_axis_motion(&mut self, axis_motion: AxisMotion));
-  delegate_engine!(fn on_focus_changed(&mut self, state: bool));
-  delegate_engine!(fn on_occlusion_changed(&mut self, state: bool));
-  delegate_engine!(fn on_file_over(&mut self, path: Option<PathBuf>, dropped: bool));
-  delegate_engine!(fn on_resumed(&mut self));
-  delegate_engine!(fn on_suspended(&mut self));
-  delegate_engine!(fn on_close_requested(&mut self) -> bool);
-  delegate!(handler: fn should_exit(&self, window_state: &EngineWindowState) -> bool);
-  delegate!(handler: fn on_exited(self));
-}
-
-impl<H: fmt::Debug> fmt::Debug for EngineHandlerWrapper<H> {
+    // the context is still current with this surface while its canvas is dropped
+    drop(self.gl_state.window_surfaces.remove(&window_id));
+    self.gl_state.current_window_id = None;
+    self.process_requests();
+  }
+
+  delegate_engine!(fn on_ticks_dropped(&mut self, count: u32) => EngineCallback::TicksDropped(count));
+
+  fn on_user_event(&mut self, window_state: &EngineWindowState, event: T) {
+    // the event is moved into the handler, so only the before hooks see it
+    let event = RefCell::new(Some(event));
+    self.run_with_hooks(
+      window_state,
+      |plugin, context| plugin.on_user_event(context, event.borrow().as_ref().unwrap_unreachable()),
+      EngineCallback::UserEvent,
+      |handler, context| handler.on_user_event(context, event.borrow_mut().take().unwrap_unreachable())
+    );
+
+    self.process_requests();
+  }
+
+  delegate_engine!(fn on_device_event(&mut self, id: DeviceId, event: DeviceEvent) => EngineCallback::DeviceEvent(id, &event));
+  delegate_engine!(fn on_keyboard_input(&mut self, event: KeyEvent) => EngineCallback::KeyboardInput(&event));
+  delegate_engine!(fn on_synthetic_keyboard_input(&mut self, event: SyntheticKeyEvent) => EngineCallback::SyntheticKeyboardInput(&event));
+  delegate_engine!(fn on_text_input(&mut self, event: Ime) => EngineCallback::TextInput(&event));
+  delegate_engine!(fn on_cursor_moved(&mut self, pos: PhysicalPosition<f32>) => EngineCallback::CursorMoved(pos));
+  delegate_engine!(fn on_cursor_entered(&mut self) => EngineCallback::CursorEntered);
+  delegate_engine!(fn on_cursor_left(&mut self) => EngineCallback::CursorLeft);
+  delegate_engine!(fn on_mouse_input(&mut self, state: ElementState, button: MouseButton) => EngineCallback::MouseInput(state, button));
+  delegate_engine!(fn on_mouse_scroll(&mut self, delta: MouseScrollDelta) => EngineCallback::MouseScroll(delta));
+  delegate_engine!(fn on_gesture(&mut self, gesture: Gesture) => EngineCallback::Gesture(gesture));
+  delegate_engine!(fn on_touch(&mut self, touch: Touch) => EngineCallback::Touch(touch));
+  delegate_engine!(fn on_axis_motion(&mut self, axis_motion: AxisMotion) => EngineCallback::AxisMotion(axis_motion));
+  delegate_engine!(fn on_gamepad_connected(&mut self, id: GamepadId) => EngineCallback::GamepadConnected(id));
+  delegate_engine!(fn on_gamepad_disconnected(&mut self, id: GamepadId) => EngineCallback::GamepadDisconnected(id));
+  delegate_engine!(fn on_gamepad_button(&mut self, id: GamepadId, button: GamepadButton, state: ElementState) => EngineCallback::GamepadButton(id, button, state));
+  delegate_engine!(fn on_gamepad_axis(&mut self, id: GamepadId, axis: GamepadAxis, value: f32) => EngineCallback::GamepadAxis(id, axis, value));
+  delegate_engine!(fn on_focus_changed(&mut self, state: bool) => EngineCallback::FocusChanged(state));
+  delegate_engine!(fn on_occlusion_changed(&mut self, state: bool) => EngineCallback::OcclusionChanged(state));
+  delegate_engine!(fn on_file_over(&mut self, path: Option<PathBuf>, dropped: bool) => EngineCallback::FileOver(path.as_deref(), dropped));
+  delegate_engine!(fn on_resumed(&mut self) => EngineCallback::Resumed);
+  delegate_engine!(fn on_suspended(&mut self) => EngineCallback::Suspended);
+  delegate_engine!(fn on_close_requested(&mut self) -> bool => EngineCallback::CloseRequested);
+
+  fn should_exit(&self, window_state: &EngineWindowState) -> bool {
+    // like after hooks, plugins are asked from last to first, each able to override the answer so far
+    self.plugins.iter().rev().fold(self.handler.should_exit(window_state), |should_exit, plugin| {
+      plugin.should_exit(window_state, should_exit)
+    })
+  }
+
+  fn simulated_frame_duration(&self) -> Option<Duration> {
+    self.recording.as_ref().map(Recording::frame_duration)
+  }
+
+  fn on_exited(mut self) {
+    self.finish_recording();
+    self.handler.on_exited();
+  }
+}
+
+impl<H: fmt::Debug, T: 'static> fmt::Debug for EngineHandlerWrapper<H, T> {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     f.debug_struct("EngineHandlerWrapper")
-      .field("current_gl_context", &self.current_gl_context)
-      .field("gl_display", &self.gl_display)
-      .field("gl_window_surface", &self.gl_window_surface)
-      .field("canvas", &format_args!("Canvas"))
+      .field("gl_state", &self.gl_state)
+      .field("requests", &self.requests)
+      .field("recording", &self.recording)
+      .field("plugins", &self.plugins)
       .field("handler", &self.handler)
       .finish()
   }
@@ -313,7 +1051,89 @@ pub type EngineWindowState = WindowState<WindowRef>;
 
 pub struct EngineContext<'a> {
   pub window_state: &'a EngineWindowState,
-  pub canvas: &'a mut EngineCanvas
+  pub canvas: &'a mut EngineCanvas,
+  requests: Option<&'a EngineRequests>
+}
+
+impl<'a> EngineContext<'a> {
+  /// Creates a context which is not attached to a running engine, such as for driving a handler from tests.
+  /// Requests made through it, such as screenshots and vsync changes, are ignored.
+  pub fn new(window_state: &'a EngineWindowState, canvas: &'a mut EngineCanvas) -> Self {
+    EngineContext { window_state, canvas, requests: None }
+  }
+
+  #[inline]
+  pub(crate) fn with_requests(window_state: &'a EngineWindowState, canvas: &'a mut EngineCanvas, requests: &'a EngineRequests) -> Self {
+    EngineContext { window_state, canvas, requests: Some(requests) }
+  }
+
+  /// Reborrows this context for a shorter lifetime, such as to pass it to several callbacks in turn.
+  pub fn reborrow(&mut self) -> EngineContext<'_> {
+    EngineContext { window_state: self.window_state, canvas: &mut *self.canvas, requests: self.requests }
+  }
+
+  /// Immediately captures the contents of this window's canvas, including everything drawn so far this frame.
+  /// When called from [`EngineEventHandler::render`], this should be done after all drawing is finished.
+  /// See [`capture_screenshot`].
+  pub fn capture_screenshot(&mut self) -> Result<RgbaImage, femtovg::ErrorKind> {
+    capture_screenshot(self.canvas)
+  }
+
+  /// Requests that this window be captured once the next [`EngineEventHandler::render`] call has been flushed,
+  /// which will be delivered to [`EngineEventHandler::on_screenshot_captured`]. This also requests a redraw of the window.
+  pub fn request_screenshot(&self) {
+    let Some(requests) = self.requests else { return };
+    let window_id = self.window_state.id();
+    let mut screenshots = requests.screenshots.borrow_mut();
+    if !screenshots.contains(&window_id) {
+      screenshots.push(window_id);
+    };
+
+    self.window_state.request_redraw();
+  }
+
+  /// Requests that the vsync mode of all windows be changed once the current callback returns.
+  /// If the platform refuses the new mode, a warning is logged and the previous swap interval is kept.
+  pub fn set_vsync(&self, vsync: VSync) {
+    if let Some(requests) = self.requests {
+      requests.vsync.set(Some(vsync));
+    };
+  }
+
+  /// Requests that this window start being recorded once the current callback returns, replacing any active recording.
+  /// While recording, each frame advances time by [`FrameRecorder::frame_duration`] rather than real time.
+  /// The outcome is delivered to [`EngineEventHandler::on_recording_finished`] once the recording stops,
+  /// or immediately if it could not be started.
+  pub fn start_recording(&self, recorder: FrameRecorder) {
+    let Some(requests) = self.requests else { return };
+    let request = RecordingRequest::Start(Box::new(recorder), self.window_state.id());
+    *requests.recording.borrow_mut() = Some(request);
+  }
+
+  /// Requests that the active recording be stopped once the current callback returns.
+  pub fn stop_recording(&self) {
+    if let Some(requests) = self.requests {
+      *requests.recording.borrow_mut() = Some(RecordingRequest::Stop);
+    };
+  }
+
+  /// Returns a proxy for sending user events to the engine from other threads, such as from worker jobs started with
+  /// [`EngineProxy::spawn`]. Returns `None` if this context is not attached to a running engine.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `T` is not the engine's user event type.
+  pub fn proxy<T: 'static>(&self) -> Option<EngineProxy<T>> {
+    let proxy = self.requests?.proxy.as_ref()?;
+    let proxy = proxy.downcast_ref::<EngineProxy<T>>()
+      .unwrap_or_else(|| panic!("`{}` is not the engine's user event type", std::any::type_name::<T>()));
+    Some(proxy.clone())
+  }
+
+  /// Whether any window is currently being recorded, not accounting for requests made during the current callback.
+  pub fn is_recording(&self) -> bool {
+    self.requests.is_some_and(|requests| requests.recording_window_id.get().is_some())
+  }
 }
 
 impl<'a> fmt::Debug for EngineContext<'a> {
@@ -321,6 +1141,7 @@ impl<'a> fmt::Debug for EngineContext<'a> {
     f.debug_struct("EngineContext")
       .field("window_state", &self.window_state)
       .field("canvas", &format_args!("Canvas"))
+      .field("requests", &self.requests)
       .finish()
   }
 }
@@ -333,16 +1154,30 @@ pub trait EngineEventHandler<T: 'static = ()>: Sized + 'static {
   fn update(&mut self, context: EngineContext);
   /// See [`EventHandler::render`].
   fn render(&mut self, context: EngineContext);
+  /// Called once a screenshot requested with [`EngineContext::request_screenshot`] has been captured,
+  /// just after the frame has been flushed and before it is presented.
+  fn on_screenshot_captured(&mut self, context: EngineContext, screenshot: Result<RgbaImage, femtovg::ErrorKind>) {}
+  /// Called once a recording has stopped and all of its frames have been written, with the number of frames captured.
+  /// Recordings stop when requested, when the recorded window closes, or when the engine exits.
+  fn on_recording_finished(&mut self, result: Result<u64, RecordingError>) {}
+  /// See [`EventHandler::on_ticks_dropped`].
+  fn on_ticks_dropped(&mut self, context: EngineContext, count: u32) {}
   /// See [`EventHandler::on_user_event`].
   fn on_user_event(&mut self, context: EngineContext, event: T) {}
   /// See [`EventHandler::on_device_event`].
   fn on_device_event(&mut self, context: EngineContext, id: DeviceId, event: DeviceEvent) {}
   /// See [`EventHandler::on_keyboard_input`].
   fn on_keyboard_input(&mut self, context: EngineContext, event: KeyEvent) {}
+  /// See [`EventHandler::on_synthetic_keyboard_input`].
+  fn on_synthetic_keyboard_input(&mut self, context: EngineContext, event: SyntheticKeyEvent) {}
   /// See [`EventHandler::on_text_input`].
   fn on_text_input(&mut self, context: EngineContext, event: Ime) {}
   /// See [`EventHandler::on_cursor_moved`].
   fn on_cursor_moved(&mut self, context: EngineContext, pos: PhysicalPosition<f32>) {}
+  /// See [`EventHandler::on_cursor_entered`].
+  fn on_cursor_entered(&mut self, context: EngineContext) {}
+  /// See [`EventHandler::on_cursor_left`].
+  fn on_cursor_left(&mut self, context: EngineContext) {}
   /// See [`EventHandler::on_mouse_input`].
   fn on_mouse_input(&mut self, context: EngineContext, state: ElementState, button: MouseButton) {}
   /// See [`EventHandler::on_mouse_scroll`].
@@ -353,12 +1188,26 @@ pub trait EngineEventHandler<T: 'static = ()>: Sized + 'static {
   fn on_touch(&mut self, context: EngineContext, touch: Touch) {}
   /// See [`EventHandler::on_axis_motion`].
   fn on_axis_motion(&mut self, context: EngineContext, axis_motion: AxisMotion) {}
+  /// See [`EventHandler::on_gamepad_connected`].
+  fn on_gamepad_connected(&mut self, context: EngineContext, id: GamepadId) {}
+  /// See [`EventHandler::on_gamepad_disconnected`].
+  fn on_gamepad_disconnected(&mut self, context: EngineContext, id: GamepadId) {}
+  /// See [`EventHandler::on_gamepad_button`].
+  fn on_gamepad_button(&mut self, context: EngineContext, id: GamepadId, button: GamepadButton, state: ElementState) {}
+  /// See [`EventHandler::on_gamepad_axis`].
+  fn on_gamepad_axis(&mut self, context: EngineContext, id: GamepadId, axis: GamepadAxis, value: f32) {}
   /// See [`EventHandler::on_focus_changed`].
   fn on_focus_changed(&mut self, context: EngineContext, state: bool) {}
   /// See [`EventHandler::on_occlusion_changed`].
   fn on_occlusion_changed(&mut self, context: EngineContext, state: bool) {}
-  /// See [`EventHandler::on_file_dropped`].
+  /// See [`EventHandler::on_file_over`].
   fn on_file_over(&mut self, context: EngineContext, path: Option<PathBuf>, dropped: bool) {}
+  /// See [`EventHandler::on_window_opened`].
+  fn on_window_opened(&mut self, context: EngineContext) {}
+  /// See [`EventHandler::on_window_open_failed`].
+  fn on_window_open_failed(&mut self, error: Box<dyn Error>) {}
+  /// See [`EventHandler::on_window_closed`].
+  fn on_window_closed(&mut self, context: EngineContext) {}
   /// See [`EventHandler::on_resized`].
   fn on_resized(&mut self, context: EngineContext, window_size: PhysicalSize<u32>, scale_factor: f64) {}
   /// See [`EventHandler::on_resumed`].
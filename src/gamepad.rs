@@ -0,0 +1,521 @@
+//! Gamepad input, read from a [`GamepadSource`] once per frame and tracked by [`InputState`][crate::windowing::InputState].
+//!
+#![cfg_attr(feature = "gamepad", doc = "With the `gamepad` feature, [`GilrsSource`] reads connected gamepads using [`gilrs`],")]
+#![cfg_attr(not(feature = "gamepad"), doc = "With the `gamepad` feature, `GilrsSource` reads connected gamepads using `gilrs`,")]
+//! while [`FakeGamepadSource`] can be used to simulate gamepads, such as in tests or with a headless engine.
+
+use ahash::AHashSet;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use winit::event::ElementState;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::Rc;
+
+
+
+/// Identifies a gamepad for as long as it stays connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GamepadId(pub usize);
+
+/// The buttons of a gamepad, named after their position on a standard controller layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GamepadButton {
+  South,
+  East,
+  North,
+  West,
+  LeftBumper,
+  RightBumper,
+  LeftTrigger,
+  RightTrigger,
+  Select,
+  Start,
+  Mode,
+  LeftThumb,
+  RightThumb,
+  DPadUp,
+  DPadDown,
+  DPadLeft,
+  DPadRight
+}
+
+/// The analog axes of a gamepad. Stick axes range from `-1.0` to `1.0`, with positive Y pointing down,
+/// while trigger axes range from `0.0` to `1.0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GamepadAxis {
+  LeftStickX,
+  LeftStickY,
+  RightStickX,
+  RightStickY,
+  LeftTrigger,
+  RightTrigger
+}
+
+impl GamepadAxis {
+  const COUNT: usize = 6;
+
+  #[inline]
+  pub const fn is_stick(self) -> bool {
+    !self.is_trigger()
+  }
+
+  #[inline]
+  pub const fn is_trigger(self) -> bool {
+    matches!(self, GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger)
+  }
+
+  /// The other axis of the same stick, if this is a stick axis.
+  const fn stick_partner(self) -> Option<Self> {
+    match self {
+      GamepadAxis::LeftStickX => Some(GamepadAxis::LeftStickY),
+      GamepadAxis::LeftStickY => Some(GamepadAxis::LeftStickX),
+      GamepadAxis::RightStickX => Some(GamepadAxis::RightStickY),
+      GamepadAxis::RightStickY => Some(GamepadAxis::RightStickX),
+      GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => None
+    }
+  }
+}
+
+/// A change to a gamepad, reported by a [`GamepadSource`]. See [`InputState::gamepad_events`][crate::windowing::InputState::gamepad_events].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GamepadEvent {
+  Connected {
+    id: GamepadId,
+    name: String
+  },
+  Disconnected {
+    id: GamepadId
+  },
+  Button {
+    id: GamepadId,
+    button: GamepadButton,
+    state: ElementState
+  },
+  /// The raw value of an axis, before any deadzone is applied.
+  Axis {
+    id: GamepadId,
+    axis: GamepadAxis,
+    value: f32
+  }
+}
+
+impl GamepadEvent {
+  pub const fn id(&self) -> GamepadId {
+    match *self {
+      GamepadEvent::Connected { id, .. } => id,
+      GamepadEvent::Disconnected { id } => id,
+      GamepadEvent::Button { id, .. } => id,
+      GamepadEvent::Axis { id, .. } => id
+    }
+  }
+}
+
+/// Provides gamepad events to an [`Application`][crate::windowing::Application], which polls it once per frame.
+pub trait GamepadSource: fmt::Debug + 'static {
+  /// Appends every gamepad event that has happened since the previous poll to `events`.
+  fn poll(&mut self, events: &mut Vec<GamepadEvent>);
+}
+
+/// A gamepad source that reports only the events pushed into it. Clones share the same queue of events,
+/// so a clone can be kept to simulate gamepads after the source has been given to an engine.
+#[derive(Debug, Clone, Default)]
+pub struct FakeGamepadSource {
+  events: Rc<RefCell<VecDeque<GamepadEvent>>>
+}
+
+impl FakeGamepadSource {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Queues an event to be reported by the next poll.
+  pub fn push(&self, event: GamepadEvent) {
+    self.events.borrow_mut().push_back(event);
+  }
+
+  pub fn connect(&self, id: GamepadId, name: impl Into<String>) {
+    self.push(GamepadEvent::Connected { id, name: name.into() });
+  }
+
+  pub fn disconnect(&self, id: GamepadId) {
+    self.push(GamepadEvent::Disconnected { id });
+  }
+
+  pub fn press(&self, id: GamepadId, button: GamepadButton) {
+    self.push(GamepadEvent::Button { id, button, state: ElementState::Pressed });
+  }
+
+  pub fn release(&self, id: GamepadId, button: GamepadButton) {
+    self.push(GamepadEvent::Button { id, button, state: ElementState::Released });
+  }
+
+  pub fn set_axis(&self, id: GamepadId, axis: GamepadAxis, value: f32) {
+    self.push(GamepadEvent::Axis { id, axis, value });
+  }
+}
+
+impl GamepadSource for FakeGamepadSource {
+  fn poll(&mut self, events: &mut Vec<GamepadEvent>) {
+    events.extend(self.events.borrow_mut().drain(..));
+  }
+}
+
+/// A gamepad source which reads connected gamepads using [`gilrs`], such as through evdev on Linux.
+#[cfg(feature = "gamepad")]
+#[derive(Debug)]
+pub struct GilrsSource {
+  gilrs: gilrs::Gilrs,
+  connected: Vec<GamepadEvent>
+}
+
+#[cfg(feature = "gamepad")]
+impl GilrsSource {
+  #[allow(clippy::result_large_err)]
+  pub fn new() -> Result<Self, gilrs::Error> {
+    gilrs::Gilrs::new().map(Self::from_gilrs)
+  }
+
+  /// Creates a gamepad source from a [`gilrs::Gilrs`] that has been configured with [`gilrs::GilrsBuilder`].
+  pub fn from_gilrs(gilrs: gilrs::Gilrs) -> Self {
+    // gamepads that were connected before the source was created are reported by the first poll
+    let connected = gilrs.gamepads()
+      .map(|(id, gamepad)| GamepadEvent::Connected { id: GamepadId(id.into()), name: gamepad.name().to_owned() })
+      .collect();
+    GilrsSource { gilrs, connected }
+  }
+
+  #[inline]
+  pub fn gilrs(&self) -> &gilrs::Gilrs {
+    &self.gilrs
+  }
+
+  #[inline]
+  pub fn gilrs_mut(&mut self) -> &mut gilrs::Gilrs {
+    &mut self.gilrs
+  }
+}
+
+#[cfg(feature = "gamepad")]
+impl GamepadSource for GilrsSource {
+  fn poll(&mut self, events: &mut Vec<GamepadEvent>) {
+    use gilrs::{Axis, Button, EventType};
+
+    events.append(&mut self.connected);
+    while let Some(gilrs::Event { id: gilrs_id, event, .. }) = self.gilrs.next_event() {
+      let id = GamepadId(gilrs_id.into());
+      events.push(match event {
+        EventType::Connected => GamepadEvent::Connected { id, name: self.gilrs.gamepad(gilrs_id).name().to_owned() },
+        EventType::Disconnected => GamepadEvent::Disconnected { id },
+        EventType::ButtonPressed(button, _) | EventType::ButtonReleased(button, _) => {
+          let Some(button) = gilrs_button(button) else { continue };
+          let state = if matches!(event, EventType::ButtonPressed(..)) { ElementState::Pressed } else { ElementState::Released };
+          GamepadEvent::Button { id, button, state }
+        },
+        // analog triggers are reported as button values rather than axes
+        EventType::ButtonChanged(Button::LeftTrigger2, value, _) => GamepadEvent::Axis { id, axis: GamepadAxis::LeftTrigger, value },
+        EventType::ButtonChanged(Button::RightTrigger2, value, _) => GamepadEvent::Axis { id, axis: GamepadAxis::RightTrigger, value },
+        EventType::AxisChanged(axis, value, _) => match axis {
+          Axis::LeftStickX => GamepadEvent::Axis { id, axis: GamepadAxis::LeftStickX, value },
+          Axis::LeftStickY => GamepadEvent::Axis { id, axis: GamepadAxis::LeftStickY, value: -value },
+          Axis::RightStickX => GamepadEvent::Axis { id, axis: GamepadAxis::RightStickX, value },
+          Axis::RightStickY => GamepadEvent::Axis { id, axis: GamepadAxis::RightStickY, value: -value },
+          _ => continue
+        },
+        _ => continue
+      });
+    };
+
+    self.gilrs.inc();
+  }
+}
+
+#[cfg(feature = "gamepad")]
+fn gilrs_button(button: gilrs::Button) -> Option<GamepadButton> {
+  use gilrs::Button;
+
+  Some(match button {
+    Button::South => GamepadButton::South,
+    Button::East => GamepadButton::East,
+    Button::North => GamepadButton::North,
+    Button::West => GamepadButton::West,
+    Button::LeftTrigger => GamepadButton::LeftBumper,
+    Button::RightTrigger => GamepadButton::RightBumper,
+    Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+    Button::RightTrigger2 => GamepadButton::RightTrigger,
+    Button::Select => GamepadButton::Select,
+    Button::Start => GamepadButton::Start,
+    Button::Mode => GamepadButton::Mode,
+    Button::LeftThumb => GamepadButton::LeftThumb,
+    Button::RightThumb => GamepadButton::RightThumb,
+    Button::DPadUp => GamepadButton::DPadUp,
+    Button::DPadDown => GamepadButton::DPadDown,
+    Button::DPadLeft => GamepadButton::DPadLeft,
+    Button::DPadRight => GamepadButton::DPadRight,
+    Button::C | Button::Z | Button::Unknown => return None
+  })
+}
+
+/// Deadzones applied to gamepad axes. See [`WindowState::set_gamepad_config`][crate::windowing::WindowState::set_gamepad_config].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct GamepadConfig {
+  /// Sticks whose distance from the center is within this radius read as centered.
+  /// Beyond it, values are rescaled so that they still cover the full range.
+  pub stick_deadzone: f32,
+  /// Triggers pulled no further than this read as released.
+  pub trigger_deadzone: f32
+}
+
+impl Default for GamepadConfig {
+  fn default() -> Self {
+    GamepadConfig {
+      stick_deadzone: 0.15,
+      trigger_deadzone: 0.05
+    }
+  }
+}
+
+/// The state of a connected gamepad. See [`InputState::gamepad`][crate::windowing::InputState::gamepad].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GamepadState {
+  name: String,
+  buttons_held: AHashSet<GamepadButton>,
+  buttons_pressed: Vec<GamepadButton>,
+  buttons_released: Vec<GamepadButton>,
+  axes_raw: [f32; GamepadAxis::COUNT],
+  axes: [f32; GamepadAxis::COUNT]
+}
+
+impl GamepadState {
+  pub(crate) fn new(name: String) -> Self {
+    GamepadState {
+      name,
+      buttons_held: AHashSet::new(),
+      buttons_pressed: Vec::new(),
+      buttons_released: Vec::new(),
+      axes_raw: [0.0; GamepadAxis::COUNT],
+      axes: [0.0; GamepadAxis::COUNT]
+    }
+  }
+
+  /// The name of the gamepad, as reported by the platform.
+  #[inline]
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  #[inline]
+  pub fn is_button_held(&self, button: GamepadButton) -> bool {
+    self.buttons_held.contains(&button)
+  }
+
+  /// Returns every button that is currently held, in no particular order.
+  pub fn buttons_held(&self) -> impl Iterator<Item = GamepadButton> + '_ {
+    self.buttons_held.iter().copied()
+  }
+
+  /// Checks whether the given button was pressed during the current frame.
+  #[inline]
+  pub fn was_button_pressed(&self, button: GamepadButton) -> bool {
+    self.buttons_pressed.contains(&button)
+  }
+
+  /// Checks whether the given button was released during the current frame.
+  #[inline]
+  pub fn was_button_released(&self, button: GamepadButton) -> bool {
+    self.buttons_released.contains(&button)
+  }
+
+  /// The value of the given axis, with its deadzone applied.
+  #[inline]
+  pub fn axis(&self, axis: GamepadAxis) -> f32 {
+    self.axes[axis as usize]
+  }
+
+  /// The value of the given axis as reported by the gamepad, without any deadzone applied.
+  #[inline]
+  pub fn axis_raw(&self, axis: GamepadAxis) -> f32 {
+    self.axes_raw[axis as usize]
+  }
+
+  /// The position of the left stick, with its deadzone applied.
+  #[inline]
+  pub fn left_stick(&self) -> [f32; 2] {
+    [self.axis(GamepadAxis::LeftStickX), self.axis(GamepadAxis::LeftStickY)]
+  }
+
+  /// The position of the right stick, with its deadzone applied.
+  #[inline]
+  pub fn right_stick(&self) -> [f32; 2] {
+    [self.axis(GamepadAxis::RightStickX), self.axis(GamepadAxis::RightStickY)]
+  }
+
+  pub(crate) fn reset(&mut self) {
+    self.buttons_pressed.clear();
+    self.buttons_released.clear();
+  }
+
+  pub(crate) fn handle_button(&mut self, button: GamepadButton, state: ElementState) {
+    match state {
+      ElementState::Pressed => if self.buttons_held.insert(button) {
+        self.buttons_pressed.push(button);
+      },
+      ElementState::Released => if self.buttons_held.remove(&button) {
+        self.buttons_released.push(button);
+      }
+    };
+  }
+
+  pub(crate) fn handle_axis(&mut self, axis: GamepadAxis, value: f32, gamepad_config: &GamepadConfig) {
+    self.axes_raw[axis as usize] = value;
+    if let Some(partner) = axis.stick_partner() {
+      // the deadzone of a stick is radial, so both of its axes are updated together
+      let (x, y) = (self.axes_raw[axis as usize], self.axes_raw[partner as usize]);
+      let scale = apply_deadzone(x.hypot(y), gamepad_config.stick_deadzone) / x.hypot(y).max(f32::EPSILON);
+      self.axes[axis as usize] = x * scale;
+      self.axes[partner as usize] = y * scale;
+    } else {
+      self.axes[axis as usize] = apply_deadzone(value, gamepad_config.trigger_deadzone);
+    };
+  }
+}
+
+/// Maps a magnitude from `deadzone..=1` onto `0..=1`, clamping anything within the deadzone to zero.
+fn apply_deadzone(magnitude: f32, deadzone: f32) -> f32 {
+  if magnitude <= deadzone { return 0.0 };
+  ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use winit::window::Window;
+
+  use crate::windowing::tests::{headless_window_state, NullHandler};
+  use crate::windowing::{InputEvent, InputState, WindowState};
+
+  use super::*;
+
+  const ID: GamepadId = GamepadId(0);
+
+  /// Runs a frame in which every event queued in the source is delivered to the window.
+  fn frame<'a>(window_state: &'a mut WindowState<Window>, gamepad_source: &mut FakeGamepadSource) -> &'a InputState {
+    window_state.reset();
+    let mut events = Vec::new();
+    gamepad_source.poll(&mut events);
+    for event in events {
+      window_state.handle_input_event(&mut NullHandler, InputEvent::Gamepad(event));
+    };
+
+    window_state.input()
+  }
+
+  #[test]
+  fn fake_sources_share_their_events_with_clones() {
+    let mut gamepad_source = FakeGamepadSource::new();
+    gamepad_source.clone().connect(ID, "Gamepad");
+    gamepad_source.clone().press(ID, GamepadButton::South);
+
+    let mut events = Vec::new();
+    gamepad_source.poll(&mut events);
+    assert_eq!(events, [
+      GamepadEvent::Connected { id: ID, name: "Gamepad".to_owned() },
+      GamepadEvent::Button { id: ID, button: GamepadButton::South, state: ElementState::Pressed }
+    ]);
+
+    events.clear();
+    gamepad_source.poll(&mut events);
+    assert!(events.is_empty());
+  }
+
+  #[test]
+  fn gamepads_connect_and_disconnect() {
+    let mut window_state = headless_window_state();
+    let mut gamepad_source = FakeGamepadSource::new();
+    gamepad_source.connect(ID, "Gamepad");
+    gamepad_source.connect(GamepadId(1), "Other");
+    let input = frame(&mut window_state, &mut gamepad_source);
+    assert_eq!(input.gamepad(ID).map(GamepadState::name), Some("Gamepad"));
+    assert_eq!(input.gamepads().count(), 2);
+
+    gamepad_source.disconnect(ID);
+    let input = frame(&mut window_state, &mut gamepad_source);
+    assert!(input.gamepad(ID).is_none());
+    assert_eq!(input.gamepads().map(|(id, _)| id).collect::<Vec<_>>(), [GamepadId(1)]);
+    assert_eq!(input.gamepad_events(), [GamepadEvent::Disconnected { id: ID }]);
+
+    // input from gamepads which aren't connected is ignored
+    gamepad_source.press(ID, GamepadButton::South);
+    gamepad_source.set_axis(GamepadId(2), GamepadAxis::LeftStickX, 1.0);
+    let input = frame(&mut window_state, &mut gamepad_source);
+    assert_eq!(input.gamepads().map(|(id, _)| id).collect::<Vec<_>>(), [GamepadId(1)]);
+    assert!(input.gamepad_events().is_empty());
+  }
+
+  #[test]
+  fn buttons_are_pressed_and_released_for_a_single_frame() {
+    let mut window_state = headless_window_state();
+    let mut gamepad_source = FakeGamepadSource::new();
+    gamepad_source.connect(ID, "Gamepad");
+    gamepad_source.press(ID, GamepadButton::South);
+    let gamepad = frame(&mut window_state, &mut gamepad_source).gamepad(ID).unwrap();
+    assert!(gamepad.was_button_pressed(GamepadButton::South) && gamepad.is_button_held(GamepadButton::South));
+
+    let gamepad = frame(&mut window_state, &mut gamepad_source).gamepad(ID).unwrap();
+    assert!(!gamepad.was_button_pressed(GamepadButton::South) && gamepad.is_button_held(GamepadButton::South));
+
+    // repeated presses of a held button are ignored
+    gamepad_source.press(ID, GamepadButton::South);
+    gamepad_source.release(ID, GamepadButton::South);
+    let gamepad = frame(&mut window_state, &mut gamepad_source).gamepad(ID).unwrap();
+    assert!(!gamepad.was_button_pressed(GamepadButton::South) && gamepad.was_button_released(GamepadButton::South));
+    assert!(!gamepad.is_button_held(GamepadButton::South));
+
+    let gamepad = frame(&mut window_state, &mut gamepad_source).gamepad(ID).unwrap();
+    assert!(!gamepad.was_button_released(GamepadButton::South));
+  }
+
+  #[test]
+  fn deadzones_rescale_the_remaining_range() {
+    assert_eq!(apply_deadzone(0.1, 0.2), 0.0);
+    assert_eq!(apply_deadzone(0.2, 0.2), 0.0);
+    assert!((apply_deadzone(0.6, 0.2) - 0.5).abs() < 1e-6);
+    assert_eq!(apply_deadzone(1.0, 0.2), 1.0);
+    assert_eq!(apply_deadzone(1.5, 0.2), 1.0);
+  }
+
+  #[test]
+  fn stick_deadzones_are_radial() {
+    let gamepad_config = GamepadConfig { stick_deadzone: 0.2, trigger_deadzone: 0.1 };
+    let mut gamepad = GamepadState::new(String::new());
+
+    // each axis is within the deadzone on its own, but not together
+    gamepad.handle_axis(GamepadAxis::LeftStickX, 0.18, &gamepad_config);
+    assert_eq!(gamepad.left_stick(), [0.0, 0.0]);
+    gamepad.handle_axis(GamepadAxis::LeftStickY, 0.18, &gamepad_config);
+    let [x, y] = gamepad.left_stick();
+    assert!(x > 0.0 && (x - y).abs() < 1e-6);
+    assert_eq!(gamepad.axis_raw(GamepadAxis::LeftStickY), 0.18);
+
+    // the direction of the stick is preserved when rescaled
+    gamepad.handle_axis(GamepadAxis::LeftStickX, 0.6, &gamepad_config);
+    gamepad.handle_axis(GamepadAxis::LeftStickY, -0.8, &gamepad_config);
+    let [x, y] = gamepad.left_stick();
+    assert!((x - 0.6).abs() < 1e-6 && (y + 0.8).abs() < 1e-6);
+    assert_eq!(gamepad.right_stick(), [0.0, 0.0]);
+
+    gamepad.handle_axis(GamepadAxis::LeftTrigger, 0.05, &gamepad_config);
+    assert_eq!(gamepad.axis(GamepadAxis::LeftTrigger), 0.0);
+    gamepad.handle_axis(GamepadAxis::LeftTrigger, 0.55, &gamepad_config);
+    assert!((gamepad.axis(GamepadAxis::LeftTrigger) - 0.5).abs() < 1e-6);
+  }
+}
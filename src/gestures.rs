@@ -0,0 +1,457 @@
+//! Recognition of gestures from touch and mouse input, for platforms which do not report gestures themselves.
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use winit::dpi::PhysicalPosition;
+use winit::event::{DeviceId, ElementState, MouseButton, TouchPhase};
+
+use crate::misc::OptionExt;
+use crate::windowing::{Gesture, InputState, SwipeDirection, TouchPoint};
+
+use std::time::{Duration, Instant};
+
+
+
+/// Thresholds used by a [`GestureRecognizer`]. Distances are in logical pixels and velocities in logical pixels per second,
+/// so they are scaled by the scale factor of the window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct GestureConfig {
+  /// The longest a pointer may be held down for its release to count as a tap.
+  pub tap_max_duration: Duration,
+  /// The farthest a pointer may move while held down for its release to count as a tap or for it to long-press.
+  pub tap_max_distance: f64,
+  /// The longest time between two taps for the second one to count as a double tap.
+  pub double_tap_max_interval: Duration,
+  /// The farthest apart two taps may be for the second one to count as a double tap.
+  pub double_tap_max_distance: f64,
+  /// How long a pointer must be held down without moving to long-press.
+  pub long_press_duration: Duration,
+  /// How far a pointer must move while held down before it starts panning.
+  pub pan_min_distance: f64,
+  /// The shortest distance a pointer must travel between being pressed and released to count as a swipe.
+  pub swipe_min_distance: f64,
+  /// The lowest average velocity a pointer must travel at between being pressed and released to count as a swipe.
+  pub swipe_min_velocity: f64,
+  /// Whether the left mouse button acts as a single finger while no touches are active.
+  pub mouse_enabled: bool
+}
+
+impl Default for GestureConfig {
+  fn default() -> Self {
+    GestureConfig {
+      tap_max_duration: Duration::from_millis(300),
+      tap_max_distance: 10.0,
+      double_tap_max_interval: Duration::from_millis(300),
+      double_tap_max_distance: 20.0,
+      long_press_duration: Duration::from_millis(500),
+      pan_min_distance: 10.0,
+      swipe_min_distance: 50.0,
+      swipe_min_velocity: 300.0,
+      mouse_enabled: true
+    }
+  }
+}
+
+/// Synthesizes [`Gesture`]s from the touches and mouse input tracked by an [`InputState`], once per frame.
+///
+/// A single finger (or the left mouse button) produces [`Gesture::Tap`], [`Gesture::DoubleTap`], [`Gesture::LongPress`],
+/// [`Gesture::Swipe`] and [`Gesture::Pan`], while two fingers produce [`Gesture::Pan`], [`Gesture::Pinch`] and [`Gesture::Rotation`].
+///
+/// Long presses are only recognized when a frame runs, so they may be delayed while waiting for events
+/// with [`RedrawMode::OnDemand`][crate::windowing::RedrawMode::OnDemand].
+#[derive(Debug, Clone, Copy)]
+pub struct GestureRecognizer {
+  config: GestureConfig,
+  pointer: Option<Pointer>,
+  multi_touch: Option<MultiTouch>,
+  last_tap: Option<(Instant, PhysicalPosition<f64>)>
+}
+
+impl GestureRecognizer {
+  pub fn new(config: GestureConfig) -> Self {
+    GestureRecognizer { config, pointer: None, multi_touch: None, last_tap: None }
+  }
+
+  #[inline]
+  pub fn config(&self) -> &GestureConfig {
+    &self.config
+  }
+
+  #[inline]
+  pub fn set_config(&mut self, config: GestureConfig) {
+    self.config = config;
+  }
+
+  /// Forgets any gestures in progress, without reporting them as ended.
+  pub fn reset(&mut self) {
+    self.pointer = None;
+    self.multi_touch = None;
+    self.last_tap = None;
+  }
+
+  /// Recognizes the gestures made during the current frame, which started at `now`.
+  pub fn recognize(&mut self, input: &InputState, now: Instant, scale_factor: f64) -> Vec<Gesture> {
+    let mut gestures = Vec::new();
+    self.recognize_multi_touch(input, &mut gestures);
+    self.recognize_pointer(input, now, scale_factor, &mut gestures);
+    gestures
+  }
+
+  fn recognize_multi_touch(&mut self, input: &InputState, gestures: &mut Vec<Gesture>) {
+    let mut ids = input.touch_points().map(|touch_point| touch_point.id).collect::<Vec<u64>>();
+    ids.sort_unstable();
+
+    let multi_touch = match *ids.as_slice() {
+      [first, second, ..] => Some(MultiTouch::new(
+        input.touch_point(first).unwrap_unreachable(),
+        input.touch_point(second).unwrap_unreachable()
+      )),
+      _ => None
+    };
+
+    match (self.multi_touch, multi_touch) {
+      (Some(prev), Some(current)) if prev.ids == current.ids => {
+        let device_id = current.device_id;
+        let delta = PhysicalPosition::new(current.centroid.x - prev.centroid.x, current.centroid.y - prev.centroid.y);
+        if delta.x != 0.0 || delta.y != 0.0 {
+          gestures.push(Gesture::Pan { device_id, delta: delta.cast(), phase: TouchPhase::Moved });
+        };
+
+        if prev.distance > 0.0 && current.distance != prev.distance {
+          let delta = current.distance / prev.distance - 1.0;
+          gestures.push(Gesture::Pinch { device_id, delta, phase: TouchPhase::Moved });
+        };
+
+        // angles increase clockwise on screen, while rotation is counterclockwise
+        let delta = (prev.angle - current.angle + 180.0).rem_euclid(360.0) - 180.0;
+        if delta != 0.0 {
+          gestures.push(Gesture::Rotation { device_id, delta: delta as f32, phase: TouchPhase::Moved });
+        };
+      },
+      (prev, current) => {
+        if let Some(prev) = prev {
+          prev.push_phase(TouchPhase::Ended, gestures);
+        };
+
+        if let Some(current) = current {
+          current.push_phase(TouchPhase::Started, gestures);
+        };
+      }
+    };
+
+    self.multi_touch = multi_touch;
+  }
+
+  fn recognize_pointer(&mut self, input: &InputState, now: Instant, scale_factor: f64, gestures: &mut Vec<Gesture>) {
+    if self.pointer.is_none() && let Some(&id) = input.touches_started().first() {
+      let touch_point = input.touch_point(id)
+        .or_else(|| input.touches_ended().iter().find(|touch_point| touch_point.id == id))
+        .unwrap_unreachable();
+      self.pointer = Some(Pointer::new(PointerId::Touch(id), touch_point.device_id, touch_point.position, now));
+    };
+
+    if let Some(Pointer { id: PointerId::Touch(id), .. }) = self.pointer {
+      if input.touch_count() > 1 {
+        self.cancel_pointer(gestures);
+      };
+
+      if let Some(touch_point) = input.touch_point(id) {
+        self.move_pointer(touch_point.position, now, scale_factor, gestures);
+      } else if let Some(touch_point) = input.touches_ended().iter().find(|touch_point| touch_point.id == id) {
+        if touch_point.phase == TouchPhase::Cancelled {
+          self.cancel_pointer(gestures);
+        };
+
+        self.release_pointer(touch_point.position, now, scale_factor, gestures);
+      } else {
+        self.pointer = None;
+      };
+    };
+
+    if !self.config.mouse_enabled { return };
+    let mouse_actions = input.mouse_actions().iter()
+      .filter(|mouse_action| mouse_action.button == MouseButton::Left);
+    for mouse_action in mouse_actions {
+      match mouse_action.state {
+        ElementState::Pressed => if self.pointer.is_none() && input.touch_count() == 0 && let Some(position) = input.cursor_pos() {
+          self.pointer = Some(Pointer::new(PointerId::Mouse, DeviceId::dummy(), position.cast(), now));
+        },
+        ElementState::Released => if let Some(Pointer { id: PointerId::Mouse, position, .. }) = self.pointer {
+          let position = input.cursor_pos().map_or(position, |position| position.cast());
+          self.release_pointer(position, now, scale_factor, gestures);
+        }
+      };
+    };
+
+    if let Some(Pointer { id: PointerId::Mouse, .. }) = self.pointer && let Some(position) = input.cursor_pos() {
+      self.move_pointer(position.cast(), now, scale_factor, gestures);
+    };
+  }
+
+  /// Stops the current pointer from producing any more gestures until it is released.
+  fn cancel_pointer(&mut self, gestures: &mut Vec<Gesture>) {
+    let Some(pointer) = &mut self.pointer else { return };
+    if pointer.panning {
+      gestures.push(Gesture::Pan { device_id: pointer.device_id, delta: PhysicalPosition::new(0.0, 0.0), phase: TouchPhase::Cancelled });
+      pointer.panning = false;
+    };
+
+    pointer.cancelled = true;
+  }
+
+  fn move_pointer(&mut self, position: PhysicalPosition<f64>, now: Instant, scale_factor: f64, gestures: &mut Vec<Gesture>) {
+    let config = self.config;
+    let Some(pointer) = &mut self.pointer else { return };
+    let delta = PhysicalPosition::new(position.x - pointer.position.x, position.y - pointer.position.y);
+    pointer.position = position;
+    if pointer.cancelled { return };
+
+    let distance = pointer.distance();
+    if pointer.panning {
+      if delta.x != 0.0 || delta.y != 0.0 {
+        gestures.push(Gesture::Pan { device_id: pointer.device_id, delta: delta.cast(), phase: TouchPhase::Moved });
+      };
+    } else if distance > config.pan_min_distance * scale_factor {
+      // the movement made before panning started is included, so that none of it is lost
+      let delta = PhysicalPosition::new(position.x - pointer.start_position.x, position.y - pointer.start_position.y);
+      gestures.push(Gesture::Pan { device_id: pointer.device_id, delta: delta.cast(), phase: TouchPhase::Started });
+      pointer.panning = true;
+    };
+
+    if !pointer.panning && !pointer.long_pressed && distance <= config.tap_max_distance * scale_factor
+      && now.duration_since(pointer.started_at) >= config.long_press_duration {
+      gestures.push(Gesture::LongPress { device_id: pointer.device_id, position: pointer.start_position });
+      pointer.long_pressed = true;
+    };
+  }
+
+  fn release_pointer(&mut self, position: PhysicalPosition<f64>, now: Instant, scale_factor: f64, gestures: &mut Vec<Gesture>) {
+    self.move_pointer(position, now, scale_factor, gestures);
+    let config = self.config;
+    let Some(pointer) = self.pointer.take() else { return };
+    let device_id = pointer.device_id;
+    if pointer.panning {
+      gestures.push(Gesture::Pan { device_id, delta: PhysicalPosition::new(0.0, 0.0), phase: TouchPhase::Ended });
+    };
+
+    if pointer.cancelled { return };
+
+    let duration = now.duration_since(pointer.started_at);
+    let distance = pointer.distance();
+    if !pointer.long_pressed && duration <= config.tap_max_duration && distance <= config.tap_max_distance * scale_factor {
+      gestures.push(Gesture::Tap { device_id, position: pointer.start_position });
+
+      let is_double_tap = self.last_tap.is_some_and(|(last_tap_instant, last_tap_position)| {
+        let distance = (pointer.start_position.x - last_tap_position.x).hypot(pointer.start_position.y - last_tap_position.y);
+        pointer.started_at.duration_since(last_tap_instant) <= config.double_tap_max_interval
+          && distance <= config.double_tap_max_distance * scale_factor
+      });
+
+      if is_double_tap {
+        gestures.push(Gesture::DoubleTap { device_id });
+        self.last_tap = None;
+      } else {
+        self.last_tap = Some((now, pointer.start_position));
+      };
+    } else if distance >= config.swipe_min_distance * scale_factor {
+      // a pointer pressed and released within the same frame is treated as having moved very quickly
+      let seconds = duration.as_secs_f64().max(f64::EPSILON);
+      let velocity = PhysicalPosition::new(
+        (pointer.position.x - pointer.start_position.x) / seconds,
+        (pointer.position.y - pointer.start_position.y) / seconds
+      );
+
+      if velocity.x.hypot(velocity.y) >= config.swipe_min_velocity * scale_factor {
+        let direction = SwipeDirection::from_delta(velocity);
+        gestures.push(Gesture::Swipe { device_id, direction, velocity });
+      };
+    };
+  }
+}
+
+impl Default for GestureRecognizer {
+  #[inline]
+  fn default() -> Self {
+    Self::new(GestureConfig::default())
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PointerId {
+  Touch(u64),
+  Mouse
+}
+
+/// A single finger or mouse button being held down.
+#[derive(Debug, Clone, Copy)]
+struct Pointer {
+  id: PointerId,
+  device_id: DeviceId,
+  start_position: PhysicalPosition<f64>,
+  position: PhysicalPosition<f64>,
+  started_at: Instant,
+  panning: bool,
+  long_pressed: bool,
+  cancelled: bool
+}
+
+impl Pointer {
+  fn new(id: PointerId, device_id: DeviceId, position: PhysicalPosition<f64>, started_at: Instant) -> Self {
+    Pointer {
+      id,
+      device_id,
+      start_position: position,
+      position,
+      started_at,
+      panning: false,
+      long_pressed: false,
+      cancelled: false
+    }
+  }
+
+  fn distance(&self) -> f64 {
+    (self.position.x - self.start_position.x).hypot(self.position.y - self.start_position.y)
+  }
+}
+
+/// The two lowest numbered touches, as of the current frame.
+#[derive(Debug, Clone, Copy)]
+struct MultiTouch {
+  ids: [u64; 2],
+  device_id: DeviceId,
+  centroid: PhysicalPosition<f64>,
+  distance: f64,
+  /// The angle of the line from the first touch to the second, in degrees.
+  angle: f64
+}
+
+impl MultiTouch {
+  fn new(first: &TouchPoint, second: &TouchPoint) -> Self {
+    let (dx, dy) = (second.position.x - first.position.x, second.position.y - first.position.y);
+    MultiTouch {
+      ids: [first.id, second.id],
+      device_id: first.device_id,
+      centroid: PhysicalPosition::new((first.position.x + second.position.x) / 2.0, (first.position.y + second.position.y) / 2.0),
+      distance: dx.hypot(dy),
+      angle: dy.atan2(dx).to_degrees()
+    }
+  }
+
+  fn push_phase(&self, phase: TouchPhase, gestures: &mut Vec<Gesture>) {
+    let device_id = self.device_id;
+    gestures.push(Gesture::Pan { device_id, delta: PhysicalPosition::new(0.0, 0.0), phase });
+    gestures.push(Gesture::Pinch { device_id, delta: 0.0, phase });
+    gestures.push(Gesture::Rotation { device_id, delta: 0.0, phase });
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use winit::window::Window;
+
+  use crate::windowing::tests::{headless_window_state, NullHandler};
+  use crate::windowing::{InputEvent, WindowState};
+
+  use super::*;
+
+  struct Recognizer {
+    window_state: WindowState<Window>,
+    gesture_recognizer: GestureRecognizer,
+    start: Instant,
+    scale_factor: f64
+  }
+
+  impl Recognizer {
+    fn new(scale_factor: f64) -> Self {
+      Recognizer {
+        window_state: headless_window_state(),
+        gesture_recognizer: GestureRecognizer::default(),
+        start: Instant::now(),
+        scale_factor
+      }
+    }
+
+    /// Runs a frame at the given time since the start, in which a touch changes phase at the given position.
+    fn touch(&mut self, millis: u64, id: u64, phase: TouchPhase, (x, y): (f64, f64)) -> Vec<Gesture> {
+      self.window_state.reset();
+      self.window_state.handle_input_event(&mut NullHandler, InputEvent::Touch {
+        device_id: DeviceId::dummy(),
+        phase,
+        location: PhysicalPosition::new(x, y),
+        force: None,
+        id
+      });
+
+      let now = self.start + Duration::from_millis(millis);
+      self.gesture_recognizer.recognize(self.window_state.input(), now, self.scale_factor)
+    }
+
+    fn tap(&mut self, millis: u64, duration: u64, position: (f64, f64)) -> Vec<Gesture> {
+      self.touch(millis, 0, TouchPhase::Started, position);
+      self.touch(millis + duration, 0, TouchPhase::Ended, position)
+    }
+  }
+
+  fn is_tap(gesture: &Gesture) -> bool {
+    matches!(gesture, Gesture::Tap { .. })
+  }
+
+  #[test]
+  fn taps_must_be_short_and_still() {
+    let mut recognizer = Recognizer::new(1.0);
+    let gestures = recognizer.tap(0, 100, (100.0, 100.0));
+    assert!(matches!(gestures[..], [Gesture::Tap { position, .. }] if position == PhysicalPosition::new(100.0, 100.0)));
+
+    // held for longer than the maximum tap duration
+    assert!(recognizer.tap(1000, 400, (100.0, 100.0)).is_empty());
+
+    // moved farther than the maximum tap distance, which starts a pan instead
+    recognizer.touch(2000, 0, TouchPhase::Started, (100.0, 100.0));
+    let gestures = recognizer.touch(2100, 0, TouchPhase::Ended, (115.0, 100.0));
+    assert!(!gestures.iter().any(is_tap));
+    assert!(gestures.iter().any(|gesture| matches!(gesture, Gesture::Pan { phase: TouchPhase::Ended, .. })));
+  }
+
+  #[test]
+  fn double_taps_must_be_close_in_time_and_space() {
+    let mut recognizer = Recognizer::new(1.0);
+    assert_eq!(recognizer.tap(0, 50, (100.0, 100.0)).len(), 1);
+    let gestures = recognizer.tap(200, 50, (110.0, 100.0));
+    assert!(matches!(gestures[..], [Gesture::Tap { .. }, Gesture::DoubleTap { .. }]));
+
+    // a double tap is not the first tap of another double tap
+    assert_eq!(recognizer.tap(400, 50, (100.0, 100.0)).len(), 1);
+
+    // too long after the previous tap
+    assert_eq!(recognizer.tap(1000, 50, (100.0, 100.0)).len(), 1);
+
+    // too far from the previous tap
+    assert_eq!(recognizer.tap(1200, 50, (130.0, 100.0)).len(), 1);
+  }
+
+  #[test]
+  fn swipes_must_be_long_and_fast() {
+    let swipe = |scale_factor, duration, distance| {
+      let mut recognizer = Recognizer::new(scale_factor);
+      recognizer.touch(0, 0, TouchPhase::Started, (100.0, 100.0));
+      let gestures = recognizer.touch(duration, 0, TouchPhase::Ended, (100.0 + distance, 100.0));
+      gestures.into_iter().find_map(|gesture| match gesture {
+        Gesture::Swipe { direction, .. } => Some(direction),
+        _ => None
+      })
+    };
+
+    assert_eq!(swipe(1.0, 100, 100.0), Some(SwipeDirection::Right));
+    assert_eq!(swipe(1.0, 100, -100.0), Some(SwipeDirection::Left));
+    // too slow
+    assert_eq!(swipe(1.0, 500, 100.0), None);
+    // too short once scaled by the scale factor
+    assert_eq!(swipe(1.0, 100, 40.0), None);
+    assert_eq!(swipe(2.0, 100, 80.0), None);
+    assert_eq!(swipe(2.0, 100, 120.0), Some(SwipeDirection::Right));
+  }
+}
@@ -0,0 +1,381 @@
+//! Offscreen rendering without a window or an event loop.
+//!
+//! A [`HeadlessEngine`] drives an [`EngineEventHandler`] one frame at a time, rendering into an offscreen
+//! OpenGL pbuffer instead of a window. Each frame simulates a constant amount of elapsed time, so that
+//! rendering is deterministic regardless of how long frames actually take.
+
+use glutin::config::{Config, ConfigSurfaceTypes, ConfigTemplateBuilder};
+use image::RgbaImage;
+use winit::dpi::PhysicalSize;
+use winit::window::WindowId;
+
+use crate::engine::{
+  capture_screenshot, create_canvas, create_gl_context_for, default_gl_config_picker, EngineCanvas, EngineError, EngineEventHandler,
+  EngineGlState, EngineHandlerWrapper, EngineSurface, EngineWindowState
+};
+use crate::gamepad::GamepadSource;
+use crate::gestures::GestureConfig;
+use crate::plugins::EnginePlugin;
+use crate::proxy::{EngineProxy, HeadlessUserEvents};
+#[cfg(feature = "serde")]
+use crate::replay::InputReplay;
+use crate::windowing::{run_update, EventHandler, FixedTimestep, FrameTiming, InputEvent, TimestepState, WindowState};
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem::{replace, take};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+
+
+pub struct HeadlessEngineBuilder {
+  window_size: PhysicalSize<u32>,
+  scale_factor: f64,
+  frame_duration: Duration,
+  gl_config_template_builder: ConfigTemplateBuilder,
+  gl_config_picker: fn(Box<dyn Iterator<Item = Config> + '_>) -> Config,
+  fixed_timestep: Option<FixedTimestep>,
+  gesture_config: Option<GestureConfig>,
+  gamepad_source: Option<Box<dyn GamepadSource>>,
+  frame_history_len: usize,
+  #[cfg(feature = "serde")]
+  input_replay: Option<InputReplay>
+}
+
+impl HeadlessEngineBuilder {
+  /// The simulated duration of each frame by default, equivalent to 60 frames per second.
+  pub const DEFAULT_FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+  pub fn new(window_size: PhysicalSize<u32>) -> Self {
+    HeadlessEngineBuilder {
+      window_size,
+      scale_factor: 1.0,
+      frame_duration: Self::DEFAULT_FRAME_DURATION,
+      gl_config_template_builder: ConfigTemplateBuilder::new(),
+      gl_config_picker: default_gl_config_picker,
+      fixed_timestep: None,
+      gesture_config: None,
+      gamepad_source: None,
+      frame_history_len: FrameTiming::DEFAULT_HISTORY_LEN,
+      #[cfg(feature = "serde")]
+      input_replay: None
+    }
+  }
+
+  pub fn with_scale_factor(mut self, scale_factor: f64) -> Self {
+    self.scale_factor = scale_factor;
+    self
+  }
+
+  /// Sets the amount of time that is simulated to elapse between each frame.
+  pub fn with_frame_duration(mut self, frame_duration: Duration) -> Self {
+    self.frame_duration = frame_duration;
+    self
+  }
+
+  pub fn with_gl_config_template_builder(mut self, operate: impl FnOnce(ConfigTemplateBuilder) -> ConfigTemplateBuilder) -> Self {
+    self.gl_config_template_builder = operate(self.gl_config_template_builder);
+    self
+  }
+
+  /// See [`EngineBuilder::with_gl_config_picker`][crate::engine::EngineBuilder::with_gl_config_picker].
+  pub fn with_gl_config_picker(mut self, gl_config_picker: fn(Box<dyn Iterator<Item = Config> + '_>) -> Config) -> Self {
+    self.gl_config_picker = gl_config_picker;
+    self
+  }
+
+  /// See [`EngineBuilder::with_fixed_timestep`][crate::engine::EngineBuilder::with_fixed_timestep].
+  pub fn with_fixed_timestep(mut self, fixed_timestep: Option<FixedTimestep>) -> Self {
+    self.fixed_timestep = fixed_timestep;
+    self
+  }
+
+  /// See [`EngineBuilder::with_gesture_recognition`][crate::engine::EngineBuilder::with_gesture_recognition].
+  pub fn with_gesture_recognition(mut self, gesture_config: Option<GestureConfig>) -> Self {
+    self.gesture_config = gesture_config;
+    self
+  }
+
+  /// Configures the engine to poll the given source for gamepad input once per frame run,
+  /// such as a [`FakeGamepadSource`][crate::gamepad::FakeGamepadSource]. See [`EngineBuilder::with_gamepad_source`][crate::engine::EngineBuilder::with_gamepad_source].
+  pub fn with_gamepad_source(mut self, gamepad_source: Option<Box<dyn GamepadSource>>) -> Self {
+    self.gamepad_source = gamepad_source;
+    self
+  }
+
+  /// See [`EngineBuilder::with_frame_history_len`][crate::engine::EngineBuilder::with_frame_history_len].
+  pub fn with_frame_history_len(mut self, frame_history_len: usize) -> Self {
+    self.frame_history_len = frame_history_len;
+    self
+  }
+
+  /// Configures the engine to replay recorded input, one recorded frame per frame run.
+  /// Frames still advance by the engine's own frame duration rather than the recorded timing.
+  /// See the [`replay`][crate::replay] module.
+  #[cfg(feature = "serde")]
+  pub fn with_input_replay(mut self, input_replay: Option<InputReplay>) -> Self {
+    self.input_replay = input_replay;
+    self
+  }
+
+  /// Creates the headless engine, panicking if any stage of OpenGL setup fails.
+  /// See [`HeadlessEngineBuilder::try_create`] for a fallible alternative.
+  pub fn create<H: EngineEventHandler<T>, T: 'static>(self, handler: H) -> HeadlessEngine<H, T> {
+    self.try_create(handler).unwrap_or_else(|error| panic!("{error}"))
+  }
+
+  /// Creates the headless engine, returning an [`EngineError`] describing which stage of setup failed.
+  pub fn try_create<H: EngineEventHandler<T>, T: 'static>(self, handler: H) -> Result<HeadlessEngine<H, T>, EngineError> {
+    let (timestep, frame_duration) = (self.fixed_timestep.map(TimestepState::new), self.frame_duration);
+    let mut window_state = WindowState::new_headless(self.window_size, self.scale_factor, timestep, self.frame_history_len);
+    window_state.set_gesture_config(self.gesture_config);
+    let gl_state = self.build_gl_state(window_state.id())?;
+    let user_events = Arc::new(Mutex::new(Some(VecDeque::new())));
+
+    Ok(HeadlessEngine {
+      wrapper: EngineHandlerWrapper::new(gl_state, EngineProxy::headless(&user_events), handler),
+      window_state,
+      timestep,
+      frame_duration,
+      frame_instant: Instant::now(),
+      pending_input_events: Vec::new(),
+      user_events,
+      gamepad_source: self.gamepad_source,
+      #[cfg(feature = "serde")]
+      input_replay: self.input_replay,
+      initialized: false,
+      exited: false
+    })
+  }
+
+  #[cfg(all(any(windows, unix), not(target_vendor = "apple"), not(target_family = "wasm")))]
+  fn build_gl_state(&self, window_id: WindowId) -> Result<EngineGlState, EngineError> {
+    use glutin::api::egl::device::Device;
+    use glutin::api::egl::display::Display as EglDisplay;
+    use glutin::display::Display;
+    use glutin::prelude::*;
+    use glutin::surface::{PbufferSurface, SurfaceAttributesBuilder};
+    use std::num::NonZero;
+
+    // use the first device that an EGL display can be created for, such as a GPU or Mesa's llvmpipe
+    let mut error = None;
+    let egl_display = Device::query_devices()
+      .map_err(EngineError::HeadlessDisplay)?
+      .find_map(|device| unsafe { EglDisplay::with_device(&device, None) }.map_err(|e| error = Some(e)).ok());
+    let gl_display = match (egl_display, error) {
+      (Some(egl_display), _) => Display::Egl(egl_display),
+      (None, Some(error)) => return Err(EngineError::HeadlessDisplay(error)),
+      (None, None) => return Err(EngineError::HeadlessUnsupported)
+    };
+
+    let gl_config_template = self.gl_config_template_builder.clone()
+      .with_alpha_size(8)
+      .with_surface_type(ConfigSurfaceTypes::PBUFFER)
+      .build();
+    let mut gl_configs = unsafe { gl_display.find_configs(gl_config_template) }
+      .map_err(|error| EngineError::Display(error.into()))?
+      .peekable();
+    if gl_configs.peek().is_none() { return Err(EngineError::NoConfig) };
+    let gl_config = (self.gl_config_picker)(Box::new(gl_configs));
+    let not_current_gl_context = create_gl_context_for(None, &gl_config)?;
+
+    let width = NonZero::new(self.window_size.width).unwrap_or(NonZero::<u32>::MIN);
+    let height = NonZero::new(self.window_size.height).unwrap_or(NonZero::<u32>::MIN);
+    let gl_surface_attributes = SurfaceAttributesBuilder::<PbufferSurface>::new().build(width, height);
+    let gl_pbuffer_surface = unsafe {
+      gl_display.create_pbuffer_surface(&gl_config, &gl_surface_attributes)
+        .map_err(EngineError::Surface)?
+    };
+
+    let current_gl_context = not_current_gl_context.make_current(&gl_pbuffer_surface)
+      .map_err(EngineError::MakeCurrent)?;
+    let canvas = create_canvas(&gl_display)?;
+
+    Ok(EngineGlState::new(window_id, canvas, EngineSurface::Pbuffer(gl_pbuffer_surface), current_gl_context, gl_config))
+  }
+
+  #[cfg(not(all(any(windows, unix), not(target_vendor = "apple"), not(target_family = "wasm"))))]
+  fn build_gl_state(&self, _window_id: WindowId) -> Result<EngineGlState, EngineError> {
+    Err(EngineError::HeadlessUnsupported)
+  }
+}
+
+impl fmt::Debug for HeadlessEngineBuilder {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut debug_struct = f.debug_struct("HeadlessEngineBuilder");
+    debug_struct
+      .field("window_size", &self.window_size)
+      .field("scale_factor", &self.scale_factor)
+      .field("frame_duration", &self.frame_duration)
+      .field("gl_config_template_builder", &self.gl_config_template_builder)
+      .field("gl_config_picker", &self.gl_config_picker)
+      .field("fixed_timestep", &self.fixed_timestep)
+      .field("gesture_config", &self.gesture_config)
+      .field("gamepad_source", &self.gamepad_source)
+      .field("frame_history_len", &self.frame_history_len);
+    #[cfg(feature = "serde")]
+    debug_struct.field("input_replay", &self.input_replay);
+    debug_struct.finish()
+  }
+}
+
+
+
+/// An engine which renders offscreen, and whose frames are run manually rather than by an event loop.
+#[derive(Debug)]
+pub struct HeadlessEngine<H: EngineEventHandler<T>, T: 'static = ()> {
+  wrapper: EngineHandlerWrapper<H, T>,
+  window_state: EngineWindowState,
+  timestep: Option<TimestepState>,
+  frame_duration: Duration,
+  frame_instant: Instant,
+  pending_input_events: Vec<InputEvent>,
+  user_events: Arc<HeadlessUserEvents<T>>,
+  gamepad_source: Option<Box<dyn GamepadSource>>,
+  #[cfg(feature = "serde")]
+  input_replay: Option<InputReplay>,
+  initialized: bool,
+  exited: bool
+}
+
+impl<H: EngineEventHandler<T>, T: 'static> HeadlessEngine<H, T> {
+  #[inline]
+  pub fn window_state(&self) -> &EngineWindowState {
+    &self.window_state
+  }
+
+  #[inline]
+  pub fn handler(&self) -> &H {
+    self.wrapper.handler()
+  }
+
+  #[inline]
+  pub fn handler_mut(&mut self) -> &mut H {
+    self.wrapper.handler_mut()
+  }
+
+  /// Adds a plugin which runs around the handler's callbacks, after any plugins added before it.
+  /// Plugins added before the first frame is run also receive [`EngineCallback::Init`][crate::plugins::EngineCallback::Init].
+  /// See [`EngineBuilder::with_plugin`][crate::engine::EngineBuilder::with_plugin].
+  pub fn add_plugin(&mut self, plugin: impl EnginePlugin<T>) {
+    self.wrapper.add_plugin(Box::new(plugin));
+  }
+
+  /// Returns the canvas that frames are rendered to.
+  pub fn canvas(&mut self) -> &mut EngineCanvas {
+    self.wrapper.canvas_mut(self.window_state.id())
+  }
+
+  /// Captures the most recently rendered frame. See [`capture_screenshot`].
+  pub fn capture_screenshot(&mut self) -> Result<RgbaImage, femtovg::ErrorKind> {
+    capture_screenshot(self.canvas())
+  }
+
+  /// Whether the handler has asked to exit, either through [`EngineEventHandler::should_exit`]
+  /// or by closing its window. No more frames will be run once this is `true`.
+  #[inline]
+  pub fn has_exited(&self) -> bool {
+    self.exited
+  }
+
+  /// Returns the input replay configured with [`HeadlessEngineBuilder::with_input_replay`], if any.
+  #[cfg(feature = "serde")]
+  #[inline]
+  pub fn input_replay(&self) -> Option<&InputReplay> {
+    self.input_replay.as_ref()
+  }
+
+  /// Queues an input event to be delivered at the start of the next frame, before [`EngineEventHandler::update`].
+  /// See [`WindowState::handle_input_event`].
+  pub fn send_input_event(&mut self, event: InputEvent) {
+    self.pending_input_events.push(event);
+  }
+
+  /// Returns a proxy for sending user events from other threads, which are delivered at the start of the next frame.
+  /// Sending fails once the engine has exited or been destroyed. See the [`proxy`][crate::proxy] module.
+  pub fn proxy(&self) -> EngineProxy<T> {
+    EngineProxy::headless(&self.user_events)
+  }
+
+  /// Delivers a user event directly to the handler.
+  pub fn send_user_event(&mut self, event: T) {
+    self.initialize();
+    self.wrapper.on_user_event(&self.window_state, event);
+  }
+
+  /// Runs a single frame, updating and then rendering the handler.
+  /// Returns `false` without running a frame if the handler has exited.
+  pub fn run_frame(&mut self) -> bool {
+    if self.exited { return false };
+    self.initialize();
+
+    let elapsed = if self.window_state.frame_timing().frame_count() == 0 { Duration::ZERO } else { self.frame_duration };
+    self.window_state.reset();
+
+    let user_events = self.user_events.lock().unwrap_or_else(|error| error.into_inner()).as_mut().map(take);
+    for event in user_events.into_iter().flatten() {
+      self.wrapper.on_user_event(&self.window_state, event);
+    };
+
+    if let Some(gamepad_source) = &mut self.gamepad_source {
+      let mut gamepad_events = Vec::new();
+      gamepad_source.poll(&mut gamepad_events);
+      self.pending_input_events.extend(gamepad_events.into_iter().map(InputEvent::Gamepad));
+    };
+
+    #[cfg(feature = "serde")]
+    if let Some((_, events)) = self.input_replay.as_mut().and_then(|input_replay| input_replay.next_frame(self.frame_instant)) {
+      self.pending_input_events.extend(events);
+    };
+
+    for event in take(&mut self.pending_input_events) {
+      self.window_state.handle_input_event(&mut self.wrapper, event);
+    };
+
+    self.window_state.begin_frame(self.frame_instant, true);
+    self.window_state.recognize_gestures(&mut self.wrapper);
+    run_update::<_, _, T>(&mut self.wrapper, &mut self.window_state, &mut self.timestep, elapsed);
+    if self.wrapper.should_exit(&self.window_state) || self.window_state.take_headless_close_request() {
+      self.set_exited();
+      return false;
+    };
+
+    self.wrapper.render(&self.window_state);
+    if self.window_state.take_headless_close_request() {
+      self.set_exited();
+    };
+
+    self.frame_instant += self.frame_duration;
+    true
+  }
+
+  /// Stops running frames, and drops any user events that have not been delivered yet so that proxies stop accepting them.
+  fn set_exited(&mut self) {
+    self.exited = true;
+    *self.user_events.lock().unwrap_or_else(|error| error.into_inner()) = None;
+  }
+
+  /// Runs up to the given number of frames, stopping early if the handler exits.
+  /// Returns the number of frames that were run.
+  pub fn run_frames(&mut self, count: u64) -> u64 {
+    let mut frames = 0;
+    while frames < count && self.run_frame() {
+      frames += 1;
+    };
+
+    frames
+  }
+
+  /// Destroys the engine, calling [`EngineEventHandler::on_exited`].
+  pub fn exit(self) {
+    self.wrapper.on_exited();
+  }
+
+  fn initialize(&mut self) {
+    if !replace(&mut self.initialized, true) {
+      self.wrapper.init(&self.window_state);
+      self.wrapper.on_resumed(&self.window_state);
+    };
+  }
+}
@@ -0,0 +1,247 @@
+//! Layered input dispatch, where layers such as a debug UI or pause menu can consume input before the layers beneath them see it.
+//!
+//! Layers are handled from the topmost down. Each layer marks the input it has handled as consumed with
+//! [`WindowState::consume_input`], after which [`WindowState::unconsumed_input`] returns a view of the [`InputState`]
+//! with that input filtered out for the rest of the frame. Consumed input is forgotten when the next frame's events
+//! start arriving, so a layer which keeps handling a held key, drag or touch should consume it each frame.
+//! Event callbacks such as [`EventHandler::on_mouse_input`][crate::windowing::EventHandler::on_mouse_input]
+//! are not filtered, but can check [`WindowState::consumed_input`] themselves.
+
+use winit::event::MouseButton;
+use winit::keyboard::{Key as LogicalKey, KeyCode, NamedKey, PhysicalKey};
+
+use crate::windowing::{HasWindow, InputState, KeyAction, WindowState};
+
+
+
+/// The input consumed by layers during the current frame. See [`WindowState::consume_input`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ConsumedInput {
+  keyboard: bool,
+  keys_physical: Vec<KeyCode>,
+  keys_logical: Vec<NamedKey>,
+  mouse: bool,
+  mouse_buttons: Vec<MouseButton>,
+  scroll: bool,
+  touch: bool,
+  touch_ids: Vec<u64>
+}
+
+impl ConsumedInput {
+  /// Consumes every key, along with the modifiers, typed text and IME composition.
+  pub fn consume_keyboard(&mut self) {
+    self.keyboard = true;
+  }
+
+  /// Consumes the key with the given key code, whichever logical key it produces.
+  pub fn consume_key_physical(&mut self, key_code: KeyCode) {
+    push_unique(&mut self.keys_physical, key_code);
+  }
+
+  /// Consumes the given named key, whichever physical key produces it.
+  pub fn consume_key_logical(&mut self, named_key: NamedKey) {
+    push_unique(&mut self.keys_logical, named_key);
+  }
+
+  /// Consumes every mouse button, along with the cursor position and mouse motion,
+  /// so that layers beneath see the cursor as being outside the window.
+  pub fn consume_mouse(&mut self) {
+    self.mouse = true;
+  }
+
+  /// Consumes the given mouse button, including its clicks and drags.
+  pub fn consume_mouse_button(&mut self, button: MouseButton) {
+    push_unique(&mut self.mouse_buttons, button);
+  }
+
+  /// Consumes mouse wheel and touchpad scrolling.
+  pub fn consume_scroll(&mut self) {
+    self.scroll = true;
+  }
+
+  /// Consumes every touch, along with gestures.
+  pub fn consume_touch(&mut self) {
+    self.touch = true;
+  }
+
+  /// Consumes the touch with the given ID.
+  pub fn consume_touch_point(&mut self, id: u64) {
+    push_unique(&mut self.touch_ids, id);
+  }
+
+  #[inline]
+  pub fn is_keyboard_consumed(&self) -> bool {
+    self.keyboard
+  }
+
+  pub fn is_key_consumed_physical(&self, key_code: &KeyCode) -> bool {
+    self.keyboard || self.keys_physical.contains(key_code)
+  }
+
+  pub fn is_key_consumed_logical(&self, named_key: &NamedKey) -> bool {
+    self.keyboard || self.keys_logical.contains(named_key)
+  }
+
+  /// Whether the key operated by the given key action has been consumed, either by its key code or its named key.
+  pub fn is_key_action_consumed(&self, key_action: &KeyAction) -> bool {
+    matches!(key_action.physical_key, PhysicalKey::Code(key_code) if self.is_key_consumed_physical(&key_code))
+      || matches!(key_action.logical_key, LogicalKey::Named(named_key) if self.is_key_consumed_logical(&named_key))
+      || self.keyboard
+  }
+
+  #[inline]
+  pub fn is_mouse_consumed(&self) -> bool {
+    self.mouse
+  }
+
+  pub fn is_mouse_button_consumed(&self, button: MouseButton) -> bool {
+    self.mouse || self.mouse_buttons.contains(&button)
+  }
+
+  #[inline]
+  pub fn is_scroll_consumed(&self) -> bool {
+    self.scroll
+  }
+
+  #[inline]
+  pub fn is_touch_consumed(&self) -> bool {
+    self.touch
+  }
+
+  pub fn is_touch_point_consumed(&self, id: u64) -> bool {
+    self.touch || self.touch_ids.contains(&id)
+  }
+
+  /// Whether no input has been consumed.
+  pub fn is_empty(&self) -> bool {
+    *self == ConsumedInput::default()
+  }
+
+  pub fn clear(&mut self) {
+    *self = ConsumedInput::default();
+  }
+}
+
+fn push_unique<T: PartialEq>(list: &mut Vec<T>, value: T) {
+  if !list.contains(&value) {
+    list.push(value);
+  };
+}
+
+/// A layer of input handling, such as a debug UI or pause menu drawn over gameplay. See [`dispatch_input_layers`].
+pub trait InputLayer<W: HasWindow> {
+  /// Handles the input left over by the layers above this one,
+  /// consuming any input that the layers beneath should not see with [`WindowState::consume_input`].
+  fn handle_input(&mut self, window_state: &WindowState<W>, input: &InputState);
+}
+
+/// Hands the input of the current frame to each layer in turn, from the topmost down,
+/// with each layer seeing only the input that has not been consumed by the layers above it.
+pub fn dispatch_input_layers<W: HasWindow>(window_state: &WindowState<W>, layers: &mut [&mut dyn InputLayer<W>]) {
+  for layer in layers {
+    layer.handle_input(window_state, &window_state.unconsumed_input());
+  };
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use winit::dpi::PhysicalPosition;
+  use winit::event::{DeviceId, ElementState, TouchPhase};
+  use winit::keyboard::KeyLocation;
+  use winit::window::Window;
+
+  use crate::windowing::tests::{headless_window_state, NullHandler};
+  use crate::windowing::InputEvent;
+
+  use super::*;
+
+  use std::time::Instant;
+
+  /// A layer which consumes the left mouse button, the escape key and the first touch point, if enabled.
+  struct ConsumingLayer {
+    consume: bool
+  }
+
+  impl InputLayer<Window> for ConsumingLayer {
+    fn handle_input(&mut self, window_state: &WindowState<Window>, _: &InputState) {
+      if !self.consume { return };
+      window_state.consume_input(|consumed| {
+        consumed.consume_mouse_button(MouseButton::Left);
+        consumed.consume_key_physical(KeyCode::Escape);
+        consumed.consume_touch_point(0);
+      });
+    }
+  }
+
+  #[derive(Default)]
+  struct RecordingLayer {
+    input: Option<InputState>
+  }
+
+  impl InputLayer<Window> for RecordingLayer {
+    fn handle_input(&mut self, _: &WindowState<Window>, input: &InputState) {
+      self.input = Some(input.clone());
+    }
+  }
+
+  fn touch_event(id: u64) -> InputEvent {
+    InputEvent::Touch {
+      device_id: DeviceId::dummy(),
+      phase: TouchPhase::Started,
+      location: PhysicalPosition::new(100.0, 100.0),
+      force: None,
+      id
+    }
+  }
+
+  #[test]
+  fn consumed_input_is_hidden_from_lower_layers_for_a_single_frame() {
+    let mut window_state = headless_window_state();
+    let events = [
+      InputEvent::MouseInput { device_id: DeviceId::dummy(), state: ElementState::Pressed, button: MouseButton::Left },
+      InputEvent::MouseInput { device_id: DeviceId::dummy(), state: ElementState::Pressed, button: MouseButton::Right },
+      InputEvent::Keyboard {
+        device_id: DeviceId::dummy(),
+        physical_key: PhysicalKey::Code(KeyCode::Escape),
+        logical_key: LogicalKey::Named(NamedKey::Escape),
+        text: None,
+        location: KeyLocation::Standard,
+        state: ElementState::Pressed,
+        repeat: false
+      },
+      touch_event(0),
+      touch_event(1)
+    ];
+
+    for event in events {
+      window_state.handle_input_event(&mut NullHandler, event);
+    };
+
+    let now = Instant::now();
+    window_state.begin_frame(now, true);
+    let mut lower_layer = RecordingLayer::default();
+    dispatch_input_layers(&window_state, &mut [&mut ConsumingLayer { consume: true }, &mut lower_layer]);
+    let input = lower_layer.input.take().unwrap();
+    assert!(!input.is_button_held(MouseButton::Left) && input.is_button_held(MouseButton::Right));
+    assert!(input.mouse_actions().iter().all(|mouse_action| mouse_action.button != MouseButton::Left));
+    assert!(input.key_actions().is_empty());
+    assert!(!input.is_key_held_physical(&KeyCode::Escape) && !input.is_key_held_logical(&NamedKey::Escape));
+    assert!(input.touch_point(0).is_none() && input.touch_point(1).is_some());
+    assert_eq!(input.touches_started(), [1]);
+
+    // the input is still reported in full outside of the layers
+    assert!(window_state.input().is_button_held(MouseButton::Left));
+    assert!(window_state.input().is_key_held_logical(&NamedKey::Escape));
+
+    window_state.reset();
+    window_state.begin_frame(now, true);
+    assert!(window_state.consumed_input().is_empty());
+    dispatch_input_layers(&window_state, &mut [&mut ConsumingLayer { consume: false }, &mut lower_layer]);
+    let input = lower_layer.input.take().unwrap();
+    assert!(input.is_button_held(MouseButton::Left));
+    assert!(input.is_key_held_physical(&KeyCode::Escape) && input.is_key_held_logical(&NamedKey::Escape));
+    assert!(input.touch_point(0).is_some());
+  }
+}
@@ -0,0 +1,442 @@
+//! Named actions bound to keys, mouse buttons and modifiers.
+//!
+//! An [`InputMap`] maps actions, identified by any hashable type such as a `String` or an enum, to [`ActionBinding`]s.
+//! Buttons are on or off, while axes combine pairs of opposing inputs into a value from `-1.0` to `1.0`,
+//! in one or two dimensions. Each frame, [`InputMap::update`] samples an [`InputState`],
+//! after which each action can be queried with [`InputMap::pressed`], [`InputMap::just_pressed`],
+//! [`InputMap::just_released`] and [`InputMap::value`].
+
+use ahash::AHashMap;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use winit::event::{ElementState, MouseButton};
+use winit::keyboard::{Key as LogicalKey, KeyCode, ModifiersState, NamedKey, PhysicalKey};
+
+use crate::windowing::{InputState, MouseAction};
+
+use std::hash::Hash;
+
+
+
+/// A single input that can activate an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum InputBinding {
+  /// A key at a physical location on the keyboard, regardless of layout.
+  Physical(KeyCode),
+  /// A named key produced by the current keyboard layout.
+  Logical(NamedKey),
+  Mouse(MouseButton),
+  /// Held while all of the given modifiers are held. An empty set of modifiers is never held.
+  Modifiers(ModifiersState)
+}
+
+impl InputBinding {
+  /// Whether this input is currently held.
+  pub fn is_held(&self, input: &InputState) -> bool {
+    match *self {
+      InputBinding::Physical(key_code) => input.is_key_held_physical(&key_code),
+      InputBinding::Logical(named_key) => input.is_key_held_logical(&named_key),
+      InputBinding::Mouse(button) => input.is_button_held(button),
+      InputBinding::Modifiers(modifiers) => !modifiers.is_empty() && input.modifiers().state().contains(modifiers)
+    }
+  }
+
+  /// Whether this input was operated in the given way during the current frame, counting key repeats as presses.
+  /// Modifiers do not produce actions, so this is always `false` for [`InputBinding::Modifiers`].
+  pub fn was_operated(&self, input: &InputState, state: ElementState) -> bool {
+    match *self {
+      InputBinding::Physical(key_code) => {
+        let physical_key = PhysicalKey::Code(key_code);
+        input.key_actions().iter().any(|action| action.physical_key == physical_key && state == action.state.into())
+      },
+      InputBinding::Logical(named_key) => {
+        let logical_key = LogicalKey::Named(named_key);
+        input.key_actions().iter().any(|action| action.logical_key == logical_key && state == action.state.into())
+      },
+      InputBinding::Mouse(button) => input.mouse_actions().contains(&MouseAction { button, state }),
+      InputBinding::Modifiers(..) => false
+    }
+  }
+
+  fn was_pressed(&self, input: &InputState) -> bool {
+    match *self {
+      // key repeats are not new presses
+      InputBinding::Physical(key_code) => input.was_key_pressed_physical(&PhysicalKey::Code(key_code)),
+      InputBinding::Logical(named_key) => input.was_key_pressed_logical(&LogicalKey::Named(named_key)),
+      _ => self.was_operated(input, ElementState::Pressed)
+    }
+  }
+}
+
+impl From<KeyCode> for InputBinding {
+  #[inline]
+  fn from(key_code: KeyCode) -> Self {
+    InputBinding::Physical(key_code)
+  }
+}
+
+impl From<NamedKey> for InputBinding {
+  #[inline]
+  fn from(named_key: NamedKey) -> Self {
+    InputBinding::Logical(named_key)
+  }
+}
+
+impl From<MouseButton> for InputBinding {
+  #[inline]
+  fn from(button: MouseButton) -> Self {
+    InputBinding::Mouse(button)
+  }
+}
+
+impl From<ModifiersState> for InputBinding {
+  #[inline]
+  fn from(modifiers: ModifiersState) -> Self {
+    InputBinding::Modifiers(modifiers)
+  }
+}
+
+/// A one-dimensional axis composed of two opposing inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AxisBinding {
+  pub negative: InputBinding,
+  pub positive: InputBinding
+}
+
+impl AxisBinding {
+  pub fn new(negative: impl Into<InputBinding>, positive: impl Into<InputBinding>) -> Self {
+    AxisBinding { negative: negative.into(), positive: positive.into() }
+  }
+
+  /// The value of this axis, which is `-1.0`, `0.0` or `1.0`.
+  pub fn value(&self, input: &InputState) -> f32 {
+    self.positive.is_held(input) as u8 as f32 - self.negative.is_held(input) as u8 as f32
+  }
+}
+
+/// A two-dimensional axis composed of two [`AxisBinding`]s.
+/// Following screen coordinates, positive `y` points down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Axis2dBinding {
+  pub x: AxisBinding,
+  pub y: AxisBinding
+}
+
+impl Axis2dBinding {
+  pub fn new(left: impl Into<InputBinding>, right: impl Into<InputBinding>, up: impl Into<InputBinding>, down: impl Into<InputBinding>) -> Self {
+    Axis2dBinding { x: AxisBinding::new(left, right), y: AxisBinding::new(up, down) }
+  }
+
+  /// The W, A, S and D keys, by physical location.
+  pub fn wasd() -> Self {
+    Self::new(KeyCode::KeyA, KeyCode::KeyD, KeyCode::KeyW, KeyCode::KeyS)
+  }
+
+  /// The arrow keys, by physical location.
+  pub fn arrow_keys() -> Self {
+    Self::new(KeyCode::ArrowLeft, KeyCode::ArrowRight, KeyCode::ArrowUp, KeyCode::ArrowDown)
+  }
+
+  pub fn value(&self, input: &InputState) -> [f32; 2] {
+    [self.x.value(input), self.y.value(input)]
+  }
+}
+
+/// The inputs bound to an action, which determine what kind of action it is.
+/// Any one of the bindings can activate the action, and axis values from multiple bindings are combined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ActionBinding {
+  /// An action which is either pressed or not, and has a value of `0.0` or `1.0`.
+  Button(Vec<InputBinding>),
+  /// An action with a value from `-1.0` to `1.0`.
+  Axis(Vec<AxisBinding>),
+  /// An action with a value within the unit circle, so that diagonal movement is not faster.
+  Axis2d(Vec<Axis2dBinding>)
+}
+
+impl ActionBinding {
+  fn value(&self, input: &InputState) -> [f32; 2] {
+    match self {
+      ActionBinding::Button(bindings) => {
+        [bindings.iter().any(|binding| binding.is_held(input)) as u8 as f32, 0.0]
+      },
+      ActionBinding::Axis(bindings) => {
+        let x = bindings.iter().map(|binding| binding.value(input)).sum::<f32>();
+        [x.clamp(-1.0, 1.0), 0.0]
+      },
+      ActionBinding::Axis2d(bindings) => {
+        let [x, y] = bindings.iter()
+          .map(|binding| binding.value(input))
+          .fold([0.0, 0.0], |[x, y], [binding_x, binding_y]| [x + binding_x, y + binding_y]);
+        let length = x.hypot(y);
+        if length > 1.0 { [x / length, y / length] } else { [x, y] }
+      }
+    }
+  }
+
+  /// Whether a button was pressed during the current frame, even if it has already been released again,
+  /// which sampling its held state alone would miss.
+  fn was_tapped(&self, input: &InputState) -> bool {
+    match self {
+      ActionBinding::Button(bindings) => bindings.iter().any(|binding| binding.was_pressed(input)),
+      ActionBinding::Axis(..) | ActionBinding::Axis2d(..) => false
+    }
+  }
+}
+
+/// A set of actions and the inputs bound to them, which can be saved and loaded under the `serde` feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InputBindings<A: Eq + Hash> {
+  actions: AHashMap<A, ActionBinding>
+}
+
+impl<A: Eq + Hash> InputBindings<A> {
+  pub fn new() -> Self {
+    InputBindings { actions: AHashMap::new() }
+  }
+
+  pub fn with_button(mut self, action: A, bindings: impl IntoIterator<Item = InputBinding>) -> Self {
+    self.set(action, ActionBinding::Button(bindings.into_iter().collect()));
+    self
+  }
+
+  pub fn with_axis(mut self, action: A, bindings: impl IntoIterator<Item = AxisBinding>) -> Self {
+    self.set(action, ActionBinding::Axis(bindings.into_iter().collect()));
+    self
+  }
+
+  pub fn with_axis_2d(mut self, action: A, bindings: impl IntoIterator<Item = Axis2dBinding>) -> Self {
+    self.set(action, ActionBinding::Axis2d(bindings.into_iter().collect()));
+    self
+  }
+
+  #[inline]
+  pub fn get(&self, action: &A) -> Option<&ActionBinding> {
+    self.actions.get(action)
+  }
+
+  #[inline]
+  pub fn get_mut(&mut self, action: &A) -> Option<&mut ActionBinding> {
+    self.actions.get_mut(action)
+  }
+
+  /// Binds an action, replacing its previous bindings.
+  pub fn set(&mut self, action: A, binding: ActionBinding) -> Option<ActionBinding> {
+    self.actions.insert(action, binding)
+  }
+
+  /// Removes an action and its bindings.
+  pub fn remove(&mut self, action: &A) -> Option<ActionBinding> {
+    self.actions.remove(action)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&A, &ActionBinding)> {
+    self.actions.iter()
+  }
+}
+
+impl<A: Eq + Hash> Default for InputBindings<A> {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct ActionState {
+  value: [f32; 2],
+  just_pressed: bool,
+  just_released: bool
+}
+
+impl ActionState {
+  #[inline]
+  fn is_pressed(&self) -> bool {
+    self.value != [0.0, 0.0]
+  }
+}
+
+/// Tracks the state of a set of actions from frame to frame.
+///
+/// [`InputMap::update`] should be called once per frame, before any actions are queried,
+/// such as at the start of [`EventHandler::update`][crate::windowing::EventHandler::update].
+/// When using a [`FixedTimestep`][crate::windowing::FixedTimestep], it should not be called once per tick,
+/// since [`InputState`] only changes between frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputMap<A: Eq + Hash> {
+  bindings: InputBindings<A>,
+  states: AHashMap<A, ActionState>
+}
+
+impl<A: Eq + Hash + Clone> InputMap<A> {
+  pub fn new(bindings: InputBindings<A>) -> Self {
+    InputMap { bindings, states: AHashMap::new() }
+  }
+
+  #[inline]
+  pub fn bindings(&self) -> &InputBindings<A> {
+    &self.bindings
+  }
+
+  /// Returns the bindings for modification, such as to rebind an action at runtime.
+  /// Changes take effect on the next [`InputMap::update`].
+  #[inline]
+  pub fn bindings_mut(&mut self) -> &mut InputBindings<A> {
+    &mut self.bindings
+  }
+
+  /// Replaces every binding, such as with bindings loaded from a settings file.
+  pub fn set_bindings(&mut self, bindings: InputBindings<A>) {
+    self.bindings = bindings;
+  }
+
+  /// Samples the current input, updating the state of every action.
+  pub fn update(&mut self, input: &InputState) {
+    self.states.retain(|action, _| self.bindings.actions.contains_key(action));
+    for (action, binding) in self.bindings.actions.iter() {
+      let state = self.states.entry(action.clone()).or_default();
+      let was_pressed = state.is_pressed();
+      state.value = binding.value(input);
+
+      let (is_pressed, tapped) = (state.is_pressed(), binding.was_tapped(input));
+      state.just_pressed = !was_pressed && (is_pressed || tapped);
+      state.just_released = !is_pressed && (was_pressed || tapped);
+    };
+  }
+
+  /// Whether the action is active, meaning that a button is held or an axis is away from zero.
+  pub fn pressed(&self, action: &A) -> bool {
+    self.state(action).is_pressed()
+  }
+
+  /// Whether the action became active during the current frame.
+  /// This includes buttons that were pressed and released again within the frame.
+  pub fn just_pressed(&self, action: &A) -> bool {
+    self.state(action).just_pressed
+  }
+
+  /// Whether the action stopped being active during the current frame.
+  /// This includes buttons that were pressed and released again within the frame.
+  pub fn just_released(&self, action: &A) -> bool {
+    self.state(action).just_released
+  }
+
+  /// The value of the action, which is `0.0` or `1.0` for buttons, and from `-1.0` to `1.0` for axes.
+  /// For two-dimensional axes this is the `x` component.
+  pub fn value(&self, action: &A) -> f32 {
+    self.state(action).value[0]
+  }
+
+  /// The value of a two-dimensional axis. Other actions have a `y` component of `0.0`.
+  pub fn value_2d(&self, action: &A) -> [f32; 2] {
+    self.state(action).value
+  }
+
+  fn state(&self, action: &A) -> ActionState {
+    self.states.get(action).copied().unwrap_or_default()
+  }
+}
+
+impl<A: Eq + Hash + Clone> Default for InputMap<A> {
+  #[inline]
+  fn default() -> Self {
+    Self::new(InputBindings::new())
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use winit::window::Window;
+
+  use crate::windowing::tests::{headless_window_state, key_event, NullHandler};
+  use crate::windowing::WindowState;
+
+  use super::*;
+
+  #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+  #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+  enum Action {
+    Jump,
+    Steer,
+    Move
+  }
+
+  fn bindings() -> InputBindings<Action> {
+    InputBindings::new()
+      .with_button(Action::Jump, [InputBinding::Physical(KeyCode::Space), InputBinding::Mouse(MouseButton::Left)])
+      .with_axis(Action::Steer, [AxisBinding::new(NamedKey::ArrowLeft, NamedKey::ArrowRight)])
+      .with_axis_2d(Action::Move, [Axis2dBinding::wasd(), Axis2dBinding::arrow_keys()])
+  }
+
+  /// Applies the given key events as a single frame, then updates the input map.
+  fn frame(window_state: &mut WindowState<Window>, input_map: &mut InputMap<Action>, events: &[(KeyCode, ElementState)]) {
+    window_state.reset();
+    for &(key_code, state) in events {
+      window_state.handle_input_event(&mut NullHandler, key_event(key_code, state));
+    };
+
+    input_map.update(window_state.input());
+  }
+
+  #[test]
+  fn composite_2d_axes_are_normalized() {
+    let (mut window_state, mut input_map) = (headless_window_state(), InputMap::new(bindings()));
+    frame(&mut window_state, &mut input_map, &[(KeyCode::KeyD, ElementState::Pressed)]);
+    assert_eq!(input_map.value_2d(&Action::Move), [1.0, 0.0]);
+    assert_eq!(input_map.value(&Action::Move), 1.0);
+
+    frame(&mut window_state, &mut input_map, &[(KeyCode::KeyS, ElementState::Pressed)]);
+    let [x, y] = input_map.value_2d(&Action::Move);
+    assert!((x - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6 && (y - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+
+    // opposing bindings from different composites cancel out
+    frame(&mut window_state, &mut input_map, &[(KeyCode::ArrowUp, ElementState::Pressed)]);
+    assert_eq!(input_map.value_2d(&Action::Move), [1.0, 0.0]);
+  }
+
+  #[test]
+  fn composite_2d_axes_are_just_pressed_once() {
+    let (mut window_state, mut input_map) = (headless_window_state(), InputMap::new(bindings()));
+    frame(&mut window_state, &mut input_map, &[(KeyCode::KeyW, ElementState::Pressed)]);
+    assert!(input_map.pressed(&Action::Move) && input_map.just_pressed(&Action::Move));
+
+    // pressing a second key of an axis which is already active does not press it again
+    frame(&mut window_state, &mut input_map, &[(KeyCode::KeyA, ElementState::Pressed)]);
+    assert!(input_map.pressed(&Action::Move) && !input_map.just_pressed(&Action::Move));
+
+    frame(&mut window_state, &mut input_map, &[(KeyCode::KeyW, ElementState::Released)]);
+    assert!(input_map.pressed(&Action::Move) && !input_map.just_released(&Action::Move));
+
+    frame(&mut window_state, &mut input_map, &[(KeyCode::KeyA, ElementState::Released)]);
+    assert!(!input_map.pressed(&Action::Move) && input_map.just_released(&Action::Move));
+    assert_eq!(input_map.value_2d(&Action::Move), [0.0, 0.0]);
+
+    frame(&mut window_state, &mut input_map, &[]);
+    assert!(!input_map.just_pressed(&Action::Move) && !input_map.just_released(&Action::Move));
+  }
+
+  #[test]
+  fn buttons_tapped_within_a_frame_are_just_pressed_and_released() {
+    let (mut window_state, mut input_map) = (headless_window_state(), InputMap::new(bindings()));
+    frame(&mut window_state, &mut input_map, &[(KeyCode::Space, ElementState::Pressed), (KeyCode::Space, ElementState::Released)]);
+    assert!(!input_map.pressed(&Action::Jump));
+    assert!(input_map.just_pressed(&Action::Jump) && input_map.just_released(&Action::Jump));
+    assert_eq!(input_map.value(&Action::Jump), 0.0);
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn bindings_round_trip_through_serde() {
+    let bindings = bindings()
+      .with_button(Action::Steer, [InputBinding::Modifiers(ModifiersState::CONTROL | ModifiersState::SHIFT)]);
+    let json = serde_json::to_string(&bindings).unwrap();
+    assert_eq!(serde_json::from_str::<InputBindings<Action>>(&json).unwrap(), bindings);
+  }
+}
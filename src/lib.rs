@@ -19,4 +19,16 @@ pub extern crate winit;
 #[macro_use]
 pub mod misc;
 pub mod engine;
+pub mod gamepad;
+pub mod gestures;
+pub mod headless;
+pub mod input_layers;
+pub mod input_map;
+pub mod plugins;
+pub mod proxy;
+pub mod recording;
+#[cfg(feature = "serde")]
+pub mod replay;
+pub mod scenes;
+pub mod shortcuts;
 pub mod windowing;
@@ -92,14 +92,48 @@ where image::LumaA<T>: Pixel<Subpixel = T> {
 
 
 
-macro_rules! delegate {
-  ($delegate:ident: $vis:vis fn $name:ident(&self $(, $arg:ident : $Arg:ty)* $(,)?) $(-> $Ret:ty)?) => (
-    #[inline] $vis fn $name(&self, $($arg: $Arg),*) $(-> $Ret)? { self.$delegate.$name($($arg),*) }
-  );
-  ($delegate:ident: $vis:vis fn $name:ident(&mut self $(, $arg:ident : $Arg:ty)* $(,)?) $(-> $Ret:ty)?) => (
-    #[inline] $vis fn $name(&mut self, $($arg: $Arg),*) $(-> $Ret)? { self.$delegate.$name($($arg),*) }
-  );
-  ($delegate:ident: $vis:vis fn $name:ident(self $(, $arg:ident : $Arg:ty)* $(,)?) $(-> $Ret:ty)?) => (
-    #[inline] $vis fn $name(self, $($arg: $Arg),*) $(-> $Ret)? { self.$delegate.$name($($arg),*) }
-  );
+/// Serializes a [`Modifiers`][winit::event::Modifiers] as just its [`ModifiersState`][winit::keyboard::ModifiersState],
+/// since which side's keys are pressed cannot be restored.
+#[cfg(feature = "serde")]
+pub(crate) mod modifiers_serde {
+  use serde::{Deserialize, Deserializer, Serialize, Serializer};
+  use winit::event::Modifiers;
+  use winit::keyboard::ModifiersState;
+
+  pub(crate) fn serialize<S: Serializer>(modifiers: &Modifiers, serializer: S) -> Result<S::Ok, S::Error> {
+    modifiers.state().serialize(serializer)
+  }
+
+  pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Modifiers, D::Error> {
+    ModifiersState::deserialize(deserializer).map(Modifiers::from)
+  }
+}
+
+/// Serializes an optional [`Force`][winit::event::Force], which does not implement serde's traits itself.
+#[cfg(feature = "serde")]
+pub(crate) mod force_serde {
+  use serde::{Deserialize, Deserializer, Serialize, Serializer};
+  use winit::event::Force;
+
+  #[derive(Serialize, Deserialize)]
+  #[serde(remote = "Force")]
+  enum ForceDef {
+    Calibrated {
+      force: f64,
+      max_possible_force: f64,
+      altitude_angle: Option<f64>
+    },
+    Normalized(f64)
+  }
+
+  #[derive(Serialize, Deserialize)]
+  struct ForceWrapper(#[serde(with = "ForceDef")] Force);
+
+  pub(crate) fn serialize<S: Serializer>(force: &Option<Force>, serializer: S) -> Result<S::Ok, S::Error> {
+    force.map(ForceWrapper).serialize(serializer)
+  }
+
+  pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Force>, D::Error> {
+    Option::<ForceWrapper>::deserialize(deserializer).map(|force| force.map(|ForceWrapper(force)| force))
+  }
 }
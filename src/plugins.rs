@@ -0,0 +1,110 @@
+//! Reusable plugins which run around an [`EngineEventHandler`][crate::engine::EngineEventHandler], such as an FPS overlay or a screenshot hotkey.
+//!
+//! Plugins are added with [`EngineBuilder::with_plugin`][crate::engine::EngineBuilder::with_plugin]
+//! or [`HeadlessEngine::add_plugin`][crate::headless::HeadlessEngine::add_plugin],
+//! and are given every callback that receives an [`EngineContext`], described by an [`EngineCallback`].
+//! Plugins run in the order they were added: [`EnginePlugin::before`] hooks from first to last, then the handler,
+//! then [`EnginePlugin::after`] hooks from last to first. A before hook can short-circuit a callback by returning
+//! [`ControlFlow::Break`], which skips the handler along with every plugin added after it,
+//! while the after hooks of the plugins before it still run.
+
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{DeviceEvent, DeviceId, ElementState, Ime, KeyEvent, MouseButton, MouseScrollDelta, Touch};
+
+use crate::engine::{EngineContext, EngineWindowState};
+use crate::gamepad::{GamepadAxis, GamepadButton, GamepadId};
+use crate::windowing::{AxisMotion, Gesture, SyntheticKeyEvent};
+
+use std::error::Error;
+use std::fmt;
+use std::ops::ControlFlow;
+use std::path::Path;
+
+
+
+/// A callback of an [`EngineEventHandler`][crate::engine::EngineEventHandler] along with its arguments, as seen by an [`EnginePlugin`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EngineCallback<'a> {
+  /// See [`EngineEventHandler::init`][crate::engine::EngineEventHandler::init].
+  Init,
+  /// See [`EngineEventHandler::on_user_event`][crate::engine::EngineEventHandler::on_user_event].
+  /// Only given to after hooks, since before hooks are given the event itself by [`EnginePlugin::on_user_event`].
+  UserEvent,
+  /// See [`EngineEventHandler::update`][crate::engine::EngineEventHandler::update].
+  Update,
+  /// See [`EngineEventHandler::render`][crate::engine::EngineEventHandler::render]. After hooks draw over the handler's frame before it is presented.
+  Render,
+  /// See [`EngineEventHandler::on_ticks_dropped`][crate::engine::EngineEventHandler::on_ticks_dropped].
+  TicksDropped(u32),
+  /// See [`EngineEventHandler::on_device_event`][crate::engine::EngineEventHandler::on_device_event].
+  DeviceEvent(DeviceId, &'a DeviceEvent),
+  /// See [`EngineEventHandler::on_keyboard_input`][crate::engine::EngineEventHandler::on_keyboard_input].
+  KeyboardInput(&'a KeyEvent),
+  /// See [`EngineEventHandler::on_synthetic_keyboard_input`][crate::engine::EngineEventHandler::on_synthetic_keyboard_input].
+  SyntheticKeyboardInput(&'a SyntheticKeyEvent),
+  /// See [`EngineEventHandler::on_text_input`][crate::engine::EngineEventHandler::on_text_input].
+  TextInput(&'a Ime),
+  /// See [`EngineEventHandler::on_cursor_moved`][crate::engine::EngineEventHandler::on_cursor_moved].
+  CursorMoved(PhysicalPosition<f32>),
+  /// See [`EngineEventHandler::on_cursor_entered`][crate::engine::EngineEventHandler::on_cursor_entered].
+  CursorEntered,
+  /// See [`EngineEventHandler::on_cursor_left`][crate::engine::EngineEventHandler::on_cursor_left].
+  CursorLeft,
+  /// See [`EngineEventHandler::on_mouse_input`][crate::engine::EngineEventHandler::on_mouse_input].
+  MouseInput(ElementState, MouseButton),
+  /// See [`EngineEventHandler::on_mouse_scroll`][crate::engine::EngineEventHandler::on_mouse_scroll].
+  MouseScroll(MouseScrollDelta),
+  /// See [`EngineEventHandler::on_gesture`][crate::engine::EngineEventHandler::on_gesture].
+  Gesture(Gesture),
+  /// See [`EngineEventHandler::on_touch`][crate::engine::EngineEventHandler::on_touch].
+  Touch(Touch),
+  /// See [`EngineEventHandler::on_axis_motion`][crate::engine::EngineEventHandler::on_axis_motion].
+  AxisMotion(AxisMotion),
+  /// See [`EngineEventHandler::on_gamepad_connected`][crate::engine::EngineEventHandler::on_gamepad_connected].
+  GamepadConnected(GamepadId),
+  /// See [`EngineEventHandler::on_gamepad_disconnected`][crate::engine::EngineEventHandler::on_gamepad_disconnected].
+  GamepadDisconnected(GamepadId),
+  /// See [`EngineEventHandler::on_gamepad_button`][crate::engine::EngineEventHandler::on_gamepad_button].
+  GamepadButton(GamepadId, GamepadButton, ElementState),
+  /// See [`EngineEventHandler::on_gamepad_axis`][crate::engine::EngineEventHandler::on_gamepad_axis].
+  GamepadAxis(GamepadId, GamepadAxis, f32),
+  /// See [`EngineEventHandler::on_focus_changed`][crate::engine::EngineEventHandler::on_focus_changed].
+  FocusChanged(bool),
+  /// See [`EngineEventHandler::on_occlusion_changed`][crate::engine::EngineEventHandler::on_occlusion_changed].
+  OcclusionChanged(bool),
+  /// See [`EngineEventHandler::on_file_over`][crate::engine::EngineEventHandler::on_file_over].
+  FileOver(Option<&'a Path>, bool),
+  /// See [`EngineEventHandler::on_window_opened`][crate::engine::EngineEventHandler::on_window_opened].
+  WindowOpened,
+  /// See [`EngineEventHandler::on_window_closed`][crate::engine::EngineEventHandler::on_window_closed].
+  WindowClosed,
+  /// See [`EngineEventHandler::on_resized`][crate::engine::EngineEventHandler::on_resized].
+  Resized(PhysicalSize<u32>, f64),
+  /// See [`EngineEventHandler::on_resumed`][crate::engine::EngineEventHandler::on_resumed].
+  Resumed,
+  /// See [`EngineEventHandler::on_suspended`][crate::engine::EngineEventHandler::on_suspended].
+  Suspended,
+  /// See [`EngineEventHandler::on_close_requested`][crate::engine::EngineEventHandler::on_close_requested]. Short-circuiting this callback cancels closing the window.
+  CloseRequested
+}
+
+/// A reusable piece of behaviour which runs around an [`EngineEventHandler`][crate::engine::EngineEventHandler]. See the [`plugins`][crate::plugins] module.
+#[allow(unused_variables)]
+pub trait EnginePlugin<T: 'static = ()>: fmt::Debug + 'static {
+  /// Called before the handler's callback. Returning [`ControlFlow::Break`] skips the handler's callback,
+  /// along with the hooks of every plugin added after this one.
+  fn before(&mut self, context: EngineContext, callback: EngineCallback) -> ControlFlow<()> { ControlFlow::Continue(()) }
+  /// Called after the handler's callback, unless it was short-circuited by this plugin or one added before it.
+  fn after(&mut self, context: EngineContext, callback: EngineCallback) {}
+  /// Called instead of [`EnginePlugin::before`] for the handler's [`EngineEventHandler::on_user_event`][crate::engine::EngineEventHandler::on_user_event],
+  /// which takes the event by value. Returning [`ControlFlow::Break`] works like it does for [`EnginePlugin::before`],
+  /// and [`EnginePlugin::after`] is then called with [`EngineCallback::UserEvent`].
+  fn on_user_event(&mut self, context: EngineContext, event: &T) -> ControlFlow<()> { ControlFlow::Continue(()) }
+  /// Called before the handler's [`EngineEventHandler::on_window_open_failed`][crate::engine::EngineEventHandler::on_window_open_failed],
+  /// which has no window to give a context for.
+  fn on_window_open_failed(&mut self, error: &dyn Error) {}
+  /// Called after the handler's [`EngineEventHandler::should_exit`][crate::engine::EngineEventHandler::should_exit]
+  /// in the same order as after hooks, with whether the handler and the plugins asked so far want the application to exit.
+  /// Returns whether it should exit, which lets a plugin request exiting or prevent it.
+  fn should_exit(&self, window_state: &EngineWindowState, should_exit: bool) -> bool { should_exit }
+}
@@ -0,0 +1,90 @@
+//! Sending user events to an engine from other threads.
+//!
+//! An [`EngineProxy`] is obtained from [`Engine::proxy`][crate::engine::Engine::proxy],
+//! [`HeadlessEngine::proxy`][crate::headless::HeadlessEngine::proxy] or [`EngineContext::proxy`][crate::engine::EngineContext::proxy],
+//! and delivers events to [`EngineEventHandler::on_user_event`][crate::engine::EngineEventHandler::on_user_event],
+//! waking the event loop if it is waiting. Once the engine has exited, sending an event fails and hands the event back.
+
+use winit::event_loop::{EventLoopClosed, EventLoopProxy};
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, JoinHandle};
+
+
+
+/// The user events sent to a headless engine, which are delivered at the start of its next frame,
+/// or `None` once the engine has exited.
+pub(crate) type HeadlessUserEvents<T> = Mutex<Option<VecDeque<T>>>;
+
+/// A cloneable handle for sending user events to an engine, which can be moved to other threads if `T` is [`Send`].
+/// See the [`proxy`][crate::proxy] module.
+pub struct EngineProxy<T: 'static> {
+  inner: EngineProxyInner<T>
+}
+
+enum EngineProxyInner<T: 'static> {
+  EventLoop(EventLoopProxy<T>),
+  Headless(Weak<HeadlessUserEvents<T>>)
+}
+
+impl<T: 'static> EngineProxy<T> {
+  pub(crate) fn headless(user_events: &Arc<HeadlessUserEvents<T>>) -> Self {
+    EngineProxy { inner: EngineProxyInner::Headless(Arc::downgrade(user_events)) }
+  }
+
+  /// Sends a user event to the engine, waking its event loop.
+  /// Returns the event in an [`EventLoopClosed`] error if the engine has already exited.
+  pub fn send_event(&self, event: T) -> Result<(), EventLoopClosed<T>> {
+    match &self.inner {
+      EngineProxyInner::EventLoop(event_loop_proxy) => event_loop_proxy.send_event(event),
+      EngineProxyInner::Headless(user_events) => {
+        let Some(user_events) = user_events.upgrade() else { return Err(EventLoopClosed(event)) };
+        let mut user_events = user_events.lock().unwrap_or_else(|error| error.into_inner());
+        match user_events.as_mut() {
+          Some(user_events) => {
+            user_events.push_back(event);
+            Ok(())
+          },
+          None => Err(EventLoopClosed(event))
+        }
+      }
+    }
+  }
+
+  /// Runs a job on a new thread, sending its result to the engine as a user event once it finishes.
+  /// If the engine has exited by then, the result is returned through the join handle instead of being delivered.
+  pub fn spawn<F>(&self, job: F) -> JoinHandle<Result<(), EventLoopClosed<T>>>
+  where F: FnOnce() -> T + Send + 'static, T: Send {
+    let proxy = self.clone();
+    thread::spawn(move || proxy.send_event(job()))
+  }
+}
+
+impl<T: 'static> From<EventLoopProxy<T>> for EngineProxy<T> {
+  #[inline]
+  fn from(event_loop_proxy: EventLoopProxy<T>) -> Self {
+    EngineProxy { inner: EngineProxyInner::EventLoop(event_loop_proxy) }
+  }
+}
+
+impl<T: 'static> Clone for EngineProxy<T> {
+  fn clone(&self) -> Self {
+    let inner = match &self.inner {
+      EngineProxyInner::EventLoop(event_loop_proxy) => EngineProxyInner::EventLoop(event_loop_proxy.clone()),
+      EngineProxyInner::Headless(user_events) => EngineProxyInner::Headless(user_events.clone())
+    };
+
+    EngineProxy { inner }
+  }
+}
+
+impl<T: 'static> fmt::Debug for EngineProxy<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.inner {
+      EngineProxyInner::EventLoop(event_loop_proxy) => f.debug_tuple("EngineProxy").field(event_loop_proxy).finish(),
+      EngineProxyInner::Headless(..) => f.debug_tuple("EngineProxy").field(&format_args!("Headless")).finish()
+    }
+  }
+}
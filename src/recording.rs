@@ -0,0 +1,340 @@
+//! Recording sequences of rendered frames to disk.
+//!
+//! While a recording is active, the engine advances time by a constant simulated duration each frame,
+//! so recordings play back smoothly no matter how long each frame actually took to render and capture.
+//! [`HeadlessEngine`][crate::headless::HeadlessEngine]s always advance by their own frame duration instead.
+//! Captured frames are encoded and written on a background thread.
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageError, RgbaImage};
+use winit::window::WindowId;
+
+use crate::engine::{capture_screenshot, EngineCanvas};
+
+use std::fmt;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::num::NonZero;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+
+
+/// Where and how a recording's frames are written.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RecordingOutput {
+  /// Writes each frame as a separate PNG file in the given directory, which is created if necessary.
+  /// Files are named with the given prefix followed by the zero-padded frame number, such as `frame_00000.png`.
+  PngSequence {
+    directory: PathBuf,
+    file_prefix: String
+  },
+  /// Writes all frames into a single animated GIF file, optionally looping forever.
+  /// The file's parent directory is created if necessary.
+  Gif {
+    path: PathBuf,
+    repeat: bool
+  }
+}
+
+/// Configuration for recording the frames rendered to a window.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FrameRecorder {
+  output: RecordingOutput,
+  frame_duration: Duration,
+  capture_interval: NonZero<u32>
+}
+
+impl FrameRecorder {
+  /// The simulated duration of each frame by default, equivalent to 60 frames per second.
+  pub const DEFAULT_FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+  pub fn new(output: RecordingOutput) -> Self {
+    FrameRecorder {
+      output,
+      frame_duration: Self::DEFAULT_FRAME_DURATION,
+      capture_interval: NonZero::<u32>::MIN
+    }
+  }
+
+  /// Shortcut to creating a recorder with [`RecordingOutput::PngSequence`].
+  pub fn png_sequence(directory: impl Into<PathBuf>, file_prefix: impl Into<String>) -> Self {
+    Self::new(RecordingOutput::PngSequence { directory: directory.into(), file_prefix: file_prefix.into() })
+  }
+
+  /// Shortcut to creating a recorder with [`RecordingOutput::Gif`], looping forever.
+  pub fn gif(path: impl Into<PathBuf>) -> Self {
+    Self::new(RecordingOutput::Gif { path: path.into(), repeat: true })
+  }
+
+  /// Sets the amount of time that is simulated to elapse between each rendered frame while recording.
+  pub fn with_frame_duration(mut self, frame_duration: Duration) -> Self {
+    self.frame_duration = frame_duration;
+    self
+  }
+
+  /// Sets how many frames are rendered for every frame that is captured, so that only every Nth frame is recorded.
+  pub fn with_capture_interval(mut self, capture_interval: NonZero<u32>) -> Self {
+    self.capture_interval = capture_interval;
+    self
+  }
+
+  #[inline]
+  pub fn output(&self) -> &RecordingOutput {
+    &self.output
+  }
+
+  #[inline]
+  pub fn frame_duration(&self) -> Duration {
+    self.frame_duration
+  }
+
+  #[inline]
+  pub fn capture_interval(&self) -> NonZero<u32> {
+    self.capture_interval
+  }
+
+  /// The amount of simulated time between each captured frame.
+  pub fn captured_frame_duration(&self) -> Duration {
+    self.frame_duration * self.capture_interval.get()
+  }
+}
+
+/// An error that stopped a recording from being written.
+#[derive(Debug)]
+pub enum RecordingError {
+  Io(io::Error),
+  Image(ImageError),
+  /// A frame could not be read back from the canvas.
+  Capture(femtovg::ErrorKind),
+  /// The background thread writing the recording panicked.
+  WriterPanicked
+}
+
+impl fmt::Display for RecordingError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      RecordingError::Io(error) => write!(f, "failed to write recording: {error}"),
+      RecordingError::Image(error) => write!(f, "failed to encode recording: {error}"),
+      RecordingError::Capture(error) => write!(f, "failed to capture frame: {error}"),
+      RecordingError::WriterPanicked => f.write_str("recording writer thread panicked")
+    }
+  }
+}
+
+impl Error for RecordingError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      RecordingError::Io(error) => Some(error),
+      RecordingError::Image(error) => Some(error),
+      RecordingError::Capture(error) => Some(error),
+      RecordingError::WriterPanicked => None
+    }
+  }
+}
+
+impl From<io::Error> for RecordingError {
+  fn from(error: io::Error) -> Self {
+    RecordingError::Io(error)
+  }
+}
+
+impl From<ImageError> for RecordingError {
+  fn from(error: ImageError) -> Self {
+    RecordingError::Image(error)
+  }
+}
+
+
+
+/// The number of captured frames that may be waiting to be written before rendering blocks.
+const FRAME_QUEUE_LEN: usize = 4;
+
+/// An active recording of a window, which owns the background thread writing its frames.
+#[derive(Debug)]
+pub(crate) struct Recording {
+  window_id: WindowId,
+  frame_duration: Duration,
+  capture_interval: NonZero<u32>,
+  frames_rendered: u64,
+  frames_captured: u64,
+  capture_error: Option<femtovg::ErrorKind>,
+  sender: Option<SyncSender<RgbaImage>>,
+  writer: JoinHandle<Result<(), RecordingError>>
+}
+
+impl Recording {
+  /// Starts recording the given window, creating the output file or directory up front
+  /// so that any errors doing so are reported immediately.
+  pub(crate) fn start(recorder: FrameRecorder, window_id: WindowId) -> Result<Self, RecordingError> {
+    let (sender, receiver) = mpsc::sync_channel(FRAME_QUEUE_LEN);
+    let delay = Delay::from_saturating_duration(recorder.captured_frame_duration());
+    let writer = match recorder.output {
+      RecordingOutput::PngSequence { directory, file_prefix } => {
+        fs::create_dir_all(&directory)?;
+        thread::spawn(move || write_png_sequence(receiver, directory, file_prefix))
+      },
+      RecordingOutput::Gif { path, repeat } => {
+        if let Some(directory) = path.parent() {
+          fs::create_dir_all(directory)?;
+        };
+
+        let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+        if repeat {
+          encoder.set_repeat(Repeat::Infinite)?;
+        };
+
+        thread::spawn(move || write_gif(receiver, encoder, delay))
+      }
+    };
+
+    Ok(Recording {
+      window_id,
+      frame_duration: recorder.frame_duration,
+      capture_interval: recorder.capture_interval,
+      frames_rendered: 0,
+      frames_captured: 0,
+      capture_error: None,
+      sender: Some(sender),
+      writer
+    })
+  }
+
+  #[inline]
+  pub(crate) fn window_id(&self) -> WindowId {
+    self.window_id
+  }
+
+  #[inline]
+  pub(crate) fn frame_duration(&self) -> Duration {
+    self.frame_duration
+  }
+
+  /// Called after each frame of the recorded window has been flushed, capturing it if it falls on the capture interval.
+  pub(crate) fn record_frame(&mut self, canvas: &mut EngineCanvas) {
+    self.record_frame_with(|| capture_screenshot(canvas));
+  }
+
+  /// Like [`Recording::record_frame`], capturing the frame with the given function only if it falls on the capture interval.
+  fn record_frame_with(&mut self, capture: impl FnOnce() -> Result<RgbaImage, femtovg::ErrorKind>) {
+    let frame = self.frames_rendered;
+    self.frames_rendered += 1;
+    if !frame.is_multiple_of(self.capture_interval.get() as u64) || self.capture_error.is_some() {
+      return;
+    };
+
+    match capture() {
+      Ok(image) => {
+        // if the writer has stopped early, its error is reported once the recording is finished
+        if let Some(sender) = &self.sender && sender.send(image).is_ok() {
+          self.frames_captured += 1;
+        };
+      },
+      Err(error) => self.capture_error = Some(error)
+    };
+  }
+
+  /// Waits for all captured frames to be written, returning the number of frames captured.
+  pub(crate) fn finish(mut self) -> Result<u64, RecordingError> {
+    drop(self.sender.take());
+    self.writer.join().map_err(|_| RecordingError::WriterPanicked)??;
+    match self.capture_error {
+      Some(error) => Err(RecordingError::Capture(error)),
+      None => Ok(self.frames_captured)
+    }
+  }
+}
+
+fn write_png_sequence(receiver: Receiver<RgbaImage>, directory: PathBuf, file_prefix: String) -> Result<(), RecordingError> {
+  for (index, image) in receiver.into_iter().enumerate() {
+    image.save(directory.join(format!("{file_prefix}{index:05}.png")))?;
+  };
+
+  Ok(())
+}
+
+fn write_gif(receiver: Receiver<RgbaImage>, mut encoder: GifEncoder<BufWriter<File>>, delay: Delay) -> Result<(), RecordingError> {
+  for image in receiver {
+    encoder.encode_frame(Frame::from_parts(image, 0, 0, delay))?;
+  };
+
+  Ok(())
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use image::codecs::gif::GifDecoder;
+  use image::{AnimationDecoder, Rgba};
+
+  use super::*;
+
+  use std::io::BufReader;
+  use std::path::Path;
+
+  fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("platinum-recording-test-{}-{name}", std::process::id()))
+  }
+
+  fn record_frames(recording: &mut Recording, count: u8) {
+    for frame in 0..count {
+      recording.record_frame_with(|| Ok(RgbaImage::from_pixel(4, 4, Rgba([frame, 0, 0, 255]))));
+    };
+  }
+
+  fn file_names(directory: &Path) -> Vec<String> {
+    let mut names = fs::read_dir(directory).unwrap()
+      .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+      .collect::<Vec<_>>();
+    names.sort();
+    names
+  }
+
+  #[test]
+  fn png_sequences_write_every_captured_frame() {
+    let directory = temp_path("png");
+    let recorder = FrameRecorder::png_sequence(&directory, "frame_")
+      .with_capture_interval(NonZero::new(2).unwrap());
+    let mut recording = Recording::start(recorder, WindowId::dummy()).unwrap();
+    record_frames(&mut recording, 5);
+
+    assert_eq!(recording.finish().unwrap(), 3);
+    assert_eq!(file_names(&directory), ["frame_00000.png", "frame_00001.png", "frame_00002.png"]);
+    fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[test]
+  fn gifs_write_every_captured_frame() {
+    let directory = temp_path("gif");
+    let path = directory.join("recording.gif");
+    let mut recording = Recording::start(FrameRecorder::gif(&path), WindowId::dummy()).unwrap();
+    record_frames(&mut recording, 3);
+
+    assert_eq!(recording.finish().unwrap(), 3);
+    assert_eq!(file_names(&directory), ["recording.gif"]);
+    let decoder = GifDecoder::new(BufReader::new(File::open(&path).unwrap())).unwrap();
+    assert_eq!(decoder.into_frames().count(), 3);
+    fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[test]
+  fn finishing_reports_errors() {
+    let directory = temp_path("error");
+    let mut recording = Recording::start(FrameRecorder::png_sequence(&directory, "frame_"), WindowId::dummy()).unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+    record_frames(&mut recording, 1);
+    assert!(matches!(recording.finish(), Err(RecordingError::Image(_))));
+
+    let directory = temp_path("capture-error");
+    let mut recording = Recording::start(FrameRecorder::png_sequence(&directory, "frame_"), WindowId::dummy()).unwrap();
+    recording.record_frame_with(|| Err(femtovg::ErrorKind::UnknownError));
+    record_frames(&mut recording, 1);
+    assert!(matches!(recording.finish(), Err(RecordingError::Capture(femtovg::ErrorKind::UnknownError))));
+    assert!(file_names(&directory).is_empty());
+    fs::remove_dir_all(&directory).unwrap();
+  }
+}
@@ -0,0 +1,309 @@
+//! Recording the input of a window to a file, and replaying it deterministically.
+//!
+//! An [`InputRecorder`] writes one [`InputFrame`] per frame as a line of JSON, holding every [`InputEvent`]
+//! that the primary window received since the previous frame, along with the frame's number and timestamp.
+//! An [`InputReplay`] feeds those events back through [`WindowState::handle_input_event`][crate::windowing::WindowState::handle_input_event]
+//! at the start of the same frames, before [`EventHandler::update`][crate::windowing::EventHandler::update], and reproduces the recorded frame timing.
+//! While a replay is running, real input to the primary window is ignored.
+
+use serde::{Serialize, Deserialize};
+
+use crate::windowing::InputEvent;
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::mem::take;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+
+
+/// The input events received during a single frame of a recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputFrame {
+  /// The number of this frame, counting from zero at the start of the recording.
+  pub frame: u64,
+  /// The time at which this frame started, relative to the start of the recording.
+  pub time: Duration,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub events: Vec<InputEvent>
+}
+
+/// Writes the input of a window to a file, one frame at a time.
+/// See [`EngineBuilder::with_input_recorder`][crate::engine::EngineBuilder::with_input_recorder].
+#[derive(Debug)]
+pub struct InputRecorder {
+  writer: Option<BufWriter<File>>,
+  frame: u64,
+  start_instant: Option<Instant>,
+  events: Vec<InputEvent>
+}
+
+impl InputRecorder {
+  /// Creates a recorder which writes to the given file, replacing it if it exists.
+  pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+    Ok(InputRecorder {
+      writer: Some(BufWriter::new(File::create(path)?)),
+      frame: 0,
+      start_instant: None,
+      events: Vec::new()
+    })
+  }
+
+  /// The number of frames that have been recorded so far.
+  #[inline]
+  pub fn frames_recorded(&self) -> u64 {
+    self.frame
+  }
+
+  /// Whether the recorder is still writing, which stops being the case if writing to the file fails.
+  #[inline]
+  pub fn is_recording(&self) -> bool {
+    self.writer.is_some()
+  }
+
+  pub(crate) fn record_event(&mut self, event: InputEvent) {
+    if self.is_recording() {
+      self.events.push(event);
+    };
+  }
+
+  /// Writes the events received since the previous frame as a frame starting at the given instant.
+  pub(crate) fn record_frame(&mut self, frame_instant: Instant) {
+    let Some(writer) = &mut self.writer else { return };
+    let start_instant = *self.start_instant.get_or_insert(frame_instant);
+    let input_frame = InputFrame {
+      frame: self.frame,
+      time: frame_instant.saturating_duration_since(start_instant),
+      events: take(&mut self.events)
+    };
+
+    let result = serde_json::to_writer(&mut *writer, &input_frame)
+      .map_err(io::Error::from)
+      .and_then(|()| writer.write_all(b"\n"));
+    if let Err(error) = result {
+      log::warn!("failed to write input recording, stopping recording: {error}");
+      self.writer = None;
+    };
+
+    self.frame += 1;
+  }
+
+  /// Flushes everything recorded so far to the file, after which no more frames are recorded.
+  pub(crate) fn finish(&mut self) {
+    if let Some(mut writer) = self.writer.take() && let Err(error) = writer.flush() {
+      log::warn!("failed to write input recording: {error}");
+    };
+  }
+}
+
+/// Replays input recorded by an [`InputRecorder`].
+/// See [`EngineBuilder::with_input_replay`][crate::engine::EngineBuilder::with_input_replay].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputReplay {
+  frames: VecDeque<InputFrame>,
+  frame: u64,
+  start_instant: Option<Instant>,
+  exit_when_finished: bool
+}
+
+impl InputReplay {
+  /// Creates a replay of the given frames, which must be in order, although frames without events may be omitted.
+  pub fn new(frames: impl IntoIterator<Item = InputFrame>) -> Self {
+    InputReplay {
+      frames: frames.into_iter().collect(),
+      frame: 0,
+      start_instant: None,
+      exit_when_finished: false
+    }
+  }
+
+  /// Loads a replay from a file written by an [`InputRecorder`].
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut frames = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+      let line = line?;
+      if line.trim().is_empty() { continue };
+      let input_frame = serde_json::from_str(&line)
+        .map_err(|error| ReplayError::Parse { line: index + 1, error })?;
+      frames.push(input_frame);
+    };
+
+    Ok(Self::new(frames))
+  }
+
+  /// Configures the application to exit once every frame has been replayed,
+  /// which is useful for running recordings as regression tests.
+  pub fn with_exit_when_finished(mut self, exit_when_finished: bool) -> Self {
+    self.exit_when_finished = exit_when_finished;
+    self
+  }
+
+  #[inline]
+  pub fn exit_when_finished(&self) -> bool {
+    self.exit_when_finished
+  }
+
+  /// The number of frames that have been replayed so far.
+  #[inline]
+  pub fn frames_replayed(&self) -> u64 {
+    self.frame
+  }
+
+  /// Whether every recorded frame has been replayed.
+  #[inline]
+  pub fn is_finished(&self) -> bool {
+    self.frames.is_empty()
+  }
+
+  /// Advances to the next frame, returning its events if it was recorded,
+  /// along with when the frame should be considered to have started, given the current time.
+  pub(crate) fn next_frame(&mut self, now: Instant) -> Option<(Instant, Vec<InputEvent>)> {
+    let frame = self.frame;
+    self.frame += 1;
+
+    let input_frame = self.frames.front().filter(|input_frame| input_frame.frame <= frame)?;
+    let start_instant = *self.start_instant.get_or_insert_with(|| now.checked_sub(input_frame.time).unwrap_or(now));
+    let input_frame = self.frames.pop_front()?;
+    Some((start_instant + input_frame.time, input_frame.events))
+  }
+}
+
+/// An error produced when loading an [`InputReplay`].
+#[derive(Debug)]
+pub enum ReplayError {
+  Io(io::Error),
+  /// A line of the file is not a valid [`InputFrame`].
+  Parse {
+    line: usize,
+    error: serde_json::Error
+  }
+}
+
+impl fmt::Display for ReplayError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ReplayError::Io(error) => write!(f, "failed to read input recording: {error}"),
+      ReplayError::Parse { line, error } => write!(f, "invalid input frame on line {line}: {error}")
+    }
+  }
+}
+
+impl Error for ReplayError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      ReplayError::Io(error) => Some(error),
+      ReplayError::Parse { error, .. } => Some(error)
+    }
+  }
+}
+
+impl From<io::Error> for ReplayError {
+  fn from(error: io::Error) -> Self {
+    ReplayError::Io(error)
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use winit::dpi::PhysicalSize;
+  use winit::event::{DeviceId, ElementState, Ime};
+  use winit::keyboard::{Key, KeyCode, KeyLocation, PhysicalKey};
+  use winit::window::Window;
+
+  use crate::windowing::{EventHandler, FrameTiming, SyntheticKeyEvent, WindowState};
+
+  use super::*;
+
+  #[derive(Debug, PartialEq)]
+  enum Callback {
+    Key(PhysicalKey, ElementState),
+    Text(Ime)
+  }
+
+  #[derive(Default)]
+  struct TestHandler {
+    callbacks: Vec<Callback>
+  }
+
+  impl EventHandler<Window> for TestHandler {
+    fn update(&mut self, _: &WindowState<Window>) {}
+    fn render(&mut self, _: &WindowState<Window>) {}
+
+    fn on_synthetic_keyboard_input(&mut self, _: &WindowState<Window>, event: SyntheticKeyEvent) {
+      self.callbacks.push(Callback::Key(event.physical_key, event.state));
+    }
+
+    fn on_text_input(&mut self, _: &WindowState<Window>, event: Ime) {
+      self.callbacks.push(Callback::Text(event));
+    }
+  }
+
+  fn key_event(key_code: KeyCode, text: &str, state: ElementState) -> InputEvent {
+    InputEvent::Keyboard {
+      device_id: DeviceId::dummy(),
+      physical_key: PhysicalKey::Code(key_code),
+      logical_key: Key::Character(text.into()),
+      text: state.is_pressed().then(|| text.into()),
+      location: KeyLocation::Standard,
+      state,
+      repeat: false
+    }
+  }
+
+  #[test]
+  fn recording_round_trips_through_a_file() {
+    let path = std::env::temp_dir().join(format!("platinum-replay-test-{}.jsonl", std::process::id()));
+    let events = vec![
+      key_event(KeyCode::KeyA, "a", ElementState::Pressed),
+      InputEvent::Ime(Ime::Commit("é".to_owned())),
+      key_event(KeyCode::KeyA, "a", ElementState::Released)
+    ];
+
+    let start = Instant::now();
+    let mut input_recorder = InputRecorder::create(&path).unwrap();
+    input_recorder.record_frame(start);
+    for event in events.iter().cloned() {
+      input_recorder.record_event(event);
+    };
+
+    input_recorder.record_frame(start + Duration::from_millis(16));
+    input_recorder.record_frame(start + Duration::from_millis(33));
+    input_recorder.finish();
+    assert_eq!(input_recorder.frames_recorded(), 3);
+
+    let mut input_replay = InputReplay::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let replay_start = Instant::now();
+    let mut frames = Vec::new();
+    while !input_replay.is_finished() {
+      frames.push(input_replay.next_frame(replay_start).unwrap());
+    };
+
+    let times = frames.iter().map(|(frame_instant, _)| frame_instant.duration_since(frames[0].0)).collect::<Vec<_>>();
+    assert_eq!(times, [Duration::ZERO, Duration::from_millis(16), Duration::from_millis(33)]);
+    assert!(frames[0].1.is_empty());
+    assert_eq!(frames[1].1, events);
+    assert!(frames[2].1.is_empty());
+
+    let mut window_state = WindowState::<Window>::new_headless(PhysicalSize::new(640, 480), 1.0, None, FrameTiming::DEFAULT_HISTORY_LEN);
+    let mut handler = TestHandler::default();
+    for event in frames.into_iter().flat_map(|(_, events)| events) {
+      window_state.handle_input_event(&mut handler, event);
+    };
+
+    assert_eq!(handler.callbacks, [
+      Callback::Key(PhysicalKey::Code(KeyCode::KeyA), ElementState::Pressed),
+      Callback::Text(Ime::Commit("é".to_owned())),
+      Callback::Key(PhysicalKey::Code(KeyCode::KeyA), ElementState::Released)
+    ]);
+    assert_eq!(window_state.input().text(), "aé");
+  }
+}
@@ -0,0 +1,527 @@
+//! A stack of scenes, such as a title screen, gameplay, a pause menu and settings, managed by a [`SceneManager`].
+//!
+//! The [`SceneManager`] is an [`EngineEventHandler`] which forwards updates, rendering and input to the scene on top of its stack.
+//! Scenes request transitions through the [`SceneCommands`] they are given, which are applied once the callback returns:
+//! pushing a scene pauses the one beneath it, and popping a scene resumes the one beneath it.
+//! Overlay scenes are drawn over the scenes beneath them, and transitions can cross-fade from the previous frame,
+//! in which case they are applied at the start of the next render, once the scenes before them have been captured.
+
+use femtovg::{Color, ImageFlags, ImageId, Paint, Path, PixelFormat, RenderTarget};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{DeviceEvent, DeviceId, ElementState, Ime, KeyEvent, MouseButton, MouseScrollDelta, Touch};
+use winit::window::WindowId;
+
+use crate::engine::{EngineContext, EngineEventHandler, EngineWindowState};
+use crate::gamepad::{GamepadAxis, GamepadButton, GamepadId};
+use crate::windowing::{AxisMotion, Gesture, SyntheticKeyEvent};
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+
+
+/// A state of the application, such as a title screen or pause menu, which is run by a [`SceneManager`] while on top of its stack.
+#[allow(unused_variables)]
+pub trait Scene<T: 'static = ()>: fmt::Debug + 'static {
+  /// Called when the scene is pushed onto the stack, or when it is the initial scene and the handler is initialized.
+  fn on_enter(&mut self, context: EngineContext) {}
+  /// Called when the scene is popped or replaced.
+  fn on_exit(&mut self, context: EngineContext) {}
+  /// Called when another scene is pushed on top of this one.
+  fn on_pause(&mut self, context: EngineContext) {}
+  /// Called when this scene is on top of the stack again after the scene above it was popped.
+  fn on_resume(&mut self, context: EngineContext) {}
+  /// See [`EngineEventHandler::update`].
+  fn update(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>);
+  /// See [`EngineEventHandler::render`]. Scenes may be rendered more than once per frame while a cross-fade starts.
+  fn render(&mut self, context: EngineContext);
+  /// Whether the scene beneath this one should be rendered first, such as for a pause menu drawn over gameplay.
+  fn is_overlay(&self) -> bool { false }
+  /// See [`EngineEventHandler::on_resized`]. Unlike other callbacks, this is called for every scene in the stack.
+  fn on_resized(&mut self, context: EngineContext, window_size: PhysicalSize<u32>, scale_factor: f64) {}
+  /// See [`EngineEventHandler::on_user_event`].
+  fn on_user_event(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, event: T) {}
+  /// See [`EngineEventHandler::on_device_event`].
+  fn on_device_event(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, id: DeviceId, event: DeviceEvent) {}
+  /// See [`EngineEventHandler::on_keyboard_input`].
+  fn on_keyboard_input(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, event: KeyEvent) {}
+  /// See [`EngineEventHandler::on_synthetic_keyboard_input`].
+  fn on_synthetic_keyboard_input(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, event: SyntheticKeyEvent) {}
+  /// See [`EngineEventHandler::on_text_input`].
+  fn on_text_input(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, event: Ime) {}
+  /// See [`EngineEventHandler::on_cursor_moved`].
+  fn on_cursor_moved(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, pos: PhysicalPosition<f32>) {}
+  /// See [`EngineEventHandler::on_cursor_entered`].
+  fn on_cursor_entered(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>) {}
+  /// See [`EngineEventHandler::on_cursor_left`].
+  fn on_cursor_left(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>) {}
+  /// See [`EngineEventHandler::on_mouse_input`].
+  fn on_mouse_input(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, state: ElementState, button: MouseButton) {}
+  /// See [`EngineEventHandler::on_mouse_scroll`].
+  fn on_mouse_scroll(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, delta: MouseScrollDelta) {}
+  /// See [`EngineEventHandler::on_gesture`].
+  fn on_gesture(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, gesture: Gesture) {}
+  /// See [`EngineEventHandler::on_touch`].
+  fn on_touch(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, touch: Touch) {}
+  /// See [`EngineEventHandler::on_axis_motion`].
+  fn on_axis_motion(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, axis_motion: AxisMotion) {}
+  /// See [`EngineEventHandler::on_gamepad_connected`].
+  fn on_gamepad_connected(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, id: GamepadId) {}
+  /// See [`EngineEventHandler::on_gamepad_disconnected`].
+  fn on_gamepad_disconnected(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, id: GamepadId) {}
+  /// See [`EngineEventHandler::on_gamepad_button`].
+  fn on_gamepad_button(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, id: GamepadId, button: GamepadButton, state: ElementState) {}
+  /// See [`EngineEventHandler::on_gamepad_axis`].
+  fn on_gamepad_axis(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, id: GamepadId, axis: GamepadAxis, value: f32) {}
+  /// See [`EngineEventHandler::on_focus_changed`].
+  fn on_focus_changed(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, state: bool) {}
+  /// See [`EngineEventHandler::on_file_over`].
+  fn on_file_over(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>, path: Option<PathBuf>, dropped: bool) {}
+  /// See [`EngineEventHandler::on_close_requested`]. Returning `false` cancels closing the window.
+  fn on_close_requested(&mut self, context: EngineContext, scenes: &mut SceneCommands<T>) -> bool { true }
+}
+
+enum SceneCommand<T: 'static> {
+  Push(Box<dyn Scene<T>>),
+  Pop,
+  Replace(Box<dyn Scene<T>>)
+}
+
+impl<T: 'static> fmt::Debug for SceneCommand<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      SceneCommand::Push(scene) => f.debug_tuple("Push").field(scene).finish(),
+      SceneCommand::Pop => f.write_str("Pop"),
+      SceneCommand::Replace(scene) => f.debug_tuple("Replace").field(scene).finish()
+    }
+  }
+}
+
+/// Transitions requested by a scene during a callback, which are applied in order once the callback returns.
+#[derive(Debug)]
+pub struct SceneCommands<T: 'static = ()> {
+  commands: Vec<SceneCommand<T>>,
+  cross_fade: Option<Duration>,
+  depth: usize
+}
+
+impl<T: 'static> SceneCommands<T> {
+  fn new(depth: usize) -> Self {
+    SceneCommands { commands: Vec::new(), cross_fade: None, depth }
+  }
+
+  /// Pushes a scene on top of the current one, which is paused until the new scene is popped.
+  pub fn push(&mut self, scene: impl Scene<T>) {
+    self.commands.push(SceneCommand::Push(Box::new(scene)));
+  }
+
+  /// Pops the current scene, resuming the one beneath it.
+  /// Once the last scene has been popped, the scene manager asks the application to exit.
+  pub fn pop(&mut self) {
+    self.commands.push(SceneCommand::Pop);
+  }
+
+  /// Replaces the current scene with another, without resuming the scene beneath it in between.
+  pub fn replace(&mut self, scene: impl Scene<T>) {
+    self.commands.push(SceneCommand::Replace(Box::new(scene)));
+  }
+
+  /// Cross-fades from the scenes as they were before this callback's transitions over the given duration.
+  /// The transitions are then applied at the start of the next render, once those scenes have been captured.
+  pub fn cross_fade(&mut self, duration: Duration) {
+    self.cross_fade = Some(duration);
+  }
+
+  /// The number of scenes in the stack before this callback's transitions are applied,
+  /// not counting transitions still waiting for a cross-fade to start.
+  #[inline]
+  pub fn depth(&self) -> usize {
+    self.depth
+  }
+}
+
+/// The hooks called on scenes as transitions are applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SceneHook {
+  Enter,
+  Exit,
+  Pause,
+  Resume
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CrossFade {
+  image: ImageId,
+  window_id: WindowId,
+  duration: Duration,
+  /// The start of the first frame rendered after the cross-fade began.
+  started_at: Option<Instant>
+}
+
+/// An [`EngineEventHandler`] which runs a stack of [`Scene`]s. See the [`scenes`][crate::scenes] module.
+///
+/// The scene on top of the stack receives updates and input, and is rendered along with any scenes beneath it
+/// that it overlays. Once the stack is empty, [`EngineEventHandler::should_exit`] returns `true`.
+pub struct SceneManager<T: 'static = ()> {
+  scenes: Vec<Box<dyn Scene<T>>>,
+  /// Transitions waiting for the next render to capture the scenes before them, which then cross-fades from them.
+  pending: Option<SceneCommands<T>>,
+  cross_fade: Option<CrossFade>
+}
+
+impl<T: 'static> SceneManager<T> {
+  /// Creates a scene manager with the given initial scene, which is entered once the handler is initialized.
+  pub fn new(scene: impl Scene<T>) -> Self {
+    SceneManager { scenes: vec![Box::new(scene)], pending: None, cross_fade: None }
+  }
+
+  /// The number of scenes in the stack.
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.scenes.len()
+  }
+
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.scenes.is_empty()
+  }
+
+  /// Returns the scene on top of the stack, if there is one.
+  pub fn top(&self) -> Option<&dyn Scene<T>> {
+    self.scenes.last().map(Box::as_ref)
+  }
+
+  /// Whether a cross-fade is currently in progress, or waiting for the next render to start.
+  #[inline]
+  pub fn is_cross_fading(&self) -> bool {
+    self.cross_fade.is_some() || self.pending.is_some()
+  }
+
+  /// Applies the transitions requested during a callback, unless they cross-fade,
+  /// in which case they wait for the next render so that it can capture the scenes before them at the right size.
+  fn apply(&mut self, context: EngineContext, commands: SceneCommands<T>) {
+    if commands.commands.is_empty() { return };
+    match self.defer(commands) {
+      Some(commands) => self.apply_now(context, commands),
+      None => context.window_state.request_redraw()
+    };
+  }
+
+  /// Holds back transitions which cross-fade until the next render, returning any transitions to apply right away.
+  fn defer(&mut self, commands: SceneCommands<T>) -> Option<SceneCommands<T>> {
+    match &mut self.pending {
+      // transitions requested after a pending cross-fade are applied after it, keeping their order
+      Some(pending) => {
+        pending.commands.extend(commands.commands);
+        pending.cross_fade = commands.cross_fade.or(pending.cross_fade);
+        None
+      },
+      None if commands.cross_fade.is_some() => {
+        self.pending = Some(commands);
+        None
+      },
+      None => Some(commands)
+    }
+  }
+
+  fn apply_now(&mut self, mut context: EngineContext, commands: SceneCommands<T>) {
+    self.apply_commands(commands, |scene, hook| match hook {
+      SceneHook::Enter => scene.on_enter(context.reborrow()),
+      SceneHook::Exit => scene.on_exit(context.reborrow()),
+      SceneHook::Pause => scene.on_pause(context.reborrow()),
+      SceneHook::Resume => scene.on_resume(context.reborrow())
+    });
+  }
+
+  /// Applies transitions to the stack in order, calling the given function for each hook of the scenes involved.
+  fn apply_commands(&mut self, commands: SceneCommands<T>, mut call_hook: impl FnMut(&mut dyn Scene<T>, SceneHook)) {
+    for command in commands.commands {
+      match command {
+        SceneCommand::Push(mut scene) => {
+          if let Some(top) = self.scenes.last_mut() {
+            call_hook(top.as_mut(), SceneHook::Pause);
+          };
+
+          call_hook(scene.as_mut(), SceneHook::Enter);
+          self.scenes.push(scene);
+        },
+        SceneCommand::Pop => {
+          if let Some(mut scene) = self.scenes.pop() {
+            call_hook(scene.as_mut(), SceneHook::Exit);
+          };
+
+          if let Some(top) = self.scenes.last_mut() {
+            call_hook(top.as_mut(), SceneHook::Resume);
+          };
+        },
+        SceneCommand::Replace(mut scene) => {
+          if let Some(mut previous_scene) = self.scenes.pop() {
+            call_hook(previous_scene.as_mut(), SceneHook::Exit);
+          };
+
+          call_hook(scene.as_mut(), SceneHook::Enter);
+          self.scenes.push(scene);
+        }
+      };
+    };
+  }
+
+  /// Renders the scene on top of the stack, preceded by the scenes beneath it that it overlays.
+  fn render_scenes(&mut self, mut context: EngineContext) {
+    let bottom = self.scenes.iter()
+      .rposition(|scene| !scene.is_overlay())
+      .unwrap_or(0);
+    for scene in &mut self.scenes[bottom..] {
+      scene.render(context.reborrow());
+    };
+  }
+
+  /// Renders the scenes as they are before a transition into an image, which is then faded out over the scenes after it.
+  fn start_cross_fade(&mut self, mut context: EngineContext, duration: Duration) {
+    let canvas = &mut *context.canvas;
+    if let Some(cross_fade) = self.cross_fade.take() {
+      canvas.delete_image(cross_fade.image);
+    };
+
+    let (width, height) = (canvas.width(), canvas.height());
+    if duration.is_zero() || width == 0 || height == 0 { return };
+    let flags = ImageFlags::FLIP_Y | ImageFlags::PREMULTIPLIED;
+    let image = match canvas.create_image_empty(width as usize, height as usize, PixelFormat::Rgba8, flags) {
+      Ok(image) => image,
+      Err(error) => {
+        log::warn!("failed to create image for scene cross-fade, skipping it: {error}");
+        return;
+      }
+    };
+
+    canvas.set_render_target(RenderTarget::Image(image));
+    canvas.clear_rect(0, 0, width, height, Color::rgba(0, 0, 0, 0));
+    self.render_scenes(context.reborrow());
+    context.canvas.set_render_target(RenderTarget::Screen);
+
+    let window_id = context.window_state.id();
+    self.cross_fade = Some(CrossFade { image, window_id, duration, started_at: None });
+  }
+
+  /// Draws the image of the previous scenes over the current ones, finishing the cross-fade once its duration has elapsed.
+  fn render_cross_fade(&mut self, context: EngineContext) {
+    let Some(cross_fade) = &mut self.cross_fade else { return };
+    if cross_fade.window_id != context.window_state.id() { return };
+    let Some(now) = context.window_state.frame_timing().frame_instant() else { return };
+
+    let started_at = *cross_fade.started_at.get_or_insert(now);
+    let progress = now.saturating_duration_since(started_at).as_secs_f32() / cross_fade.duration.as_secs_f32();
+    let canvas = context.canvas;
+    if progress >= 1.0 {
+      canvas.delete_image(cross_fade.image);
+      self.cross_fade = None;
+      return;
+    };
+
+    let (width, height) = (canvas.width() as f32, canvas.height() as f32);
+    let mut path = Path::new();
+    path.rect(0.0, 0.0, width, height);
+    canvas.save();
+    canvas.reset_transform();
+    canvas.fill_path(&path, &Paint::image(cross_fade.image, 0.0, 0.0, width, height, 0.0, 1.0 - progress));
+    canvas.restore();
+    context.window_state.request_redraw();
+  }
+}
+
+macro_rules! forward_to_top_scene {
+  ($(fn $name:ident(&mut self $(, $arg:ident : $Arg:ty)* $(,)?);)*) => ($(
+    fn $name(&mut self, mut context: EngineContext, $($arg: $Arg),*) {
+      let mut commands = SceneCommands::new(self.scenes.len());
+      if let Some(scene) = self.scenes.last_mut() {
+        scene.$name(context.reborrow(), &mut commands, $($arg),*);
+      };
+
+      self.apply(context, commands);
+    }
+  )*);
+}
+
+impl<T: 'static> EngineEventHandler<T> for SceneManager<T> {
+  fn init(&mut self, context: EngineContext) {
+    if let Some(scene) = self.scenes.last_mut() {
+      scene.on_enter(context);
+    };
+  }
+
+  fn render(&mut self, mut context: EngineContext) {
+    if let Some(pending) = self.pending.take() {
+      if let Some(duration) = pending.cross_fade {
+        self.start_cross_fade(context.reborrow(), duration);
+      };
+
+      self.apply_now(context.reborrow(), pending);
+    };
+
+    self.render_scenes(context.reborrow());
+    self.render_cross_fade(context);
+  }
+
+  fn on_resized(&mut self, mut context: EngineContext, window_size: PhysicalSize<u32>, scale_factor: f64) {
+    for scene in &mut self.scenes {
+      scene.on_resized(context.reborrow(), window_size, scale_factor);
+    };
+  }
+
+  forward_to_top_scene! {
+    fn update(&mut self);
+    fn on_user_event(&mut self, event: T);
+    fn on_keyboard_input(&mut self, event: KeyEvent);
+    fn on_synthetic_keyboard_input(&mut self, event: SyntheticKeyEvent);
+    fn on_text_input(&mut self, event: Ime);
+    fn on_cursor_moved(&mut self, pos: PhysicalPosition<f32>);
+    fn on_cursor_entered(&mut self);
+    fn on_cursor_left(&mut self);
+    fn on_mouse_input(&mut self, state: ElementState, button: MouseButton);
+    fn on_mouse_scroll(&mut self, delta: MouseScrollDelta);
+    fn on_gesture(&mut self, gesture: Gesture);
+    fn on_touch(&mut self, touch: Touch);
+    fn on_axis_motion(&mut self, axis_motion: AxisMotion);
+    fn on_gamepad_connected(&mut self, id: GamepadId);
+    fn on_gamepad_disconnected(&mut self, id: GamepadId);
+    fn on_gamepad_button(&mut self, id: GamepadId, button: GamepadButton, state: ElementState);
+    fn on_gamepad_axis(&mut self, id: GamepadId, axis: GamepadAxis, value: f32);
+    fn on_focus_changed(&mut self, state: bool);
+    fn on_device_event(&mut self, id: DeviceId, event: DeviceEvent);
+    fn on_file_over(&mut self, path: Option<PathBuf>, dropped: bool);
+  }
+
+  fn on_close_requested(&mut self, mut context: EngineContext) -> bool {
+    let mut commands = SceneCommands::new(self.scenes.len());
+    let close = self.scenes.last_mut().is_none_or(|scene| scene.on_close_requested(context.reborrow(), &mut commands));
+    self.apply(context, commands);
+    close
+  }
+
+  fn should_exit(&self, _: &EngineWindowState) -> bool {
+    self.scenes.is_empty()
+  }
+}
+
+impl<T: 'static> fmt::Debug for SceneManager<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("SceneManager")
+      .field("scenes", &self.scenes)
+      .field("pending", &self.pending.as_ref().map(|pending| &pending.commands))
+      .field("cross_fade", &self.cross_fade)
+      .finish()
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use winit::window::Window;
+
+  use crate::windowing::{FrameTiming, WindowState};
+
+  use super::*;
+
+  use std::rc::Rc;
+
+  /// A scene which only records the hooks called on it, identified by its name.
+  struct RecordingScene(&'static str);
+
+  impl Scene for RecordingScene {
+    fn update(&mut self, _: EngineContext, _: &mut SceneCommands) {}
+    fn render(&mut self, _: EngineContext) {}
+  }
+
+  impl fmt::Debug for RecordingScene {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      f.write_str(self.0)
+    }
+  }
+
+  fn commands(f: impl FnOnce(&mut SceneCommands)) -> SceneCommands {
+    let mut commands = SceneCommands::new(0);
+    f(&mut commands);
+    commands
+  }
+
+  /// Applies the transitions right away, returning the hooks that were called in order.
+  fn apply(scene_manager: &mut SceneManager, commands: SceneCommands) -> Vec<(String, SceneHook)> {
+    let mut hooks = Vec::new();
+    scene_manager.apply_commands(commands, |scene, hook| hooks.push((format!("{scene:?}"), hook)));
+    hooks
+  }
+
+  fn scene_names(scene_manager: &SceneManager) -> Vec<String> {
+    scene_manager.scenes.iter().map(|scene| format!("{scene:?}")).collect()
+  }
+
+  fn hooks(expected: &[(&str, SceneHook)]) -> Vec<(String, SceneHook)> {
+    expected.iter().map(|&(name, hook)| (name.to_owned(), hook)).collect()
+  }
+
+  #[test]
+  fn transitions_call_hooks_in_order() {
+    let mut scene_manager = SceneManager::new(RecordingScene("game"));
+    let called = apply(&mut scene_manager, commands(|scenes| scenes.push(RecordingScene("pause"))));
+    assert_eq!(called, hooks(&[("game", SceneHook::Pause), ("pause", SceneHook::Enter)]));
+
+    let called = apply(&mut scene_manager, commands(|scenes| scenes.replace(RecordingScene("settings"))));
+    assert_eq!(called, hooks(&[("pause", SceneHook::Exit), ("settings", SceneHook::Enter)]));
+    assert_eq!(scene_names(&scene_manager), ["game", "settings"]);
+
+    let called = apply(&mut scene_manager, commands(|scenes| {
+      scenes.pop();
+      scenes.push(RecordingScene("title"));
+    }));
+    assert_eq!(called, hooks(&[
+      ("settings", SceneHook::Exit),
+      ("game", SceneHook::Resume),
+      ("game", SceneHook::Pause),
+      ("title", SceneHook::Enter)
+    ]));
+    assert_eq!(scene_names(&scene_manager), ["game", "title"]);
+  }
+
+  #[test]
+  fn transitions_wait_behind_a_pending_cross_fade() {
+    let mut scene_manager = SceneManager::new(RecordingScene("title"));
+    let cross_fade = commands(|scenes| {
+      scenes.replace(RecordingScene("game"));
+      scenes.cross_fade(Duration::from_millis(500));
+    });
+    assert!(scene_manager.defer(cross_fade).is_none());
+    assert!(scene_manager.defer(commands(|scenes| scenes.push(RecordingScene("pause")))).is_none());
+    assert!(scene_manager.is_cross_fading());
+    assert_eq!(scene_names(&scene_manager), ["title"]);
+
+    // the next render applies every pending transition in the order they were requested
+    let pending = scene_manager.pending.take().unwrap();
+    assert_eq!(pending.cross_fade, Some(Duration::from_millis(500)));
+    let called = apply(&mut scene_manager, pending);
+    assert_eq!(called, hooks(&[
+      ("title", SceneHook::Exit),
+      ("game", SceneHook::Enter),
+      ("game", SceneHook::Pause),
+      ("pause", SceneHook::Enter)
+    ]));
+
+    // transitions without a cross-fade are applied right away once nothing is pending
+    assert!(scene_manager.defer(commands(SceneCommands::pop)).is_some());
+  }
+
+  #[test]
+  fn popping_the_last_scene_exits() {
+    let window_state = WindowState::<Rc<Window>>::new_headless(PhysicalSize::new(640, 480), 1.0, None, FrameTiming::DEFAULT_HISTORY_LEN);
+    let mut scene_manager = SceneManager::new(RecordingScene("title"));
+    assert!(!scene_manager.should_exit(&window_state));
+
+    let called = apply(&mut scene_manager, commands(|scenes| {
+      scenes.pop();
+      scenes.pop();
+    }));
+    assert_eq!(called, hooks(&[("title", SceneHook::Exit)]));
+    assert!(scene_manager.is_empty() && scene_manager.should_exit(&window_state));
+  }
+}
@@ -0,0 +1,750 @@
+//! Keyboard shortcuts and multi-step chords.
+//!
+//! A [`Shortcut`] is a key pressed while holding an exact set of modifiers, and can be parsed from strings such as `"Ctrl+Shift+S"`.
+//! A [`Chord`] is a sequence of shortcuts pressed one after another, such as `"Ctrl+K, Ctrl+C"`.
+//! Each frame, [`ShortcutMap::update`] matches the keys pressed in an [`InputState`] against the chords bound to each action,
+//! after which [`ShortcutMap::triggered`] lists the actions whose chords were completed.
+//!
+//! The `Primary` modifier (also written `CmdOrCtrl`) stands for Cmd on macOS and Ctrl everywhere else,
+//! so that the same bindings follow each platform's conventions.
+
+use ahash::AHashMap;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use winit::keyboard::{Key as LogicalKey, KeyCode, ModifiersState, NamedKey, PhysicalKey, SmolStr};
+
+use crate::windowing::{InputState, KeyAction, KeyActionState};
+
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+
+
+/// The key of a [`Shortcut`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ShortcutKey {
+  /// A key at a physical location on the keyboard, regardless of layout. Written as a key code name with a `Code:` prefix,
+  /// such as `Code:KeyS` and `Code:Enter`, though the prefix may be left out if the name does not also name another key.
+  Physical(KeyCode),
+  /// A named key produced by the current keyboard layout, such as `Enter` or `F5`.
+  Named(NamedKey),
+  /// A character produced by the current keyboard layout, matched case-insensitively.
+  Character(SmolStr)
+}
+
+impl ShortcutKey {
+  /// Creates a character key, which is stored in lowercase.
+  /// Whitespace characters are produced by named keys instead, so they create those named keys.
+  pub fn character(character: &str) -> Self {
+    match character {
+      " " => ShortcutKey::Named(NamedKey::Space),
+      "\t" => ShortcutKey::Named(NamedKey::Tab),
+      "\r" | "\n" => ShortcutKey::Named(NamedKey::Enter),
+      _ => ShortcutKey::Character(SmolStr::new(character.to_lowercase()))
+    }
+  }
+
+  fn matches(&self, key_action: &KeyAction) -> bool {
+    match self {
+      ShortcutKey::Physical(key_code) => key_action.physical_key == PhysicalKey::Code(*key_code),
+      ShortcutKey::Named(named_key) => key_action.logical_key == LogicalKey::Named(*named_key),
+      ShortcutKey::Character(character) => match &key_action.logical_key {
+        LogicalKey::Character(c) => c.to_lowercase() == character.as_str(),
+        _ => false
+      }
+    }
+  }
+}
+
+impl From<KeyCode> for ShortcutKey {
+  #[inline]
+  fn from(key_code: KeyCode) -> Self {
+    ShortcutKey::Physical(key_code)
+  }
+}
+
+impl From<NamedKey> for ShortcutKey {
+  #[inline]
+  fn from(named_key: NamedKey) -> Self {
+    ShortcutKey::Named(named_key)
+  }
+}
+
+impl From<char> for ShortcutKey {
+  #[inline]
+  fn from(character: char) -> Self {
+    ShortcutKey::character(character.encode_utf8(&mut [0; 4]))
+  }
+}
+
+impl FromStr for ShortcutKey {
+  type Err = ParseShortcutError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut chars = s.chars();
+    if let (Some(character), None) = (chars.next(), chars.next()) {
+      return Ok(ShortcutKey::from(character));
+    };
+
+    let unknown_key = || ParseShortcutError::UnknownKey(s.to_owned());
+    if let Some((prefix, name)) = s.split_at_checked(PHYSICAL_KEY_PREFIX.len()) && prefix.eq_ignore_ascii_case(PHYSICAL_KEY_PREFIX) {
+      return find_key(KEY_CODES, name).map(ShortcutKey::Physical).ok_or_else(unknown_key);
+    };
+
+    if let Some(named_key) = find_key(NAMED_KEYS, s).or_else(|| find_key(NAMED_KEY_ALIASES, s)) {
+      return Ok(ShortcutKey::Named(named_key));
+    };
+
+    if let Some(character) = find_key(CHARACTER_ALIASES, s) {
+      return Ok(ShortcutKey::from(character));
+    };
+
+    find_key(KEY_CODES, s).map(ShortcutKey::Physical).ok_or_else(unknown_key)
+  }
+}
+
+impl fmt::Display for ShortcutKey {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ShortcutKey::Physical(key_code) => write!(f, "{PHYSICAL_KEY_PREFIX}{key_code:?}"),
+      ShortcutKey::Named(named_key) => write!(f, "{named_key:?}"),
+      ShortcutKey::Character(character) => match CHARACTER_ALIASES.iter().find(|&&(_, c)| character.starts_with(c) && character.len() == c.len_utf8()) {
+        Some((name, _)) => f.write_str(name),
+        // characters whose uppercase form is longer, such as "ß", are written as they are so that they parse back
+        None => match character.to_uppercase() {
+          uppercase if uppercase.chars().count() == 1 => f.write_str(&uppercase),
+          _ => f.write_str(character)
+        }
+      }
+    }
+  }
+}
+
+/// Written before the names of physical keys, which would otherwise be ambiguous with the names of named keys.
+const PHYSICAL_KEY_PREFIX: &str = "Code:";
+
+macro_rules! key_names {
+  ($Key:ident { $($name:ident),* }) => (&[$((stringify!($name), $Key::$name)),*]);
+}
+
+/// The name of every named key, which is also how they are written.
+const NAMED_KEYS: &[(&str, NamedKey)] = key_names!(NamedKey {
+  Alt, AltGraph, CapsLock, Control, Fn, FnLock, NumLock, ScrollLock, Shift, Symbol, SymbolLock, Meta, Hyper, Super, Enter, Tab, Space,
+  ArrowDown, ArrowLeft, ArrowRight, ArrowUp, End, Home, PageDown, PageUp, Backspace, Clear, Copy, CrSel, Cut, Delete, EraseEof, ExSel,
+  Insert, Paste, Redo, Undo, Accept, Again, Attn, Cancel, ContextMenu, Escape, Execute, Find, Help, Pause, Play, Props, Select, ZoomIn,
+  ZoomOut, BrightnessDown, BrightnessUp, Eject, LogOff, Power, PowerOff, PrintScreen, Hibernate, Standby, WakeUp, AllCandidates,
+  Alphanumeric, CodeInput, Compose, Convert, FinalMode, GroupFirst, GroupLast, GroupNext, GroupPrevious, ModeChange, NextCandidate,
+  NonConvert, PreviousCandidate, Process, SingleCandidate, HangulMode, HanjaMode, JunjaMode, Eisu, Hankaku, Hiragana, HiraganaKatakana,
+  KanaMode, KanjiMode, Katakana, Romaji, Zenkaku, ZenkakuHankaku, Soft1, Soft2, Soft3, Soft4, ChannelDown, ChannelUp, Close, MailForward,
+  MailReply, MailSend, MediaClose, MediaFastForward, MediaPause, MediaPlay, MediaPlayPause, MediaRecord, MediaRewind, MediaStop,
+  MediaTrackNext, MediaTrackPrevious, New, Open, Print, Save, SpellCheck, Key11, Key12, AudioBalanceLeft, AudioBalanceRight,
+  AudioBassBoostDown, AudioBassBoostToggle, AudioBassBoostUp, AudioFaderFront, AudioFaderRear, AudioSurroundModeNext, AudioTrebleDown,
+  AudioTrebleUp, AudioVolumeDown, AudioVolumeUp, AudioVolumeMute, MicrophoneToggle, MicrophoneVolumeDown, MicrophoneVolumeUp,
+  MicrophoneVolumeMute, SpeechCorrectionList, SpeechInputToggle, LaunchApplication1, LaunchApplication2, LaunchCalendar, LaunchContacts,
+  LaunchMail, LaunchMediaPlayer, LaunchMusicPlayer, LaunchPhone, LaunchScreenSaver, LaunchSpreadsheet, LaunchWebBrowser, LaunchWebCam,
+  LaunchWordProcessor, BrowserBack, BrowserFavorites, BrowserForward, BrowserHome, BrowserRefresh, BrowserSearch, BrowserStop, AppSwitch,
+  Call, Camera, CameraFocus, EndCall, GoBack, GoHome, HeadsetHook, LastNumberRedial, Notification, MannerMode, VoiceDial, TV, TV3DMode,
+  TVAntennaCable, TVAudioDescription, TVAudioDescriptionMixDown, TVAudioDescriptionMixUp, TVContentsMenu, TVDataService, TVInput,
+  TVInputComponent1, TVInputComponent2, TVInputComposite1, TVInputComposite2, TVInputHDMI1, TVInputHDMI2, TVInputHDMI3, TVInputHDMI4,
+  TVInputVGA1, TVMediaContext, TVNetwork, TVNumberEntry, TVPower, TVRadioService, TVSatellite, TVSatelliteBS, TVSatelliteCS,
+  TVSatelliteToggle, TVTerrestrialAnalog, TVTerrestrialDigital, TVTimer, AVRInput, AVRPower, ColorF0Red, ColorF1Green, ColorF2Yellow,
+  ColorF3Blue, ColorF4Grey, ColorF5Brown, ClosedCaptionToggle, Dimmer, DisplaySwap, DVR, Exit, FavoriteClear0, FavoriteClear1,
+  FavoriteClear2, FavoriteClear3, FavoriteRecall0, FavoriteRecall1, FavoriteRecall2, FavoriteRecall3, FavoriteStore0, FavoriteStore1,
+  FavoriteStore2, FavoriteStore3, Guide, GuideNextDay, GuidePreviousDay, Info, InstantReplay, Link, ListProgram, LiveContent, Lock,
+  MediaApps, MediaAudioTrack, MediaLast, MediaSkipBackward, MediaSkipForward, MediaStepBackward, MediaStepForward, MediaTopMenu,
+  NavigateIn, NavigateNext, NavigateOut, NavigatePrevious, NextFavoriteChannel, NextUserProfile, OnDemand, Pairing, PinPDown, PinPMove,
+  PinPToggle, PinPUp, PlaySpeedDown, PlaySpeedReset, PlaySpeedUp, RandomToggle, RcLowBattery, RecordSpeedNext, RfBypass,
+  ScanChannelsToggle, ScreenModeNext, Settings, SplitScreenToggle, STBInput, STBPower, Subtitle, Teletext, VideoModeNext, Wink, ZoomToggle,
+  F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24, F25, F26, F27, F28, F29,
+  F30, F31, F32, F33, F34, F35
+});
+
+/// Shorter names accepted for some named keys.
+const NAMED_KEY_ALIASES: &[(&str, NamedKey)] = &[
+  ("Return", NamedKey::Enter), ("Del", NamedKey::Delete), ("Esc", NamedKey::Escape),
+  ("Up", NamedKey::ArrowUp), ("Down", NamedKey::ArrowDown), ("Left", NamedKey::ArrowLeft), ("Right", NamedKey::ArrowRight)
+];
+
+/// Names for characters which would otherwise be awkward to write in a shortcut.
+const CHARACTER_ALIASES: &[(&str, char)] = &[("Plus", '+'), ("Comma", ',')];
+
+/// The name of every key code, which is also how they are written after [`PHYSICAL_KEY_PREFIX`].
+const KEY_CODES: &[(&str, KeyCode)] = key_names!(KeyCode {
+  Backquote, Backslash, BracketLeft, BracketRight, Comma, Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
+  Equal, IntlBackslash, IntlRo, IntlYen, KeyA, KeyB, KeyC, KeyD, KeyE, KeyF, KeyG, KeyH, KeyI, KeyJ, KeyK, KeyL, KeyM, KeyN, KeyO, KeyP,
+  KeyQ, KeyR, KeyS, KeyT, KeyU, KeyV, KeyW, KeyX, KeyY, KeyZ, Minus, Period, Quote, Semicolon, Slash, AltLeft, AltRight, Backspace,
+  CapsLock, ContextMenu, ControlLeft, ControlRight, Enter, SuperLeft, SuperRight, ShiftLeft, ShiftRight, Space, Tab, Convert, KanaMode,
+  Lang1, Lang2, Lang3, Lang4, Lang5, NonConvert, Delete, End, Help, Home, Insert, PageDown, PageUp, ArrowDown, ArrowLeft, ArrowRight,
+  ArrowUp, NumLock, Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9, NumpadAdd, NumpadBackspace,
+  NumpadClear, NumpadClearEntry, NumpadComma, NumpadDecimal, NumpadDivide, NumpadEnter, NumpadEqual, NumpadHash, NumpadMemoryAdd,
+  NumpadMemoryClear, NumpadMemoryRecall, NumpadMemoryStore, NumpadMemorySubtract, NumpadMultiply, NumpadParenLeft, NumpadParenRight,
+  NumpadStar, NumpadSubtract, Escape, Fn, FnLock, PrintScreen, ScrollLock, Pause, BrowserBack, BrowserFavorites, BrowserForward,
+  BrowserHome, BrowserRefresh, BrowserSearch, BrowserStop, Eject, LaunchApp1, LaunchApp2, LaunchMail, MediaPlayPause, MediaSelect,
+  MediaStop, MediaTrackNext, MediaTrackPrevious, Power, Sleep, AudioVolumeDown, AudioVolumeMute, AudioVolumeUp, WakeUp, Meta, Hyper, Turbo,
+  Abort, Resume, Suspend, Again, Copy, Cut, Find, Open, Paste, Props, Select, Undo, Hiragana, Katakana, F1, F2, F3, F4, F5, F6, F7, F8, F9,
+  F10, F11, F12, F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24, F25, F26, F27, F28, F29, F30, F31, F32, F33, F34, F35
+});
+
+/// Looks up a key by its name, case-insensitively.
+fn find_key<K: Copy>(keys: &[(&str, K)], name: &str) -> Option<K> {
+  keys.iter().find(|(key_name, _)| key_name.eq_ignore_ascii_case(name)).map(|&(_, key)| key)
+}
+
+/// A key pressed while holding exactly the given modifiers, written like `"Ctrl+Shift+S"`.
+///
+/// Modifiers are written as `Ctrl`, `Alt`, `Shift`, `Super` (also `Cmd`, `Meta` or `Win`), or `Primary` (also `CmdOrCtrl`),
+/// which is Cmd on macOS and Ctrl everywhere else. Keys are written as single characters, named keys such as `Enter`, `Esc` and `F5`,
+/// or physical key codes such as `Code:KeyS`. Parsing is case-insensitive, and a shortcut always parses back from how it is displayed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+  pub modifiers: ModifiersState,
+  pub key: ShortcutKey
+}
+
+impl Shortcut {
+  /// The modifier conventionally used for shortcuts on the current platform, which is Cmd on macOS and Ctrl everywhere else.
+  pub const PRIMARY_MODIFIER: ModifiersState = if cfg!(target_os = "macos") { ModifiersState::SUPER } else { ModifiersState::CONTROL };
+
+  pub fn new(modifiers: ModifiersState, key: impl Into<ShortcutKey>) -> Self {
+    Shortcut { modifiers, key: key.into() }
+  }
+
+  /// Creates a shortcut using [`Shortcut::PRIMARY_MODIFIER`] along with any other given modifiers.
+  pub fn primary(modifiers: ModifiersState, key: impl Into<ShortcutKey>) -> Self {
+    Self::new(modifiers | Self::PRIMARY_MODIFIER, key)
+  }
+
+  /// Whether the key action presses this shortcut's key while exactly the given modifiers are held.
+  pub fn matches(&self, key_action: &KeyAction, modifiers: ModifiersState) -> bool {
+    key_action.state == KeyActionState::Pressed && modifiers == self.modifiers && self.key.matches(key_action)
+  }
+
+  /// Whether this shortcut was pressed during the current frame.
+  /// Since [`InputState`] only keeps the latest modifiers, they are compared against the modifiers held at the end of the frame.
+  pub fn was_pressed(&self, input: &InputState) -> bool {
+    let modifiers = input.modifiers().state();
+    input.key_actions().iter().any(|key_action| self.matches(key_action, modifiers))
+  }
+}
+
+impl FromStr for Shortcut {
+  type Err = ParseShortcutError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.trim();
+    if s.is_empty() { return Err(ParseShortcutError::Empty) };
+
+    // a trailing "+" after another "+", or on its own, is the plus key rather than a separator
+    let (modifiers_part, key_part) = match s.strip_suffix('+') {
+      Some("") => ("", "+"),
+      Some(rest) if rest.ends_with('+') => (&rest[..rest.len() - 1], "+"),
+      _ => s.rsplit_once('+').unwrap_or(("", s))
+    };
+
+    let key_part = key_part.trim();
+    if key_part.is_empty() { return Err(ParseShortcutError::MissingKey) };
+
+    let mut modifiers = ModifiersState::empty();
+    for modifier in modifiers_part.split('+').map(str::trim).filter(|modifier| !modifier.is_empty()) {
+      modifiers |= parse_modifier(modifier).ok_or_else(|| ParseShortcutError::UnknownModifier(modifier.to_owned()))?;
+    };
+
+    Ok(Shortcut { modifiers, key: key_part.parse()? })
+  }
+}
+
+fn parse_modifier(name: &str) -> Option<ModifiersState> {
+  Some(match name.to_ascii_lowercase().as_str() {
+    "ctrl" | "control" => ModifiersState::CONTROL,
+    "alt" | "option" => ModifiersState::ALT,
+    "shift" => ModifiersState::SHIFT,
+    "super" | "cmd" | "command" | "meta" | "win" => ModifiersState::SUPER,
+    "primary" | "cmdorctrl" => Shortcut::PRIMARY_MODIFIER,
+    _ => return None
+  })
+}
+
+impl fmt::Display for Shortcut {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let super_name = if cfg!(target_os = "macos") { "Cmd" } else { "Super" };
+    let names = [
+      (ModifiersState::CONTROL, "Ctrl"),
+      (ModifiersState::ALT, "Alt"),
+      (ModifiersState::SHIFT, "Shift"),
+      (ModifiersState::SUPER, super_name)
+    ];
+
+    for (modifier, name) in names {
+      if self.modifiers.contains(modifier) {
+        write!(f, "{name}+")?;
+      };
+    };
+
+    write!(f, "{}", self.key)
+  }
+}
+
+/// A sequence of shortcuts pressed one after another, written like `"Ctrl+K, Ctrl+C"`. See [`ShortcutMap`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Chord(Vec<Shortcut>);
+
+impl Chord {
+  /// Creates a chord from its steps, returning `None` if there are none.
+  pub fn new(steps: impl IntoIterator<Item = Shortcut>) -> Option<Self> {
+    Some(Chord(steps.into_iter().collect())).filter(|chord| !chord.0.is_empty())
+  }
+
+  #[inline]
+  pub fn steps(&self) -> &[Shortcut] {
+    &self.0
+  }
+}
+
+impl From<Shortcut> for Chord {
+  #[inline]
+  fn from(shortcut: Shortcut) -> Self {
+    Chord(vec![shortcut])
+  }
+}
+
+impl FromStr for Chord {
+  type Err = ParseShortcutError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    // commas separate steps, unless they follow a "+" and are therefore the comma key
+    let mut steps = Vec::new();
+    let mut step_start = 0;
+    for (index, c) in s.char_indices() {
+      if c == ',' && !s[step_start..index].trim_end().ends_with('+') && !s[step_start..index].trim().is_empty() {
+        steps.push(s[step_start..index].parse()?);
+        step_start = index + 1;
+      };
+    };
+
+    steps.push(s[step_start..].parse()?);
+    Ok(Chord(steps))
+  }
+}
+
+impl fmt::Display for Chord {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (index, step) in self.0.iter().enumerate() {
+      if index > 0 { f.write_str(", ")? };
+      write!(f, "{step}")?;
+    };
+
+    Ok(())
+  }
+}
+
+macro_rules! impl_serde_via_string {
+  ($($Type:ty),*) => ($(
+    #[cfg(feature = "serde")]
+    impl Serialize for $Type {
+      fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+      }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de> Deserialize<'de> for $Type {
+      fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+      }
+    }
+  )*);
+}
+
+impl_serde_via_string!(ShortcutKey, Shortcut, Chord);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseShortcutError {
+  Empty,
+  MissingKey,
+  UnknownModifier(String),
+  UnknownKey(String)
+}
+
+impl fmt::Display for ParseShortcutError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ParseShortcutError::Empty => write!(f, "shortcut is empty"),
+      ParseShortcutError::MissingKey => write!(f, "shortcut has no key"),
+      ParseShortcutError::UnknownModifier(modifier) => write!(f, "unknown modifier {modifier:?}"),
+      ParseShortcutError::UnknownKey(key) => write!(f, "unknown key {key:?}")
+    }
+  }
+}
+
+impl Error for ParseShortcutError {}
+
+/// A set of actions and the chords bound to them, which can be saved and loaded under the `serde` feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ShortcutBindings<A: Eq + Hash> {
+  actions: AHashMap<A, Vec<Chord>>
+}
+
+impl<A: Eq + Hash> ShortcutBindings<A> {
+  pub fn new() -> Self {
+    ShortcutBindings { actions: AHashMap::new() }
+  }
+
+  pub fn with(mut self, action: A, chords: impl IntoIterator<Item = Chord>) -> Self {
+    self.set(action, chords.into_iter().collect());
+    self
+  }
+
+  #[inline]
+  pub fn get(&self, action: &A) -> Option<&[Chord]> {
+    self.actions.get(action).map(Vec::as_slice)
+  }
+
+  #[inline]
+  pub fn get_mut(&mut self, action: &A) -> Option<&mut Vec<Chord>> {
+    self.actions.get_mut(action)
+  }
+
+  /// Binds an action, replacing its previous chords.
+  pub fn set(&mut self, action: A, chords: Vec<Chord>) -> Option<Vec<Chord>> {
+    self.actions.insert(action, chords)
+  }
+
+  /// Removes an action and its chords.
+  pub fn remove(&mut self, action: &A) -> Option<Vec<Chord>> {
+    self.actions.remove(action)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&A, &[Chord])> {
+    self.actions.iter().map(|(action, chords)| (action, chords.as_slice()))
+  }
+}
+
+impl<A: Eq + Hash> Default for ShortcutBindings<A> {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Matches pressed keys against a set of [`ShortcutBindings`] from frame to frame.
+///
+/// [`ShortcutMap::update`] should be called once per frame, like [`InputMap::update`][crate::input_map::InputMap::update].
+/// Once the first steps of a multi-step chord have been pressed, the chord is pending until its next step is pressed,
+/// a key that does not continue it is pressed, or the chord timeout elapses. If one chord is a prefix of another,
+/// the shorter chord is triggered. Key repeats and presses of modifier keys on their own are ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShortcutMap<A: Eq + Hash> {
+  bindings: ShortcutBindings<A>,
+  chord_timeout: Duration,
+  pending: Vec<Shortcut>,
+  pending_since: Option<Instant>,
+  triggered: Vec<A>
+}
+
+impl<A: Eq + Hash + Clone> ShortcutMap<A> {
+  /// The longest time allowed between the steps of a chord by default.
+  pub const DEFAULT_CHORD_TIMEOUT: Duration = Duration::from_secs(1);
+
+  pub fn new(bindings: ShortcutBindings<A>) -> Self {
+    ShortcutMap {
+      bindings,
+      chord_timeout: Self::DEFAULT_CHORD_TIMEOUT,
+      pending: Vec::new(),
+      pending_since: None,
+      triggered: Vec::new()
+    }
+  }
+
+  pub fn with_chord_timeout(mut self, chord_timeout: Duration) -> Self {
+    self.chord_timeout = chord_timeout;
+    self
+  }
+
+  #[inline]
+  pub fn chord_timeout(&self) -> Duration {
+    self.chord_timeout
+  }
+
+  #[inline]
+  pub fn bindings(&self) -> &ShortcutBindings<A> {
+    &self.bindings
+  }
+
+  /// Returns the bindings for modification, such as to rebind an action at runtime.
+  /// Any pending chord is kept, and changes take effect on the next [`ShortcutMap::update`].
+  #[inline]
+  pub fn bindings_mut(&mut self) -> &mut ShortcutBindings<A> {
+    &mut self.bindings
+  }
+
+  /// Replaces every binding, such as with bindings loaded from a settings file, cancelling any pending chord.
+  pub fn set_bindings(&mut self, bindings: ShortcutBindings<A>) {
+    self.bindings = bindings;
+    self.pending.clear();
+  }
+
+  /// Matches the keys pressed during the current frame, updating which actions were triggered.
+  pub fn update(&mut self, input: &InputState) {
+    self.triggered.clear();
+    let now = input.frame_instant();
+    if let (Some(now), Some(pending_since)) = (now, self.pending_since) && now.saturating_duration_since(pending_since) > self.chord_timeout {
+      self.pending.clear();
+    };
+
+    let modifiers = input.modifiers().state();
+    let key_actions = input.key_actions().iter()
+      .filter(|key_action| key_action.state == KeyActionState::Pressed && !is_modifier_key(&key_action.logical_key));
+    for key_action in key_actions {
+      // a key which does not continue the pending chord cancels it, and may start another chord instead
+      if !self.advance(key_action, modifiers) && !self.pending.is_empty() {
+        self.pending.clear();
+        self.advance(key_action, modifiers);
+      };
+
+      self.pending_since = now;
+    };
+  }
+
+  /// Advances or completes chords with the given key action, returning whether any chord matched it.
+  fn advance(&mut self, key_action: &KeyAction, modifiers: ModifiersState) -> bool {
+    let step = self.pending.len();
+    let mut next_step = None;
+    let mut completed = false;
+    for (action, chords) in self.bindings.actions.iter() {
+      for chord in chords {
+        let steps = chord.steps();
+        if steps.len() <= step || steps[..step] != self.pending[..] || !steps[step].matches(key_action, modifiers) { continue };
+        if steps.len() == step + 1 {
+          if !self.triggered.contains(action) {
+            self.triggered.push(action.clone());
+          };
+
+          completed = true;
+        } else {
+          next_step = Some(steps[step].clone());
+        };
+      };
+    };
+
+    match (completed, next_step) {
+      (true, _) => self.pending.clear(),
+      (false, Some(next_step)) => self.pending.push(next_step),
+      (false, None) => return false
+    };
+
+    true
+  }
+
+  /// Returns the actions whose chords were completed during the current frame, in the order they were completed.
+  #[inline]
+  pub fn triggered(&self) -> &[A] {
+    &self.triggered
+  }
+
+  /// Whether the action's chord was completed during the current frame.
+  pub fn was_triggered(&self, action: &A) -> bool {
+    self.triggered.contains(action)
+  }
+
+  /// Returns the steps of the chord pressed so far, which is empty unless a multi-step chord is pending.
+  #[inline]
+  pub fn pending_chord(&self) -> &[Shortcut] {
+    &self.pending
+  }
+
+  /// Cancels the pending chord, if there is one.
+  pub fn cancel_pending_chord(&mut self) {
+    self.pending.clear();
+  }
+}
+
+impl<A: Eq + Hash + Clone> Default for ShortcutMap<A> {
+  #[inline]
+  fn default() -> Self {
+    Self::new(ShortcutBindings::new())
+  }
+}
+
+fn is_modifier_key(logical_key: &LogicalKey) -> bool {
+  matches!(logical_key, LogicalKey::Named(
+    NamedKey::Shift | NamedKey::Control | NamedKey::Alt | NamedKey::AltGraph | NamedKey::Super | NamedKey::Meta | NamedKey::Hyper
+  ))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use winit::event::ElementState;
+  use winit::window::Window;
+
+  use crate::windowing::tests::{headless_window_state, key_event, NullHandler};
+  use crate::windowing::{InputEvent, WindowState};
+
+  use super::*;
+
+  fn shortcut(modifiers: ModifiersState, key: impl Into<ShortcutKey>) -> Shortcut {
+    Shortcut::new(modifiers, key)
+  }
+
+  #[track_caller]
+  fn assert_round_trips(shortcut: Shortcut) {
+    let written = shortcut.to_string();
+    assert_eq!(written.parse(), Ok(shortcut.clone()), "{written:?} did not parse back");
+    #[cfg(feature = "serde")]
+    assert_eq!(serde_json::from_str::<Shortcut>(&serde_json::to_string(&shortcut).unwrap()).unwrap(), shortcut);
+  }
+
+  #[test]
+  fn every_key_round_trips() {
+    let modifiers = ModifiersState::CONTROL | ModifiersState::SHIFT;
+    for &(_, key_code) in KEY_CODES {
+      assert_round_trips(shortcut(modifiers, key_code));
+    };
+
+    for &(_, named_key) in NAMED_KEYS {
+      assert_round_trips(shortcut(modifiers, named_key));
+    };
+
+    for character in ['a', 'Z', '1', '-', '+', ',', ':', 'é', 'ß'] {
+      assert_round_trips(shortcut(modifiers, character));
+      assert_round_trips(shortcut(ModifiersState::empty(), character));
+    };
+  }
+
+  #[test]
+  fn whitespace_characters_are_named_keys() {
+    assert_eq!(ShortcutKey::from(' '), ShortcutKey::Named(NamedKey::Space));
+    assert_eq!(ShortcutKey::from('\t'), ShortcutKey::Named(NamedKey::Tab));
+    assert_round_trips(shortcut(ModifiersState::CONTROL, ' '));
+  }
+
+  #[test]
+  fn keys_parse_case_insensitively() {
+    let ctrl = ModifiersState::CONTROL;
+    assert_eq!("ctrl+keys".parse(), Ok(shortcut(ctrl, KeyCode::KeyS)));
+    assert_eq!("CTRL+code:digit1".parse(), Ok(shortcut(ctrl, KeyCode::Digit1)));
+    assert_eq!("Ctrl+Minus".parse(), Ok(shortcut(ctrl, KeyCode::Minus)));
+    assert_eq!("ctrl+f13".parse(), Ok(shortcut(ctrl, NamedKey::F13)));
+    assert_eq!("Ctrl+esc".parse(), Ok(shortcut(ctrl, NamedKey::Escape)));
+    assert_eq!("Ctrl+Code:Enter".parse(), Ok(shortcut(ctrl, KeyCode::Enter)));
+    assert_eq!("Ctrl+Enter".parse(), Ok(shortcut(ctrl, NamedKey::Enter)));
+    assert_eq!("Ctrl+s".parse(), Ok(shortcut(ctrl, 'S')));
+  }
+
+  #[test]
+  fn separators_can_be_keys() {
+    let ctrl = ModifiersState::CONTROL;
+    assert_eq!("Ctrl++".parse(), Ok(shortcut(ctrl, '+')));
+    assert_eq!("Ctrl+Plus".parse(), Ok(shortcut(ctrl, '+')));
+    assert_eq!("+".parse(), Ok(shortcut(ModifiersState::empty(), '+')));
+    assert_eq!("Ctrl+,".parse(), Ok(shortcut(ctrl, ',')));
+
+    let chord = "Ctrl+,, Ctrl+C".parse::<Chord>().unwrap();
+    assert_eq!(chord.steps(), [shortcut(ctrl, ','), shortcut(ctrl, 'c')]);
+    assert_eq!(chord.to_string().parse(), Ok(chord));
+  }
+
+  #[test]
+  fn invalid_shortcuts_are_rejected() {
+    assert_eq!("".parse::<Shortcut>(), Err(ParseShortcutError::Empty));
+    assert_eq!("Ctrl+".parse::<Shortcut>(), Err(ParseShortcutError::MissingKey));
+    assert_eq!("Hyper+A".parse::<Shortcut>(), Err(ParseShortcutError::UnknownModifier("Hyper".to_owned())));
+    assert_eq!("Ctrl+Nope".parse::<Shortcut>(), Err(ParseShortcutError::UnknownKey("Nope".to_owned())));
+    assert_eq!("Ctrl+Code:Nope".parse::<Shortcut>(), Err(ParseShortcutError::UnknownKey("Code:Nope".to_owned())));
+  }
+
+  fn shortcut_map(bindings: &[(&'static str, &str)]) -> ShortcutMap<&'static str> {
+    let bindings = bindings.iter().fold(ShortcutBindings::new(), |bindings, &(action, chord)| {
+      bindings.with(action, [chord.parse().unwrap()])
+    });
+
+    ShortcutMap::new(bindings)
+  }
+
+  /// Presses and releases the given keys with Ctrl held during a single frame, then updates the shortcut map.
+  fn frame(window_state: &mut WindowState<Window>, shortcut_map: &mut ShortcutMap<&'static str>, now: Instant, key_codes: &[KeyCode]) {
+    window_state.reset();
+    window_state.handle_input_event(&mut NullHandler, InputEvent::ModifiersChanged(ModifiersState::CONTROL.into()));
+    for &key_code in key_codes {
+      window_state.handle_input_event(&mut NullHandler, key_event(key_code, ElementState::Pressed));
+      window_state.handle_input_event(&mut NullHandler, key_event(key_code, ElementState::Released));
+    };
+
+    window_state.begin_frame(now, true);
+    shortcut_map.update(window_state.input());
+  }
+
+  #[test]
+  fn chords_trigger_on_their_last_step() {
+    let mut window_state = headless_window_state();
+    let mut shortcut_map = shortcut_map(&[("comment", "Ctrl+Code:KeyK, Ctrl+Code:KeyC")]);
+    let now = Instant::now();
+    frame(&mut window_state, &mut shortcut_map, now, &[KeyCode::KeyK]);
+    assert!(shortcut_map.triggered().is_empty());
+    assert_eq!(shortcut_map.pending_chord(), [shortcut(ModifiersState::CONTROL, KeyCode::KeyK)]);
+
+    frame(&mut window_state, &mut shortcut_map, now + Duration::from_millis(100), &[KeyCode::KeyC]);
+    assert_eq!(shortcut_map.triggered(), ["comment"]);
+    assert!(shortcut_map.pending_chord().is_empty());
+
+    frame(&mut window_state, &mut shortcut_map, now + Duration::from_millis(200), &[]);
+    assert!(shortcut_map.triggered().is_empty());
+  }
+
+  #[test]
+  fn chords_are_cancelled_after_the_timeout() {
+    let mut window_state = headless_window_state();
+    let mut shortcut_map = shortcut_map(&[("comment", "Ctrl+Code:KeyK, Ctrl+Code:KeyC")])
+      .with_chord_timeout(Duration::from_millis(500));
+    let now = Instant::now();
+    frame(&mut window_state, &mut shortcut_map, now, &[KeyCode::KeyK]);
+    frame(&mut window_state, &mut shortcut_map, now + Duration::from_millis(400), &[]);
+    assert_eq!(shortcut_map.pending_chord().len(), 1);
+
+    frame(&mut window_state, &mut shortcut_map, now + Duration::from_millis(600), &[KeyCode::KeyC]);
+    assert!(shortcut_map.triggered().is_empty());
+    assert!(shortcut_map.pending_chord().is_empty());
+  }
+
+  #[test]
+  fn keys_that_do_not_continue_a_chord_start_another() {
+    let mut window_state = headless_window_state();
+    let mut shortcut_map = shortcut_map(&[
+      ("comment", "Ctrl+Code:KeyK, Ctrl+Code:KeyC"),
+      ("uncomment", "Ctrl+Code:KeyU, Ctrl+Code:KeyC")
+    ]);
+    let now = Instant::now();
+    frame(&mut window_state, &mut shortcut_map, now, &[KeyCode::KeyK]);
+    frame(&mut window_state, &mut shortcut_map, now, &[KeyCode::KeyU]);
+    assert_eq!(shortcut_map.pending_chord(), [shortcut(ModifiersState::CONTROL, KeyCode::KeyU)]);
+
+    frame(&mut window_state, &mut shortcut_map, now, &[KeyCode::KeyC]);
+    assert_eq!(shortcut_map.triggered(), ["uncomment"]);
+
+    // a key that starts no chord leaves nothing pending
+    frame(&mut window_state, &mut shortcut_map, now, &[KeyCode::KeyK, KeyCode::KeyX]);
+    assert!(shortcut_map.triggered().is_empty());
+    assert!(shortcut_map.pending_chord().is_empty());
+  }
+
+  #[test]
+  fn prefix_chords_trigger_the_shorter_chord() {
+    let mut window_state = headless_window_state();
+    let mut shortcut_map = shortcut_map(&[
+      ("comment", "Ctrl+Code:KeyK, Ctrl+Code:KeyC"),
+      ("kill", "Ctrl+Code:KeyK")
+    ]);
+    let now = Instant::now();
+    frame(&mut window_state, &mut shortcut_map, now, &[KeyCode::KeyK]);
+    assert_eq!(shortcut_map.triggered(), ["kill"]);
+    assert!(shortcut_map.pending_chord().is_empty());
+
+    frame(&mut window_state, &mut shortcut_map, now, &[KeyCode::KeyC]);
+    assert!(shortcut_map.triggered().is_empty());
+  }
+}
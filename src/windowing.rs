@@ -1,40 +1,59 @@
-use ahash::AHashSet;
+use ahash::AHashMap;
 #[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 use winit::application::ApplicationHandler;
 use winit::dpi::{PhysicalPosition, PhysicalSize};
 use winit::error::EventLoopError;
 use winit::event::{
-  AxisId, DeviceEvent, DeviceId, ElementState, Ime, KeyEvent, Modifiers, MouseButton, MouseScrollDelta, StartCause, Touch, TouchPhase, WindowEvent
+  AxisId, DeviceEvent, DeviceId, ElementState, Force, Ime, KeyEvent, Modifiers, MouseButton, MouseScrollDelta, Touch, TouchPhase, WindowEvent
 };
-use winit::keyboard::{Key as LogicalKey, NamedKey, PhysicalKey, KeyCode};
-use winit::event_loop::{ActiveEventLoop, EventLoop};
-use winit::window::{Theme, Window, WindowId};
+use winit::keyboard::{Key as LogicalKey, KeyLocation, NamedKey, PhysicalKey, KeyCode, SmolStr};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::window::{CursorGrabMode, ImePurpose, Theme, Window, WindowAttributes, WindowId};
 
+use crate::gamepad::{GamepadAxis, GamepadButton, GamepadConfig, GamepadEvent, GamepadId, GamepadSource, GamepadState};
+use crate::gestures::{GestureConfig, GestureRecognizer};
+use crate::input_layers::ConsumedInput;
 use crate::misc::OptionExt;
+#[cfg(feature = "serde")]
+use crate::replay::{InputRecorder, InputReplay};
 
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::error::Error;
 use std::marker::PhantomData;
-use std::mem::replace;
+use std::mem::{replace, take};
 use std::ops::Index;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 
 
 /// Handles events emitted by a winit event loop.
+///
+/// Callbacks that are tied to a specific window receive that window's [`WindowState`],
+/// while callbacks that are not (such as [`EventHandler::update`]) receive the state of the primary window,
+/// which is the oldest window that is still open.
 #[allow(unused_variables)]
 pub trait EventHandler<W: HasWindow, T = ()>: Sized + 'static {
   /// Called upon only the first [`Event::Resumed`][winit::event::Event::Resumed].
   fn init(&mut self, window_state: &WindowState<W>) {}
 
   /// Called upon [`Event::AboutToWait`][winit::event::Event::AboutToWait].
+  ///
+  /// If a [`FixedTimestep`] has been configured, this is instead called zero or more times per frame,
+  /// once for each tick that has elapsed. See [`WindowState::timestep`].
+  /// It is only called with the state of the primary window.
   fn update(&mut self, window_state: &WindowState<W>);
 
   /// Called upon [`WindowEvent::RedrawRequested`].
   fn render(&mut self, window_state: &WindowState<W>);
 
-  /// Called when an event is sent from [`EventLoopProxy::send_event`][winit::event_loop::EventLoopProxy::send_event].
+  /// Called when an event is sent from [`EventLoopProxy::send_event`][winit::event_loop::EventLoopProxy::send_event],
+  /// with the state of the primary window.
   fn on_user_event(&mut self, window_state: &WindowState<W>, event: T) {}
 
   /// Called upon [`Event::WindowEvent`][winit::event::Event::WindowEvent].
@@ -44,21 +63,59 @@ pub trait EventHandler<W: HasWindow, T = ()>: Sized + 'static {
   /// When overriding this function, ensure that [`WindowState::handle_window_event`] is called,
   /// otherwise this [`EventHandler`] will stop working.
   fn on_window_event(&mut self, window_state: &mut WindowState<W>, event: WindowEvent, event_loop: &ActiveEventLoop) {
-    window_state.handle_window_event(self, event, event_loop);
+    window_state.handle_window_event(self, event);
+  }
+
+  /// Called when a window has been requested with [`WindowState::open_window`], and should create its window holder.
+  /// Defaults to failing, since opening additional windows requires knowing how to construct `W`.
+  fn create_window(&mut self, event_loop: &ActiveEventLoop, window_attributes: WindowAttributes) -> Result<W, Box<dyn Error>> {
+    Err("this event handler does not support opening additional windows".into())
   }
 
-  /// Called upon [`Event::DeviceEvent`][winit::event::Event::DeviceEvent].
+  /// Called once a window requested with [`WindowState::open_window`] has been created.
+  fn on_window_opened(&mut self, window_state: &WindowState<W>) {}
+
+  /// Called when [`EventHandler::create_window`] fails to create a requested window.
+  fn on_window_open_failed(&mut self, error: Box<dyn Error>) {}
+
+  /// Called when a window has been closed, just before its [`WindowState`] is dropped.
+  /// Once the last window has been closed, the application exits.
+  fn on_window_closed(&mut self, window_state: &WindowState<W>) {}
+
+  /// Called when a [`FixedTimestep`] has fallen so far behind that the ticks it would need
+  /// to catch up exceed [`FixedTimestep::max_ticks_per_frame`], and some ticks have been dropped.
+  fn on_ticks_dropped(&mut self, window_state: &WindowState<W>, count: u32) {}
+
+  /// If `Some`, each frame advances time by exactly this duration instead of the real time since the last frame,
+  /// which affects [`WindowState::frame_timing`] and fixed timestep updates. Polled once at the start of every frame.
+  /// Defaults to `None`.
+  fn simulated_frame_duration(&self) -> Option<Duration> { None }
+
+  /// Called upon [`Event::DeviceEvent`][winit::event::Event::DeviceEvent], with the state of the primary window.
+  /// The input it carries is also applied to the [`InputState`] of whichever window is focused.
   fn on_device_event(&mut self, window_state: &WindowState<W>, id: DeviceId, event: DeviceEvent) {}
 
   /// Called when an event from the keyboard has been received.
   fn on_keyboard_input(&mut self, window_state: &WindowState<W>, event: KeyEvent) {}
 
-  /// Called upon [`WindowEvent::Ime`].
+  /// Called instead of [`EventHandler::on_keyboard_input`] for keyboard events which did not come from the event loop,
+  /// such as replayed input, since a [`KeyEvent`] cannot be constructed outside of winit.
+  /// See [`WindowState::handle_input_event`].
+  fn on_synthetic_keyboard_input(&mut self, window_state: &WindowState<W>, event: SyntheticKeyEvent) {}
+
+  /// Called upon [`WindowEvent::Ime`]. Committed text and composition state are also tracked by [`InputState`],
+  /// see [`InputState::text`] and [`InputState::preedit`].
   fn on_text_input(&mut self, window_state: &WindowState<W>, event: Ime) {}
 
   /// Called when the cursor has moved on the window.
   fn on_cursor_moved(&mut self, window_state: &WindowState<W>, pos: PhysicalPosition<f32>) {}
 
+  /// Called when the cursor enters the window. See [`WindowEvent::CursorEntered`].
+  fn on_cursor_entered(&mut self, window_state: &WindowState<W>) {}
+
+  /// Called when the cursor leaves the window. See [`WindowEvent::CursorLeft`].
+  fn on_cursor_left(&mut self, window_state: &WindowState<W>) {}
+
   /// Called when a mouse button press has been received.
   fn on_mouse_input(&mut self, window_state: &WindowState<W>, state: ElementState, button: MouseButton) {}
 
@@ -79,6 +136,18 @@ pub trait EventHandler<W: HasWindow, T = ()>: Sized + 'static {
   /// Called upon [`WindowEvent::AxisMotion`].
   fn on_axis_motion(&mut self, window_state: &WindowState<W>, axis_motion: AxisMotion) {}
 
+  /// Called when a gamepad is connected. See the [`gamepad`][crate::gamepad] module.
+  fn on_gamepad_connected(&mut self, window_state: &WindowState<W>, id: GamepadId) {}
+
+  /// Called when a gamepad is disconnected.
+  fn on_gamepad_disconnected(&mut self, window_state: &WindowState<W>, id: GamepadId) {}
+
+  /// Called when a button of a gamepad is pressed or released.
+  fn on_gamepad_button(&mut self, window_state: &WindowState<W>, id: GamepadId, button: GamepadButton, state: ElementState) {}
+
+  /// Called when an axis of a gamepad changes, with its raw value before any deadzone is applied.
+  fn on_gamepad_axis(&mut self, window_state: &WindowState<W>, id: GamepadId, axis: GamepadAxis, value: f32) {}
+
   /// Called when the application window loses or gains focus. See [WindowEvent::Focused].
   fn on_focus_changed(&mut self, window_state: &WindowState<W>, state: bool) {}
 
@@ -91,14 +160,14 @@ pub trait EventHandler<W: HasWindow, T = ()>: Sized + 'static {
   /// Called when either the window has been resized or the scale factor has changed.
   fn on_resized(&mut self, window_state: &WindowState<W>, window_size: PhysicalSize<u32>, scale_factor: f64) {}
 
-  /// Called upon [`Event::Resumed`][winit::event::Event::Resumed].
+  /// Called upon [`Event::Resumed`][winit::event::Event::Resumed], with the state of the primary window.
   fn on_resumed(&mut self, window_state: &WindowState<W>) {}
 
-  /// Called upon [`Event::Suspended`][winit::event::Event::Suspended].
+  /// Called upon [`Event::Suspended`][winit::event::Event::Suspended], with the state of the primary window.
   fn on_suspended(&mut self, window_state: &WindowState<W>) {}
 
-  /// Called when the user attempts to close the application.
-  /// A return value of `true` closes the application, while `false` cancels closing it.
+  /// Called when the user attempts to close a window.
+  /// A return value of `true` closes the window, while `false` cancels closing it.
   /// Defaults to an 'always `true`' implementation.
   fn on_close_requested(&mut self, window_state: &WindowState<W>) -> bool { true }
 
@@ -115,20 +184,38 @@ pub trait EventHandler<W: HasWindow, T = ()>: Sized + 'static {
 pub struct InputState {
   cursor_pos: Option<PhysicalPosition<f32>>,
   cursor_pos_prev: Option<PhysicalPosition<f32>>,
+  last_cursor_pos: Option<PhysicalPosition<f32>>,
+  cursor_inside: bool,
+  mouse_motion: (f64, f64),
   mouse_actions: Vec<MouseAction>,
   mouse_left_held: bool,
   mouse_right_held: bool,
   mouse_middle_held: bool,
   mouse_back_held: bool,
   mouse_forward_held: bool,
+  button_states: [ButtonState; 5],
+  button_events: Vec<ButtonEvent>,
   has_cursor_not_moved: bool,
   key_actions: Vec<KeyAction>,
-  keys_held_physical: AHashSet<KeyCode>,
-  keys_held_logical: AHashSet<NamedKey>,
+  /// Held keys, mapped to the start of the frame in which they were pressed,
+  /// or `None` if they were pressed since the current frame started.
+  keys_held_physical: AHashMap<KeyCode, Option<Instant>>,
+  keys_held_logical: AHashMap<NamedKey, Option<Instant>>,
+  frame_instant: Option<Instant>,
   modifiers: Modifiers,
+  text: String,
+  ime_enabled: bool,
+  preedit: String,
+  preedit_cursor: Option<(usize, usize)>,
+  scroll_events: Vec<ScrollEvent>,
   gestures: Vec<Gesture>,
   touches: Vec<Touch>,
-  axis_motions: Vec<AxisMotion>
+  touch_points: AHashMap<u64, TouchPoint>,
+  touches_started: Vec<u64>,
+  touches_ended: Vec<TouchPoint>,
+  axis_motions: Vec<AxisMotion>,
+  gamepads: AHashMap<GamepadId, GamepadState>,
+  gamepad_events: Vec<GamepadEvent>
 }
 
 impl InputState {
@@ -142,6 +229,19 @@ impl InputState {
     self.cursor_pos_prev
   }
 
+  /// The last known position of the cursor, which unlike [`InputState::cursor_pos`] is not clipped to the window,
+  /// and remains set after the cursor leaves the window.
+  #[inline]
+  pub fn last_cursor_pos(&self) -> Option<PhysicalPosition<f32>> {
+    self.last_cursor_pos
+  }
+
+  /// Whether the cursor is currently inside the window, as reported by [`WindowEvent::CursorEntered`] and [`WindowEvent::CursorLeft`].
+  #[inline]
+  pub fn is_cursor_inside(&self) -> bool {
+    self.cursor_inside
+  }
+
   #[inline]
   pub fn cursor_pos_rel(&self) -> Option<PhysicalPosition<f32>> {
     if let (Some(pos), Some(pos_prev)) = (self.cursor_pos, self.cursor_pos_prev) {
@@ -154,18 +254,83 @@ impl InputState {
     }
   }
 
+  /// The raw mouse motion accumulated during the current frame, as reported by [`DeviceEvent::MouseMotion`].
+  /// This is unaffected by the cursor being clipped, grabbed or accelerated, and is only received while the window is focused.
+  /// See [`WindowState::set_relative_mouse_mode`].
+  #[inline]
+  pub fn mouse_motion(&self) -> (f64, f64) {
+    self.mouse_motion
+  }
+
   /// Returns a list of mouse button actions performed during the current frame.
   #[inline]
   pub fn mouse_actions(&self) -> &[MouseAction] {
     &self.mouse_actions
   }
 
+  fn is_any_button_held(&self) -> bool {
+    self.mouse_left_held || self.mouse_right_held || self.mouse_middle_held || self.mouse_back_held || self.mouse_forward_held
+  }
+
   /// Checks whether or not the given mouse button is currently pressed.
   #[inline]
   pub fn is_button_held(&self, button: MouseButton) -> bool {
     self[button]
   }
 
+  /// Returns the click and drag state of the given mouse button, or `None` for [`MouseButton::Other`].
+  #[inline]
+  pub fn button_state(&self, button: MouseButton) -> Option<&ButtonState> {
+    button_index(button).map(|index| &self.button_states[index])
+  }
+
+  /// Returns the clicks and drags of mouse buttons that happened during the current frame. See [`ClickConfig`].
+  #[inline]
+  pub fn button_events(&self) -> &[ButtonEvent] {
+    &self.button_events
+  }
+
+  /// The number of presses in quick succession of the given button, ending with its latest press,
+  /// or `0` if it has never been pressed.
+  #[inline]
+  pub fn click_count(&self, button: MouseButton) -> u32 {
+    self.button_state(button).map_or(0, |button_state| button_state.click_count)
+  }
+
+  /// Checks whether the given button was clicked during the current frame, with any click count.
+  pub fn was_clicked(&self, button: MouseButton) -> bool {
+    self.button_events.iter().any(|button_event| {
+      matches!(*button_event, ButtonEvent::Click { button: b, .. } if b == button)
+    })
+  }
+
+  /// Checks whether the given button was clicked during the current frame with exactly the given click count,
+  /// for example `2` for a double click.
+  pub fn was_clicked_times(&self, button: MouseButton, count: u32) -> bool {
+    self.button_events.iter().any(|button_event| {
+      matches!(*button_event, ButtonEvent::Click { button: b, count: c, .. } if b == button && c == count)
+    })
+  }
+
+  /// Checks whether the given button is currently being dragged.
+  #[inline]
+  pub fn is_dragging(&self, button: MouseButton) -> bool {
+    self.button_state(button).is_some_and(|button_state| button_state.dragging)
+  }
+
+  /// How far the cursor has moved since the given button was pressed, if it is currently being dragged.
+  pub fn drag_delta(&self, button: MouseButton) -> Option<PhysicalPosition<f32>> {
+    let button_state = self.button_state(button).filter(|button_state| button_state.dragging)?;
+    let (origin, position) = (button_state.press_position?, button_state.position);
+    Some(PhysicalPosition::new(position.x - origin.x, position.y - origin.y))
+  }
+
+  /// The instant that the current frame started at, or `None` before the first frame.
+  #[inline]
+  pub fn frame_instant(&self) -> Option<Instant> {
+    self.frame_instant
+  }
+
   /// Returns a list of key actions performed during the current frame.
   #[inline]
   pub fn key_actions(&self) -> &[KeyAction] {
@@ -173,15 +338,59 @@ impl InputState {
   }
 
   /// Checks whether or not the given named key is currently pressed.
+  /// Keys remain held until they are released or the window loses focus.
   #[inline]
   pub fn is_key_held_logical(&self, named_key: &NamedKey) -> bool {
-    self.keys_held_logical.contains(named_key)
+    self.keys_held_logical.contains_key(named_key)
   }
 
   /// Checks whether or not the given key code is currently pressed.
+  /// Keys remain held until they are released or the window loses focus.
   #[inline]
   pub fn is_key_held_physical(&self, key_code: &KeyCode) -> bool {
-    self.keys_held_physical.contains(key_code)
+    self.keys_held_physical.contains_key(key_code)
+  }
+
+  /// Returns every named key that is currently pressed, in no particular order.
+  pub fn keys_held_logical(&self) -> impl Iterator<Item = NamedKey> + '_ {
+    self.keys_held_logical.keys().copied()
+  }
+
+  /// Returns every key code that is currently pressed, in no particular order.
+  pub fn keys_held_physical(&self) -> impl Iterator<Item = KeyCode> + '_ {
+    self.keys_held_physical.keys().copied()
+  }
+
+  /// Returns the start of the frame in which the given named key was pressed, if it is currently pressed.
+  /// Key repeats do not affect this. Keys pressed since the current frame started, which can only be observed
+  /// from event callbacks, report the start of the current frame.
+  pub fn key_pressed_at_logical(&self, named_key: &NamedKey) -> Option<Instant> {
+    self.keys_held_logical.get(named_key).and_then(|&pressed_at| pressed_at.or(self.frame_instant))
+  }
+
+  /// Returns the start of the frame in which the given key code was pressed, if it is currently pressed.
+  /// Key repeats do not affect this. See [`InputState::key_pressed_at_logical`].
+  pub fn key_pressed_at_physical(&self, key_code: &KeyCode) -> Option<Instant> {
+    self.keys_held_physical.get(key_code).and_then(|&pressed_at| pressed_at.or(self.frame_instant))
+  }
+
+  /// Returns how long the given named key has been held as of the start of the current frame, if it is currently pressed.
+  /// This is zero during the frame in which the key was pressed.
+  pub fn key_held_duration_logical(&self, named_key: &NamedKey) -> Option<Duration> {
+    self.keys_held_logical.get(named_key).map(|&pressed_at| self.held_duration(pressed_at))
+  }
+
+  /// Returns how long the given key code has been held as of the start of the current frame, if it is currently pressed.
+  /// This is zero during the frame in which the key was pressed.
+  pub fn key_held_duration_physical(&self, key_code: &KeyCode) -> Option<Duration> {
+    self.keys_held_physical.get(key_code).map(|&pressed_at| self.held_duration(pressed_at))
+  }
+
+  fn held_duration(&self, pressed_at: Option<Instant>) -> Duration {
+    match (pressed_at, self.frame_instant) {
+      (Some(pressed_at), Some(frame_instant)) => frame_instant.saturating_duration_since(pressed_at),
+      _ => Duration::ZERO
+    }
   }
 
   /// Checks whether or not the given physical key was operated in the given method during the current frame.
@@ -241,6 +450,65 @@ impl InputState {
     self.modifiers
   }
 
+  /// The text typed during the current frame, from both key presses and [`Ime::Commit`], in the order it was typed.
+  /// Control characters such as backspace and enter are left out, and can be read from [`InputState::key_actions`] instead.
+  /// Key presses only produce text while the IME is disabled, since an enabled IME reports typed text through [`Ime::Commit`].
+  #[inline]
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+
+  /// Whether the IME is enabled, as reported by [`Ime::Enabled`] and [`Ime::Disabled`]. See [`WindowState::set_ime_allowed`].
+  #[inline]
+  pub fn is_ime_enabled(&self) -> bool {
+    self.ime_enabled
+  }
+
+  /// The text currently being composed with the IME, which is empty while not composing.
+  /// It should be displayed at the text cursor, but is not yet part of the text. See [`Ime::Preedit`].
+  #[inline]
+  pub fn preedit(&self) -> &str {
+    &self.preedit
+  }
+
+  /// The byte range of the cursor within [`InputState::preedit`], or `None` if the cursor should be hidden.
+  #[inline]
+  pub fn preedit_cursor(&self) -> Option<(usize, usize)> {
+    self.preedit_cursor
+  }
+
+  /// Whether the IME is currently composing text.
+  #[inline]
+  pub fn is_composing(&self) -> bool {
+    !self.preedit.is_empty()
+  }
+
+  /// Returns a list of mouse wheel and touchpad scroll events received during the current frame.
+  #[inline]
+  pub fn scroll_events(&self) -> &[ScrollEvent] {
+    &self.scroll_events
+  }
+
+  /// The total distance scrolled during the current frame, in lines.
+  pub fn scroll_lines(&self) -> (f32, f32) {
+    self.scroll_events.iter()
+      .fold((0.0, 0.0), |(x, y), scroll_event| (x + scroll_event.lines.0, y + scroll_event.lines.1))
+  }
+
+  /// The total distance scrolled during the current frame, in physical pixels.
+  pub fn scroll_pixels(&self) -> PhysicalPosition<f64> {
+    self.scroll_events.iter()
+      .fold(PhysicalPosition::new(0.0, 0.0), |total, scroll_event| {
+        PhysicalPosition::new(total.x + scroll_event.pixels.x, total.y + scroll_event.pixels.y)
+      })
+  }
+
+  /// Whether any scrolling happened during the current frame.
+  #[inline]
+  pub fn was_scrolling(&self) -> bool {
+    !self.scroll_events.is_empty()
+  }
+
   #[inline]
   pub fn gestures(&self) -> &[Gesture] {
     &self.gestures
@@ -251,6 +519,37 @@ impl InputState {
     &self.touches
   }
 
+  /// Returns every finger currently touching the window, in no particular order.
+  /// Touches persist across frames until they end, are cancelled, or the window loses focus.
+  pub fn touch_points(&self) -> impl Iterator<Item = &TouchPoint> {
+    self.touch_points.values()
+  }
+
+  /// Returns the touch with the given ID, if it is still touching the window.
+  #[inline]
+  pub fn touch_point(&self, id: u64) -> Option<&TouchPoint> {
+    self.touch_points.get(&id)
+  }
+
+  /// The number of fingers currently touching the window.
+  #[inline]
+  pub fn touch_count(&self) -> usize {
+    self.touch_points.len()
+  }
+
+  /// Returns the IDs of the touches that started during the current frame.
+  /// These may have already ended again, in which case they appear in [`InputState::touches_ended`] instead of being active.
+  #[inline]
+  pub fn touches_started(&self) -> &[u64] {
+    &self.touches_started
+  }
+
+  /// Returns the final state of the touches that ended or were cancelled during the current frame.
+  #[inline]
+  pub fn touches_ended(&self) -> &[TouchPoint] {
+    &self.touches_ended
+  }
+
   #[inline]
   pub fn axis_motions(&self) -> &[AxisMotion] {
     &self.axis_motions
@@ -284,6 +583,86 @@ impl InputState {
     }
   }
 
+  /// Returns every connected gamepad, in no particular order.
+  pub fn gamepads(&self) -> impl Iterator<Item = (GamepadId, &GamepadState)> {
+    self.gamepads.iter().map(|(&id, gamepad_state)| (id, gamepad_state))
+  }
+
+  /// Returns the state of the given gamepad, if it is connected.
+  #[inline]
+  pub fn gamepad(&self, id: GamepadId) -> Option<&GamepadState> {
+    self.gamepads.get(&id)
+  }
+
+  /// Returns the gamepad events received during the current frame.
+  #[inline]
+  pub fn gamepad_events(&self) -> &[GamepadEvent] {
+    &self.gamepad_events
+  }
+
+  /// Returns a copy of this input state with the given consumed input filtered out. See [`WindowState::unconsumed_input`].
+  pub fn without_consumed(&self, consumed: &ConsumedInput) -> InputState {
+    let mut input_state = self.clone();
+    input_state.remove_consumed(consumed);
+    input_state
+  }
+
+  fn remove_consumed(&mut self, consumed: &ConsumedInput) {
+    if consumed.is_keyboard_consumed() {
+      self.key_actions.clear();
+      self.keys_held_physical.clear();
+      self.keys_held_logical.clear();
+      self.modifiers = Modifiers::default();
+      self.text.clear();
+      self.preedit.clear();
+      self.preedit_cursor = None;
+    } else {
+      // a key consumed by its key code also hides the named key it produced, and vice versa
+      for key_action in self.key_actions.iter().filter(|&key_action| consumed.is_key_action_consumed(key_action)) {
+        if let PhysicalKey::Code(key_code) = key_action.physical_key {
+          self.keys_held_physical.remove(&key_code);
+        };
+
+        if let LogicalKey::Named(named_key) = key_action.logical_key {
+          self.keys_held_logical.remove(&named_key);
+        };
+      };
+
+      self.key_actions.retain(|key_action| !consumed.is_key_action_consumed(key_action));
+      self.keys_held_physical.retain(|key_code, _| !consumed.is_key_consumed_physical(key_code));
+      self.keys_held_logical.retain(|named_key, _| !consumed.is_key_consumed_logical(named_key));
+    };
+
+    if consumed.is_mouse_consumed() {
+      self.cursor_pos = None;
+      self.cursor_pos_prev = None;
+      self.mouse_motion = (0.0, 0.0);
+      self.has_cursor_not_moved = false;
+    };
+
+    self.mouse_actions.retain(|mouse_action| !consumed.is_mouse_button_consumed(mouse_action.button));
+    self.button_events.retain(|button_event| !consumed.is_mouse_button_consumed(button_event.button()));
+    for (index, button) in BUTTONS.into_iter().enumerate() {
+      if consumed.is_mouse_button_consumed(button) {
+        self.set_button_value(button, false);
+        self.button_states[index] = ButtonState::default();
+      };
+    };
+
+    if consumed.is_scroll_consumed() {
+      self.scroll_events.clear();
+    };
+
+    if consumed.is_touch_consumed() {
+      self.gestures.clear();
+    };
+
+    self.touches.retain(|touch| !consumed.is_touch_point_consumed(touch.id));
+    self.touch_points.retain(|&id, _| !consumed.is_touch_point_consumed(id));
+    self.touches_started.retain(|&id| !consumed.is_touch_point_consumed(id));
+    self.touches_ended.retain(|touch_point| !consumed.is_touch_point_consumed(touch_point.id));
+  }
+
   fn set_button_value(&mut self, button: MouseButton, value: bool) {
     match button {
       MouseButton::Left => self.mouse_left_held = value,
@@ -297,48 +676,142 @@ impl InputState {
 
   fn reset(&mut self) {
     self.cursor_pos_prev = self.cursor_pos;
+    self.mouse_motion = (0.0, 0.0);
     self.mouse_actions.clear();
+    self.button_events.clear();
     self.key_actions.clear();
-    self.keys_held_physical.clear();
-    self.keys_held_logical.clear();
+    self.text.clear();
     self.has_cursor_not_moved = false;
+    self.scroll_events.clear();
     self.gestures.clear();
     self.touches.clear();
+    self.touches_started.clear();
+    self.touches_ended.clear();
+    for touch_point in self.touch_points.values_mut() {
+      touch_point.position_prev = touch_point.position;
+    };
+
     self.axis_motions.clear();
+    self.gamepad_events.clear();
+    for gamepad_state in self.gamepads.values_mut() {
+      gamepad_state.reset();
+    };
+  }
+
+  /// Timestamps the keys and touches that have been pressed or started since the previous frame started.
+  fn begin_frame(&mut self, now: Instant) {
+    self.frame_instant = Some(now);
+    for pressed_at in self.keys_held_physical.values_mut().chain(self.keys_held_logical.values_mut()) {
+      pressed_at.get_or_insert(now);
+    };
+
+    for touch_point in self.touch_points.values_mut().chain(self.touches_ended.iter_mut()) {
+      touch_point.started_at.get_or_insert(now);
+    };
   }
 
-  fn handle_keyboard_input(&mut self, event: &KeyEvent) {
+  fn handle_keyboard_input(&mut self, physical_key: PhysicalKey, logical_key: &LogicalKey, text: Option<&str>, state: ElementState, repeat: bool) {
+    // an enabled IME commits the text itself, which would otherwise be typed twice
+    if state.is_pressed() && !self.ime_enabled && let Some(text) = text {
+      self.text.extend(text.chars().filter(|c| !c.is_control()));
+    };
+
     self.key_actions.push(KeyAction {
-      physical_key: event.physical_key,
-      logical_key: event.logical_key.clone(),
-      state: match event.state {
-        ElementState::Pressed if event.repeat => KeyActionState::Repeating,
+      physical_key,
+      logical_key: logical_key.clone(),
+      state: match state {
+        ElementState::Pressed if repeat => KeyActionState::Repeating,
         ElementState::Pressed => KeyActionState::Pressed,
         ElementState::Released => KeyActionState::Released
       }
     });
 
-    if let PhysicalKey::Code(key_code) = event.physical_key {
-      match event.state {
-        ElementState::Pressed => self.keys_held_physical.insert(key_code),
-        ElementState::Released => self.keys_held_physical.remove(&key_code)
+    if let PhysicalKey::Code(key_code) = physical_key {
+      match state {
+        ElementState::Pressed => { self.keys_held_physical.entry(key_code).or_insert(None); },
+        ElementState::Released => { self.keys_held_physical.remove(&key_code); }
       };
     };
 
-    if let LogicalKey::Named(named_key) = event.logical_key {
-      match event.state {
-        ElementState::Pressed => self.keys_held_logical.insert(named_key),
-        ElementState::Released => self.keys_held_logical.remove(&named_key)
+    if let LogicalKey::Named(named_key) = *logical_key {
+      match state {
+        ElementState::Pressed => { self.keys_held_logical.entry(named_key).or_insert(None); },
+        ElementState::Released => { self.keys_held_logical.remove(&named_key); }
       };
     };
   }
 
-  fn handle_mouse_input(&mut self, state: ElementState, button: MouseButton) {
+  fn handle_mouse_input(&mut self, state: ElementState, button: MouseButton, click_config: &ClickConfig, scale_factor: f64) {
     let condition = state.is_pressed();
+    let was_pressed = self[button];
 
     self.set_button_value(button, condition);
     self.mouse_actions.push(MouseAction { button, state });
     self.has_cursor_not_moved = condition;
+
+    let Some(index) = button_index(button) else { return };
+    let button_state = &mut self.button_states[index];
+    let position = self.cursor_pos.unwrap_or(button_state.position);
+    match state {
+      ElementState::Pressed => {
+        // presses are timed by the start of the latest frame, so that intervals are consistent when replayed
+        let max_distance = click_config.multi_click_max_distance * scale_factor as f32;
+        let is_multi_click = button_state.press_position.is_some_and(|press_position| {
+          (position.x - press_position.x).hypot(position.y - press_position.y) <= max_distance
+        }) && match (button_state.pressed_at, self.frame_instant) {
+          (Some(pressed_at), Some(frame_instant)) => frame_instant.saturating_duration_since(pressed_at) <= click_config.multi_click_interval,
+          _ => true
+        };
+
+        button_state.click_count = if is_multi_click { button_state.click_count + 1 } else { 1 };
+        button_state.press_position = Some(position);
+        button_state.pressed_at = self.frame_instant;
+        button_state.position = position;
+        button_state.dragging = false;
+      },
+      ElementState::Released => {
+        // a release without a recorded press, e.g. one that started outside the window, is not a click
+        if !was_pressed { return };
+        let Some(origin) = button_state.press_position else { return };
+        button_state.position = position;
+        self.button_events.push(if replace(&mut button_state.dragging, false) {
+          let delta = PhysicalPosition::new(position.x - origin.x, position.y - origin.y);
+          ButtonEvent::DragEnded { button, origin, delta }
+        } else {
+          ButtonEvent::Click { button, position, count: button_state.click_count }
+        });
+      }
+    };
+  }
+
+  /// Tracks the cursor for every held button, starting drags once they move far enough.
+  fn handle_cursor_drag(&mut self, position: PhysicalPosition<f32>, click_config: &ClickConfig, scale_factor: f64) {
+    let drag_threshold = click_config.drag_threshold * scale_factor as f32;
+    for (index, &button) in BUTTONS.iter().enumerate() {
+      if !self[button] { continue };
+      let button_state = &mut self.button_states[index];
+      button_state.position = position;
+
+      let Some(origin) = button_state.press_position else { continue };
+      if !button_state.dragging && (position.x - origin.x).hypot(position.y - origin.y) > drag_threshold {
+        button_state.dragging = true;
+        self.button_events.push(ButtonEvent::DragStarted { button, origin, position });
+      };
+    };
+  }
+
+  /// Records a scroll event, converting between lines and pixels using the line height in physical pixels.
+  fn handle_mouse_scroll(&mut self, delta: MouseScrollDelta, phase: TouchPhase, line_height: f64) {
+    let (lines, pixels) = match delta {
+      MouseScrollDelta::LineDelta(x, y) => {
+        ((x, y), PhysicalPosition::new(x as f64 * line_height, y as f64 * line_height))
+      },
+      MouseScrollDelta::PixelDelta(pixels) => {
+        (((pixels.x / line_height) as f32, (pixels.y / line_height) as f32), pixels)
+      }
+    };
+
+    self.scroll_events.push(ScrollEvent { delta, phase, lines, pixels });
   }
 
   fn handle_gesture(&mut self, gesture: Gesture) {
@@ -347,11 +820,74 @@ impl InputState {
 
   fn handle_touch(&mut self, touch: Touch) {
     self.touches.push(touch);
+
+    let Touch { device_id, phase, location, force, id } = touch;
+    if phase == TouchPhase::Started {
+      self.touches_started.push(id);
+    };
+
+    let touch_point = self.touch_points.entry(id).or_insert(TouchPoint {
+      id,
+      device_id,
+      phase,
+      start_position: location,
+      position: location,
+      position_prev: location,
+      force,
+      started_at: None
+    });
+
+    touch_point.phase = phase;
+    touch_point.position = location;
+    touch_point.force = force;
+
+    if matches!(phase, TouchPhase::Ended | TouchPhase::Cancelled) {
+      let touch_point = self.touch_points.remove(&id).unwrap_unreachable();
+      self.touches_ended.push(touch_point);
+    };
+  }
+
+  fn handle_ime(&mut self, event: &Ime) {
+    match event {
+      Ime::Enabled => self.ime_enabled = true,
+      Ime::Preedit(preedit, preedit_cursor) => {
+        self.preedit.clone_from(preedit);
+        self.preedit_cursor = *preedit_cursor;
+      },
+      Ime::Commit(text) => self.text.push_str(text),
+      Ime::Disabled => {
+        self.ime_enabled = false;
+        self.preedit.clear();
+        self.preedit_cursor = None;
+      }
+    };
   }
 
   fn handle_axis_motion(&mut self, axis_motion: AxisMotion) {
     self.axis_motions.push(axis_motion);
   }
+
+  fn handle_gamepad_event(&mut self, event: GamepadEvent, gamepad_config: &GamepadConfig) {
+    match event {
+      GamepadEvent::Connected { id, ref name } => {
+        self.gamepads.insert(id, GamepadState::new(name.clone()));
+      },
+      GamepadEvent::Disconnected { id } => {
+        self.gamepads.remove(&id);
+      },
+      // input from gamepads which aren't connected, such as stray events after a disconnect, is ignored
+      GamepadEvent::Button { id, button, state } => match self.gamepads.get_mut(&id) {
+        Some(gamepad_state) => gamepad_state.handle_button(button, state),
+        None => return
+      },
+      GamepadEvent::Axis { id, axis, value } => match self.gamepads.get_mut(&id) {
+        Some(gamepad_state) => gamepad_state.handle_axis(axis, value, gamepad_config),
+        None => return
+      }
+    };
+
+    self.gamepad_events.push(event);
+  }
 }
 
 impl Default for InputState {
@@ -360,20 +896,36 @@ impl Default for InputState {
     InputState {
       cursor_pos: None,
       cursor_pos_prev: None,
+      last_cursor_pos: None,
+      cursor_inside: false,
+      mouse_motion: (0.0, 0.0),
       mouse_actions: Vec::new(),
       mouse_left_held: false,
       mouse_right_held: false,
       mouse_middle_held: false,
       mouse_back_held: false,
       mouse_forward_held: false,
+      button_states: [ButtonState::default(); 5],
+      button_events: Vec::new(),
       has_cursor_not_moved: false,
       key_actions: Vec::new(),
-      keys_held_physical: AHashSet::new(),
-      keys_held_logical: AHashSet::new(),
+      keys_held_physical: AHashMap::new(),
+      keys_held_logical: AHashMap::new(),
+      frame_instant: None,
       modifiers: Modifiers::default(),
+      text: String::new(),
+      ime_enabled: false,
+      preedit: String::new(),
+      preedit_cursor: None,
+      scroll_events: Vec::new(),
       gestures: Vec::new(),
       touches: Vec::new(),
-      axis_motions: Vec::new()
+      touch_points: AHashMap::new(),
+      touches_started: Vec::new(),
+      touches_ended: Vec::new(),
+      axis_motions: Vec::new(),
+      gamepads: AHashMap::new(),
+      gamepad_events: Vec::new()
     }
   }
 }
@@ -383,7 +935,7 @@ impl Index<KeyCode> for InputState {
 
   #[inline]
   fn index(&self, key_code: KeyCode) -> &bool {
-    if self.keys_held_physical.contains(&key_code) { &true } else { &false }
+    if self.keys_held_physical.contains_key(&key_code) { &true } else { &false }
   }
 }
 
@@ -392,7 +944,7 @@ impl Index<NamedKey> for InputState {
 
   #[inline]
   fn index(&self, named_key: NamedKey) -> &bool {
-    if self.keys_held_logical.contains(&named_key) { &true } else { &false }
+    if self.keys_held_logical.contains_key(&named_key) { &true } else { &false }
   }
 }
 
@@ -456,30 +1008,181 @@ pub struct MouseAction {
   pub state: ElementState
 }
 
+/// A finger touching the window, tracked from when it started until it ended. See [`InputState::touch_points`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchPoint {
+  pub id: u64,
+  pub device_id: DeviceId,
+  /// The phase of the most recent event for this touch.
+  pub phase: TouchPhase,
+  /// Where the touch started.
+  pub start_position: PhysicalPosition<f64>,
+  pub position: PhysicalPosition<f64>,
+  /// Where the touch was at the end of the previous frame.
+  pub position_prev: PhysicalPosition<f64>,
+  pub force: Option<Force>,
+  /// The start of the frame in which the touch started, or `None` if that frame has not started yet,
+  /// which can only be observed from event callbacks.
+  pub started_at: Option<Instant>
+}
+
+impl TouchPoint {
+  /// How far the touch moved during the current frame.
+  pub fn delta(&self) -> PhysicalPosition<f64> {
+    PhysicalPosition::new(self.position.x - self.position_prev.x, self.position.y - self.position_prev.y)
+  }
+
+  /// How far the touch has moved since it started.
+  pub fn total_delta(&self) -> PhysicalPosition<f64> {
+    PhysicalPosition::new(self.position.x - self.start_position.x, self.position.y - self.start_position.y)
+  }
+
+  /// Whether the touch has ended or was cancelled.
+  #[inline]
+  pub fn has_ended(&self) -> bool {
+    matches!(self.phase, TouchPhase::Ended | TouchPhase::Cancelled)
+  }
+}
+
+/// The mouse buttons whose clicks and drags are tracked, in the order their states are stored.
+const BUTTONS: [MouseButton; 5] = [MouseButton::Left, MouseButton::Right, MouseButton::Middle, MouseButton::Back, MouseButton::Forward];
+
+#[inline]
+fn button_index(button: MouseButton) -> Option<usize> {
+  BUTTONS.iter().position(|&b| b == button)
+}
+
+/// The click and drag state of one of the five tracked mouse buttons. See [`InputState::button_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ButtonState {
+  /// Where the cursor was when the button was last pressed. Remains set after the button is released.
+  pub press_position: Option<PhysicalPosition<f32>>,
+  /// The last known cursor position while the button was held.
+  pub position: PhysicalPosition<f32>,
+  /// The number of presses in quick succession ending with the latest one, such as `2` for a double click.
+  pub click_count: u32,
+  /// Whether the cursor has moved beyond the drag threshold since the button was pressed.
+  pub dragging: bool,
+  pressed_at: Option<Instant>
+}
+
+/// A click or drag of a mouse button. See [`InputState::button_events`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ButtonEvent {
+  /// The button was released without being dragged.
+  Click {
+    button: MouseButton,
+    position: PhysicalPosition<f32>,
+    count: u32
+  },
+  /// The cursor moved beyond the drag threshold while the button was held.
+  DragStarted {
+    button: MouseButton,
+    origin: PhysicalPosition<f32>,
+    position: PhysicalPosition<f32>
+  },
+  /// The button was released after being dragged.
+  DragEnded {
+    button: MouseButton,
+    origin: PhysicalPosition<f32>,
+    delta: PhysicalPosition<f32>
+  }
+}
+
+impl ButtonEvent {
+  #[inline]
+  pub fn button(&self) -> MouseButton {
+    match *self {
+      ButtonEvent::Click { button, .. } | ButtonEvent::DragStarted { button, .. } | ButtonEvent::DragEnded { button, .. } => button
+    }
+  }
+}
+
+/// Thresholds used to count clicks and detect drags of mouse buttons, in logical pixels. See [`WindowState::set_click_config`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ClickConfig {
+  /// The longest time between two presses for the second one to continue a double or triple click.
+  pub multi_click_interval: Duration,
+  /// The farthest apart two presses may be for the second one to continue a double or triple click.
+  pub multi_click_max_distance: f32,
+  /// How far the cursor must move while a button is held before it starts dragging.
+  pub drag_threshold: f32
+}
+
+impl Default for ClickConfig {
+  fn default() -> Self {
+    ClickConfig {
+      multi_click_interval: Duration::from_millis(500),
+      multi_click_max_distance: 4.0,
+      drag_threshold: 4.0
+    }
+  }
+}
+
+/// A mouse wheel or touchpad scroll, measured both in lines and in physical pixels.
+/// See [`WindowState::set_scroll_line_height`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollEvent {
+  /// The scroll delta as reported by the platform.
+  pub delta: MouseScrollDelta,
+  pub phase: TouchPhase,
+  pub lines: (f32, f32),
+  pub pixels: PhysicalPosition<f64>
+}
+
+/// Device ids are not serialized, and are replaced with [`DeviceId::dummy`] when deserialized.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Gesture {
   Pinch {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
     device_id: DeviceId,
     delta: f64,
     phase: TouchPhase
   },
   Pan {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
     device_id: DeviceId,
     delta: PhysicalPosition<f32>,
     phase: TouchPhase
   },
   DoubleTap {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
     device_id: DeviceId
   },
   Rotation {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
     device_id: DeviceId,
     delta: f32,
     phase: TouchPhase
   },
   TouchpadPressure {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
     device_id: DeviceId,
     pressure: f32,
     stage: i64
+  },
+  /// Synthesized by a [`GestureRecognizer`] when a pointer is pressed and quickly released without moving.
+  Tap {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
+    device_id: DeviceId,
+    position: PhysicalPosition<f64>
+  },
+  /// Synthesized by a [`GestureRecognizer`] when a pointer is held down without moving.
+  LongPress {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
+    device_id: DeviceId,
+    position: PhysicalPosition<f64>
+  },
+  /// Synthesized by a [`GestureRecognizer`] when a pointer is quickly moved and released.
+  /// The velocity is the average over the whole swipe, in physical pixels per second.
+  Swipe {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
+    device_id: DeviceId,
+    direction: SwipeDirection,
+    velocity: PhysicalPosition<f64>
   }
 }
 
@@ -490,35 +1193,201 @@ impl Gesture {
       Self::Pan { device_id, .. } => device_id,
       Self::DoubleTap { device_id, .. } => device_id,
       Self::Rotation { device_id, .. } => device_id,
-      Self::TouchpadPressure { device_id, .. } => device_id
+      Self::TouchpadPressure { device_id, .. } => device_id,
+      Self::Tap { device_id, .. } => device_id,
+      Self::LongPress { device_id, .. } => device_id,
+      Self::Swipe { device_id, .. } => device_id
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SwipeDirection {
+  Left,
+  Right,
+  Up,
+  Down
+}
+
+impl SwipeDirection {
+  /// The direction along whichever axis the delta is greatest.
+  pub fn from_delta(delta: PhysicalPosition<f64>) -> Self {
+    match delta.x.abs() >= delta.y.abs() {
+      true if delta.x < 0.0 => SwipeDirection::Left,
+      true => SwipeDirection::Right,
+      false if delta.y < 0.0 => SwipeDirection::Up,
+      false => SwipeDirection::Down
     }
   }
 }
 
+/// Device ids are not serialized, and are replaced with [`DeviceId::dummy`] when deserialized.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AxisMotion {
+  #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
   device_id: DeviceId,
   axis: AxisId,
   value: f64
 }
 
+/// A window event that affects [`InputState`], in a form that can be stored, serialized and replayed.
+/// Device ids are not serialized, and are replaced with [`DeviceId::dummy`] when deserialized.
+///
+#[cfg_attr(feature = "serde", doc = "See [`WindowState::handle_input_event`] and the [`replay`][crate::replay] module.")]
+#[cfg_attr(not(feature = "serde"), doc = "See [`WindowState::handle_input_event`] and the `replay` module, with the `serde` feature.")]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum InputEvent {
+  /// See [`WindowEvent::KeyboardInput`].
+  Keyboard {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
+    device_id: DeviceId,
+    physical_key: PhysicalKey,
+    logical_key: LogicalKey,
+    text: Option<SmolStr>,
+    location: KeyLocation,
+    state: ElementState,
+    repeat: bool
+  },
+  /// See [`WindowEvent::ModifiersChanged`]. Only the [`ModifiersState`][winit::keyboard::ModifiersState] is serialized,
+  /// not which side's keys are pressed.
+  ModifiersChanged(#[cfg_attr(feature = "serde", serde(with = "crate::misc::modifiers_serde"))] Modifiers),
+  /// See [`WindowEvent::CursorMoved`]. The position has not yet been clipped to the window.
+  CursorMoved {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
+    device_id: DeviceId,
+    position: PhysicalPosition<f64>
+  },
+  /// See [`WindowEvent::CursorEntered`].
+  CursorEntered {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
+    device_id: DeviceId
+  },
+  /// See [`WindowEvent::CursorLeft`].
+  CursorLeft {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
+    device_id: DeviceId
+  },
+  /// See [`DeviceEvent::MouseMotion`].
+  MouseMotion {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
+    device_id: DeviceId,
+    delta: (f64, f64)
+  },
+  /// See [`WindowEvent::MouseInput`].
+  MouseInput {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
+    device_id: DeviceId,
+    state: ElementState,
+    button: MouseButton
+  },
+  /// See [`WindowEvent::MouseWheel`].
+  MouseWheel {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
+    device_id: DeviceId,
+    delta: MouseScrollDelta,
+    phase: TouchPhase
+  },
+  /// See [`WindowEvent::Touch`].
+  Touch {
+    #[cfg_attr(feature = "serde", serde(skip, default = "DeviceId::dummy"))]
+    device_id: DeviceId,
+    phase: TouchPhase,
+    location: PhysicalPosition<f64>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::misc::force_serde"))]
+    force: Option<Force>,
+    id: u64
+  },
+  /// See [`EventHandler::on_gesture`].
+  Gesture(Gesture),
+  /// See [`WindowEvent::AxisMotion`].
+  AxisMotion(AxisMotion),
+  /// See [`WindowEvent::Ime`].
+  Ime(Ime),
+  /// See [`GamepadSource`].
+  Gamepad(GamepadEvent),
+  /// See [`WindowEvent::Focused`].
+  Focused(bool)
+}
+
+impl InputEvent {
+  /// Converts a window event into an input event, if it is one.
+  pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+    Some(match *event {
+      WindowEvent::KeyboardInput { device_id, ref event, .. } => InputEvent::from_key_event(device_id, event),
+      WindowEvent::ModifiersChanged(modifiers) => InputEvent::ModifiersChanged(modifiers),
+      WindowEvent::CursorMoved { device_id, position } => InputEvent::CursorMoved { device_id, position },
+      WindowEvent::CursorEntered { device_id } => InputEvent::CursorEntered { device_id },
+      WindowEvent::CursorLeft { device_id } => InputEvent::CursorLeft { device_id },
+      WindowEvent::MouseInput { device_id, state, button } => InputEvent::MouseInput { device_id, state, button },
+      WindowEvent::MouseWheel { device_id, delta, phase } => InputEvent::MouseWheel { device_id, delta, phase },
+      WindowEvent::Touch(Touch { device_id, phase, location, force, id }) => InputEvent::Touch { device_id, phase, location, force, id },
+      WindowEvent::PinchGesture { device_id, delta, phase } => InputEvent::Gesture(Gesture::Pinch { device_id, delta, phase }),
+      WindowEvent::PanGesture { device_id, delta, phase } => InputEvent::Gesture(Gesture::Pan { device_id, delta, phase }),
+      WindowEvent::DoubleTapGesture { device_id } => InputEvent::Gesture(Gesture::DoubleTap { device_id }),
+      WindowEvent::RotationGesture { device_id, delta, phase } => InputEvent::Gesture(Gesture::Rotation { device_id, delta, phase }),
+      WindowEvent::TouchpadPressure { device_id, pressure, stage } => InputEvent::Gesture(Gesture::TouchpadPressure { device_id, pressure, stage }),
+      WindowEvent::AxisMotion { device_id, axis, value } => InputEvent::AxisMotion(AxisMotion { device_id, axis, value }),
+      WindowEvent::Ime(ref event) => InputEvent::Ime(event.clone()),
+      WindowEvent::Focused(focused_state) => InputEvent::Focused(focused_state),
+      _ => return None
+    })
+  }
+
+  /// Converts a device event into an input event, if it is one.
+  pub fn from_device_event(device_id: DeviceId, event: &DeviceEvent) -> Option<Self> {
+    match *event {
+      DeviceEvent::MouseMotion { delta } => Some(InputEvent::MouseMotion { device_id, delta }),
+      _ => None
+    }
+  }
+
+  fn from_key_event(device_id: DeviceId, event: &KeyEvent) -> Self {
+    InputEvent::Keyboard {
+      device_id,
+      physical_key: event.physical_key,
+      logical_key: event.logical_key.clone(),
+      text: event.text.clone(),
+      location: event.location,
+      state: event.state,
+      repeat: event.repeat
+    }
+  }
+}
+
+
+
+/// A keyboard event which did not come from the event loop, such as one replayed from an input recording,
+/// holding the same information as a [`KeyEvent`]. See [`EventHandler::on_synthetic_keyboard_input`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyntheticKeyEvent {
+  pub physical_key: PhysicalKey,
+  pub logical_key: LogicalKey,
+  pub text: Option<SmolStr>,
+  pub location: KeyLocation,
+  pub state: ElementState,
+  pub repeat: bool
+}
+
+
+
+pub trait HasWindow {
+  fn get_window(&self) -> &Window;
+
+  fn get_window_id(&self) -> WindowId {
+    self.get_window().id()
+  }
+}
+
+impl HasWindow for Window {
+  #[inline]
+  fn get_window(&self) -> &Window {
+    self
+  }
+}
 
-
-pub trait HasWindow {
-  fn get_window(&self) -> &Window;
-
-  fn get_window_id(&self) -> WindowId {
-    self.get_window().id()
-  }
-}
-
-impl HasWindow for Window {
-  #[inline]
-  fn get_window(&self) -> &Window {
-    self
-  }
-}
-
 macro_rules! impl_has_window_deref {
   ($(for $Type:ty where $T:ident),* $(,)?) => ($(
     impl<$T: HasWindow> HasWindow for $Type {
@@ -540,39 +1409,197 @@ impl_has_window_deref!(
 
 
 
+#[derive(Debug)]
+enum WindowRequest {
+  Open(Box<WindowAttributes>),
+  Close(WindowId)
+}
+
+/// A queue of window requests shared between an [`Application`] and all of its [`WindowState`]s.
+#[derive(Debug, Clone, Default)]
+struct WindowRequests(Rc<RefCell<Vec<WindowRequest>>>);
+
+impl WindowRequests {
+  fn push(&self, window_request: WindowRequest) {
+    self.0.borrow_mut().push(window_request);
+  }
+
+  fn take(&self) -> Vec<WindowRequest> {
+    take(&mut *self.0.borrow_mut())
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct WindowState<W: HasWindow> {
-  initialized: bool,
   input_state: InputState,
+  frame_timing: FrameTiming,
+  timestep: Option<TimestepState>,
+  gesture_recognizer: Option<GestureRecognizer>,
+  redraw_requested: Cell<bool>,
+  occluded: bool,
   dropped_file: Option<PathBuf>,
+  scroll_line_height: Cell<f32>,
+  click_config: Cell<ClickConfig>,
+  mouse_capture: Cell<bool>,
+  gamepad_config: Cell<GamepadConfig>,
+  relative_mouse_mode: Cell<bool>,
+  cursor_grab_mode: Cell<CursorGrabMode>,
+  consumed_input: RefCell<ConsumedInput>,
   scale_factor: f64,
   window_size: PhysicalSize<u32>,
-  window_holder: W
+  window_requests: WindowRequests,
+  window_id: WindowId,
+  window_holder: Option<W>
 }
 
 impl<W: HasWindow> WindowState<W> {
-  fn new(window_holder: W) -> Self {
+  /// The height of a line of scrolling in logical pixels by default.
+  pub const DEFAULT_SCROLL_LINE_HEIGHT: f32 = 20.0;
+
+  fn new(window_holder: W, window_requests: WindowRequests, timestep: Option<TimestepState>, frame_history_len: usize) -> Self {
     let window = window_holder.get_window();
     WindowState {
-      initialized: false,
       input_state: InputState::default(),
+      frame_timing: FrameTiming::new(frame_history_len),
+      timestep,
+      gesture_recognizer: None,
+      redraw_requested: Cell::new(true),
+      occluded: false,
       dropped_file: None,
+      scroll_line_height: Cell::new(Self::DEFAULT_SCROLL_LINE_HEIGHT),
+      click_config: Cell::new(ClickConfig::default()),
+      mouse_capture: Cell::new(false),
+      gamepad_config: Cell::new(GamepadConfig::default()),
+      relative_mouse_mode: Cell::new(false),
+      cursor_grab_mode: Cell::new(CursorGrabMode::None),
+      consumed_input: RefCell::new(ConsumedInput::default()),
       scale_factor: window.scale_factor(),
       window_size: window.inner_size().into(),
-      window_holder
+      window_requests,
+      window_id: window.id(),
+      window_holder: Some(window_holder)
     }
   }
 
-  fn reset(&mut self) {
+  /// Creates the state of a headless window, which has a size and scale factor but no underlying [`Window`].
+  pub(crate) fn new_headless(window_size: PhysicalSize<u32>, scale_factor: f64, timestep: Option<TimestepState>, frame_history_len: usize) -> Self {
+    WindowState {
+      input_state: InputState::default(),
+      frame_timing: FrameTiming::new(frame_history_len),
+      timestep,
+      gesture_recognizer: None,
+      redraw_requested: Cell::new(true),
+      occluded: false,
+      dropped_file: None,
+      scroll_line_height: Cell::new(Self::DEFAULT_SCROLL_LINE_HEIGHT),
+      click_config: Cell::new(ClickConfig::default()),
+      mouse_capture: Cell::new(false),
+      gamepad_config: Cell::new(GamepadConfig::default()),
+      relative_mouse_mode: Cell::new(false),
+      cursor_grab_mode: Cell::new(CursorGrabMode::None),
+      consumed_input: RefCell::new(ConsumedInput::default()),
+      scale_factor,
+      window_size,
+      window_requests: WindowRequests::default(),
+      window_id: WindowId::dummy(),
+      window_holder: None
+    }
+  }
+
+  pub(crate) fn reset(&mut self) {
     self.input_state.reset();
+    self.consumed_input.get_mut().clear();
     self.dropped_file = None;
   }
 
+  /// Begins a frame, advancing the frame timing only if the frame is going to be rendered.
+  pub(crate) fn begin_frame(&mut self, now: Instant, rendered: bool) {
+    if rendered {
+      self.frame_timing.begin_frame(now);
+    };
+
+    self.input_state.begin_frame(now);
+    self.recenter_cursor();
+  }
+
+  /// Delivers the gestures recognized during the current frame, if gesture recognition is enabled.
+  pub(crate) fn recognize_gestures<T, H: EventHandler<W, T>>(&mut self, handler: &mut H) {
+    let Some(gesture_recognizer) = &mut self.gesture_recognizer else { return };
+    let Some(now) = self.input_state.frame_instant else { return };
+    for gesture in gesture_recognizer.recognize(&self.input_state, now, self.scale_factor) {
+      self.input_state.handle_gesture(gesture);
+      handler.on_gesture(self, gesture);
+    };
+  }
+
+  pub(crate) fn set_gesture_config(&mut self, gesture_config: Option<GestureConfig>) {
+    self.gesture_recognizer = gesture_config.map(GestureRecognizer::new);
+  }
+
+  /// Returns the gesture recognizer, if the application was configured with gesture recognition.
+  #[inline]
+  pub fn gesture_recognizer(&self) -> Option<&GestureRecognizer> {
+    self.gesture_recognizer.as_ref()
+  }
+
+  /// Handles the window requests made to a headless window, returning whether it has been asked to close.
+  /// Headless windows cannot open other windows, so those requests are discarded.
+  pub(crate) fn take_headless_close_request(&self) -> bool {
+    let mut close_requested = false;
+    for window_request in self.window_requests.take() {
+      match window_request {
+        WindowRequest::Open(..) => log::warn!("headless windows cannot open additional windows"),
+        WindowRequest::Close(window_id) => close_requested |= window_id == self.window_id
+      };
+    };
+
+    close_requested
+  }
+
   #[inline]
   pub fn input(&self) -> &InputState {
     &self.input_state
   }
 
+  /// Returns the input of the current frame without the input consumed so far, for layers beneath those that consumed it.
+  /// This only copies the input state once some input has been consumed. See [`crate::input_layers`].
+  pub fn unconsumed_input(&self) -> Cow<'_, InputState> {
+    let consumed_input = self.consumed_input.borrow();
+    if consumed_input.is_empty() {
+      Cow::Borrowed(&self.input_state)
+    } else {
+      Cow::Owned(self.input_state.without_consumed(&consumed_input))
+    }
+  }
+
+  /// Returns the input consumed so far during the current frame.
+  pub fn consumed_input(&self) -> ConsumedInput {
+    self.consumed_input.borrow().clone()
+  }
+
+  /// Marks input as consumed for the rest of the current frame, hiding it from [`WindowState::unconsumed_input`].
+  pub fn consume_input(&self, f: impl FnOnce(&mut ConsumedInput)) {
+    f(&mut self.consumed_input.borrow_mut());
+  }
+
+  /// Returns timing information about the frames that have been run for this window.
+  #[inline]
+  pub fn frame_timing(&self) -> &FrameTiming {
+    &self.frame_timing
+  }
+
+  /// Shortcut to [`FrameTiming::delta_time`].
+  #[inline]
+  pub fn delta_time(&self) -> Duration {
+    self.frame_timing.delta_time()
+  }
+
+  /// Returns the state of the fixed timestep, if the application was configured with a [`FixedTimestep`].
+  #[inline]
+  pub fn timestep(&self) -> Option<&TimestepState> {
+    self.timestep.as_ref()
+  }
+
   #[inline]
   pub fn dropped_file(&self) -> Option<&Path> {
     self.dropped_file.as_deref()
@@ -583,29 +1610,182 @@ impl<W: HasWindow> WindowState<W> {
     self.scale_factor
   }
 
+  /// Requests that this window be redrawn at the end of the current frame.
+  /// This is only necessary when using [`RedrawMode::OnDemand`], as other modes redraw every frame.
+  #[inline]
+  pub fn request_redraw(&self) {
+    self.redraw_requested.set(true);
+  }
+
+  /// The height of a line of scrolling in logical pixels, used to convert scroll deltas between lines and pixels.
+  #[inline]
+  pub fn scroll_line_height(&self) -> f32 {
+    self.scroll_line_height.get()
+  }
+
+  /// Sets the height of a line of scrolling in logical pixels, which affects scroll events received from then on.
+  /// See [`InputState::scroll_events`].
+  #[inline]
+  pub fn set_scroll_line_height(&self, scroll_line_height: f32) {
+    self.scroll_line_height.set(scroll_line_height);
+  }
+
+  /// The thresholds used to count clicks and detect drags of mouse buttons.
+  #[inline]
+  pub fn click_config(&self) -> ClickConfig {
+    self.click_config.get()
+  }
+
+  /// Sets the thresholds used to count clicks and detect drags of mouse buttons, which affect input received from then on.
+  /// See [`InputState::button_events`].
+  #[inline]
+  pub fn set_click_config(&self, click_config: ClickConfig) {
+    self.click_config.set(click_config);
+  }
+
+  /// Whether mouse capture is enabled. See [`WindowState::set_mouse_capture`].
+  #[inline]
+  pub fn is_mouse_capture_enabled(&self) -> bool {
+    self.mouse_capture.get()
+  }
+
+  /// Enables or disables mouse capture, which is disabled by default.
+  /// While enabled and any mouse button is held, cursor positions outside of the window are still reported
+  /// through [`InputState::cursor_pos`] and [`EventHandler::on_cursor_moved`], such as when dragging a slider past the edge of the window.
+  /// Whether the platform delivers those positions at all depends on the platform.
+  #[inline]
+  pub fn set_mouse_capture(&self, enabled: bool) {
+    self.mouse_capture.set(enabled);
+  }
+
+  fn is_mouse_captured(&self) -> bool {
+    self.mouse_capture.get() && self.input_state.is_any_button_held()
+  }
+
+  /// The deadzones applied to gamepad axes.
+  #[inline]
+  pub fn gamepad_config(&self) -> GamepadConfig {
+    self.gamepad_config.get()
+  }
+
+  /// Sets the deadzones applied to gamepad axes, which affect axis values received from then on.
+  /// See [`GamepadState::axis`].
+  #[inline]
+  pub fn set_gamepad_config(&self, gamepad_config: GamepadConfig) {
+    self.gamepad_config.set(gamepad_config);
+  }
+
+  /// Whether relative mouse mode is enabled. See [`WindowState::set_relative_mouse_mode`].
+  #[inline]
+  pub fn is_relative_mouse_mode(&self) -> bool {
+    self.relative_mouse_mode.get()
+  }
+
+  /// Enables or disables relative mouse mode, for first-person controls which read [`InputState::mouse_motion`].
+  ///
+  /// While enabled, the cursor is hidden and locked in place, or confined to the window and recentered every frame
+  /// on platforms which cannot lock it. The cursor is released while the window is unfocused, and grabbed again once it regains focus.
+  /// Headless windows only keep track of whether the mode is enabled.
+  pub fn set_relative_mouse_mode(&self, enabled: bool) {
+    if self.relative_mouse_mode.replace(enabled) == enabled { return };
+    self.apply_relative_mouse_mode(enabled && self.is_focused());
+  }
+
+  /// The grab mode of the cursor currently in effect, which is [`CursorGrabMode::None`] unless relative mouse mode
+  /// is enabled and the window is focused. See [`WindowState::set_relative_mouse_mode`].
+  #[inline]
+  pub fn cursor_grab_mode(&self) -> CursorGrabMode {
+    self.cursor_grab_mode.get()
+  }
+
+  fn apply_relative_mouse_mode(&self, grabbed: bool) {
+    let Some(window) = self.try_window() else { return };
+    let cursor_grab_mode = if grabbed {
+      [CursorGrabMode::Locked, CursorGrabMode::Confined].into_iter()
+        .find(|&cursor_grab_mode| window.set_cursor_grab(cursor_grab_mode).is_ok())
+        .unwrap_or_else(|| {
+          log::warn!("failed to grab the cursor for relative mouse mode");
+          CursorGrabMode::None
+        })
+    } else {
+      if let Err(error) = window.set_cursor_grab(CursorGrabMode::None) {
+        log::warn!("failed to release the cursor: {error}");
+      };
+
+      CursorGrabMode::None
+    };
+
+    window.set_cursor_visible(!grabbed);
+    self.cursor_grab_mode.set(cursor_grab_mode);
+  }
+
+  /// Moves a confined cursor back to the center of the window, so that it never stops at the edge of the window.
+  fn recenter_cursor(&self) {
+    if self.cursor_grab_mode.get() != CursorGrabMode::Confined { return };
+    let Some(window) = self.try_window() else { return };
+    let center = PhysicalPosition::new(self.window_size.width / 2, self.window_size.height / 2);
+    if let Err(error) = window.set_cursor_position(center) {
+      log::warn!("failed to recenter the cursor: {error}");
+    };
+  }
+
+  /// Whether this window is entirely occluded. See [`WindowEvent::Occluded`].
+  #[inline]
+  pub fn is_occluded(&self) -> bool {
+    self.occluded
+  }
+
+  /// Whether this window is currently occluded or minimized, and there is no use in redrawing it.
+  fn is_hidden(&self) -> bool {
+    self.occluded || self.is_minimized() == Some(true)
+  }
+
   /// Shortcut to [`Window::theme`].
   pub fn theme(&self) -> Option<Theme> {
-    self.window().theme()
+    self.try_window().and_then(Window::theme)
   }
 
-  /// Shortcut to [`Window::has_focus`].
+  /// Shortcut to [`Window::has_focus`]. Headless windows are never focused.
   pub fn is_focused(&self) -> bool {
-    self.window().has_focus()
+    self.try_window().is_some_and(Window::has_focus)
   }
 
   /// Shortcut to [`Window::is_visible`].
   pub fn is_visible(&self) -> Option<bool> {
-    self.window().is_visible()
+    self.try_window().and_then(Window::is_visible)
   }
 
   /// Shortcut to [`Window::is_maximized`].
   pub fn is_maximized(&self) -> bool {
-    self.window().is_maximized()
+    self.try_window().is_some_and(Window::is_maximized)
   }
 
   /// Shortcut to [`Window::is_minimized`].
   pub fn is_minimized(&self) -> Option<bool> {
-    self.window().is_minimized()
+    self.try_window().and_then(Window::is_minimized)
+  }
+
+  /// Shortcut to [`Window::set_ime_allowed`], which must be called for the window to receive IME input.
+  /// See [`InputState::preedit`].
+  pub fn set_ime_allowed(&self, allowed: bool) {
+    if let Some(window) = self.try_window() {
+      window.set_ime_allowed(allowed);
+    };
+  }
+
+  /// Shortcut to [`Window::set_ime_cursor_area`], which positions the IME candidate window next to the given area,
+  /// usually the text cursor of the focused text field.
+  pub fn set_ime_cursor_area(&self, position: PhysicalPosition<f32>, size: PhysicalSize<f32>) {
+    if let Some(window) = self.try_window() {
+      window.set_ime_cursor_area(position, size);
+    };
+  }
+
+  /// Shortcut to [`Window::set_ime_purpose`].
+  pub fn set_ime_purpose(&self, purpose: ImePurpose) {
+    if let Some(window) = self.try_window() {
+      window.set_ime_purpose(purpose);
+    };
   }
 
   #[inline]
@@ -613,14 +1793,57 @@ impl<W: HasWindow> WindowState<W> {
     self.window_size
   }
 
+  /// Returns the underlying window. Panics if this window is headless, see [`WindowState::try_window`].
   #[inline]
   pub fn window(&self) -> &Window {
-    self.window_holder.get_window()
+    self.window_holder().get_window()
   }
 
+  /// Returns the window holder. Panics if this window is headless, see [`WindowState::try_window_holder`].
   #[inline]
   pub fn window_holder(&self) -> &W {
-    &self.window_holder
+    self.window_holder.as_ref().expect("headless windows have no underlying window")
+  }
+
+  /// Returns the underlying window, or `None` if this window is headless.
+  #[inline]
+  pub fn try_window(&self) -> Option<&Window> {
+    self.window_holder.as_ref().map(W::get_window)
+  }
+
+  /// Returns the window holder, or `None` if this window is headless.
+  #[inline]
+  pub fn try_window_holder(&self) -> Option<&W> {
+    self.window_holder.as_ref()
+  }
+
+  /// Whether this window is headless, meaning that it is rendered offscreen and has no underlying [`Window`].
+  #[inline]
+  pub fn is_headless(&self) -> bool {
+    self.window_holder.is_none()
+  }
+
+  /// Shortcut to [`Window::id`]. Headless windows have a [dummy][WindowId::dummy] ID.
+  #[inline]
+  pub fn id(&self) -> WindowId {
+    self.window_id
+  }
+
+  /// Requests that a new window be opened with the given attributes once the current callback returns.
+  /// The window is created by [`EventHandler::create_window`],
+  /// after which [`EventHandler::on_window_opened`] is called.
+  pub fn open_window(&self, window_attributes: WindowAttributes) {
+    self.window_requests.push(WindowRequest::Open(Box::new(window_attributes)));
+  }
+
+  /// Requests that the window with the given ID be closed once the current callback returns.
+  pub fn close_window(&self, window_id: WindowId) {
+    self.window_requests.push(WindowRequest::Close(window_id));
+  }
+
+  /// Requests that this window be closed once the current callback returns.
+  pub fn close(&self) {
+    self.close_window(self.id());
   }
 
   /// Only returns `Some` when the given position is within the window frame.
@@ -633,11 +1856,11 @@ impl<W: HasWindow> WindowState<W> {
     }
   }
 
-  pub fn handle_window_event<T, H: EventHandler<W, T>>(&mut self, handler: &mut H, event: WindowEvent, event_loop: &ActiveEventLoop) {
+  pub fn handle_window_event<T, H: EventHandler<W, T>>(&mut self, handler: &mut H, event: WindowEvent) {
     match event {
       WindowEvent::CloseRequested => {
         if handler.on_close_requested(self) {
-          event_loop.exit();
+          self.close();
         };
       },
       WindowEvent::Destroyed => (),
@@ -645,13 +1868,8 @@ impl<W: HasWindow> WindowState<W> {
         handler.render(self);
       },
       WindowEvent::ActivationTokenDone { .. } => (),
-      WindowEvent::Focused(focused_state) => {
-        if !focused_state {
-          self.input_state = InputState::default();
-        };
-        handler.on_focus_changed(self, focused_state);
-      },
       WindowEvent::Occluded(occluded_state) => {
+        self.occluded = occluded_state;
         handler.on_occlusion_changed(self, occluded_state);
       },
       WindowEvent::HoveredFileCancelled => {
@@ -674,104 +1892,770 @@ impl<W: HasWindow> WindowState<W> {
         handler.on_resized(self, self.window_size, self.scale_factor);
       },
       WindowEvent::KeyboardInput { event, .. } => {
-        self.input_state.handle_keyboard_input(&event);
+        self.input_state.handle_keyboard_input(event.physical_key, &event.logical_key, event.text.as_deref(), event.state, event.repeat);
         handler.on_keyboard_input(self, event);
       },
-      WindowEvent::Ime(event) => {
-        handler.on_text_input(self, event);
+      WindowEvent::ThemeChanged(..) => (),
+      event => {
+        if let Some(input_event) = InputEvent::from_window_event(&event) {
+          self.handle_input_event(handler, input_event);
+        };
+      }
+    }
+  }
+
+  /// Applies an input event to this window's [`InputState`] and calls the corresponding [`EventHandler`] callback.
+  /// This is how [`WindowState::handle_window_event`] processes input, and can be used to inject recorded or synthetic input.
+  ///
+  /// Since a winit [`KeyEvent`] cannot be constructed outside of winit, [`InputEvent::Keyboard`] is delivered to
+  /// [`EventHandler::on_synthetic_keyboard_input`] rather than [`EventHandler::on_keyboard_input`].
+  pub fn handle_input_event<T, H: EventHandler<W, T>>(&mut self, handler: &mut H, event: InputEvent) {
+    match event {
+      InputEvent::Keyboard { physical_key, logical_key, text, location, state, repeat, .. } => {
+        self.input_state.handle_keyboard_input(physical_key, &logical_key, text.as_deref(), state, repeat);
+        handler.on_synthetic_keyboard_input(self, SyntheticKeyEvent { physical_key, logical_key, text, location, state, repeat });
+      },
+      InputEvent::ModifiersChanged(modifiers) => {
+        self.input_state.modifiers = modifiers;
       },
-      WindowEvent::CursorEntered { .. } | WindowEvent::CursorLeft { .. } => (),
-      WindowEvent::CursorMoved { position, .. } => {
-        if let Some(position) = self.clip_pos_in_frame(position.cast()) {
+      InputEvent::CursorMoved { position, .. } => {
+        let position = position.cast();
+        self.input_state.last_cursor_pos = Some(position);
+        if let Some(position) = self.clip_pos_in_frame(position).or_else(|| self.is_mouse_captured().then_some(position)) {
           self.input_state.cursor_pos = Some(position);
           self.input_state.has_cursor_not_moved = false;
+          self.input_state.handle_cursor_drag(position, &self.click_config.get(), self.scale_factor);
           handler.on_cursor_moved(self, position);
         } else {
           self.input_state.cursor_pos = None;
         };
       },
-      WindowEvent::MouseInput { state, button, .. } => {
-        self.input_state.handle_mouse_input(state, button);
-        handler.on_mouse_input(self, state, button);
-      },
-      WindowEvent::MouseWheel { delta, .. } => {
-        handler.on_mouse_scroll(self, delta);
+      InputEvent::MouseMotion { delta: (x, y), .. } => {
+        self.input_state.mouse_motion.0 += x;
+        self.input_state.mouse_motion.1 += y;
       },
-      WindowEvent::ModifiersChanged(modifiers) => {
-        self.input_state.modifiers = modifiers;
+      InputEvent::CursorEntered { .. } => {
+        self.input_state.cursor_inside = true;
+        handler.on_cursor_entered(self);
       },
-      WindowEvent::PinchGesture { device_id, delta, phase } => {
-        let gesture = Gesture::Pinch { device_id, delta, phase };
-        self.input_state.handle_gesture(gesture);
-        handler.on_gesture(self, gesture);
+      InputEvent::CursorLeft { .. } => {
+        self.input_state.cursor_inside = false;
+        if !self.is_mouse_captured() {
+          self.input_state.cursor_pos = None;
+        };
+
+        handler.on_cursor_left(self);
       },
-      WindowEvent::PanGesture { device_id, delta, phase } => {
-        let gesture = Gesture::Pan { device_id, delta, phase };
-        self.input_state.handle_gesture(gesture);
-        handler.on_gesture(self, gesture);
+      InputEvent::MouseInput { state, button, .. } => {
+        self.input_state.handle_mouse_input(state, button, &self.click_config.get(), self.scale_factor);
+        handler.on_mouse_input(self, state, button);
       },
-      WindowEvent::DoubleTapGesture { device_id } => {
-        let gesture = Gesture::DoubleTap { device_id };
-        self.input_state.handle_gesture(gesture);
-        handler.on_gesture(self, gesture);
+      InputEvent::MouseWheel { delta, phase, .. } => {
+        let line_height = self.scroll_line_height.get() as f64 * self.scale_factor;
+        self.input_state.handle_mouse_scroll(delta, phase, line_height);
+        handler.on_mouse_scroll(self, delta);
       },
-      WindowEvent::RotationGesture { device_id, delta, phase } => {
-        let gesture = Gesture::Rotation { device_id, delta, phase };
-        self.input_state.handle_gesture(gesture);
-        handler.on_gesture(self, gesture);
+      InputEvent::Touch { device_id, phase, location, force, id } => {
+        let touch = Touch { device_id, phase, location, force, id };
+        self.input_state.handle_touch(touch);
+        handler.on_touch(self, touch);
       },
-      WindowEvent::TouchpadPressure { device_id, pressure, stage } => {
-        let gesture = Gesture::TouchpadPressure { device_id, pressure, stage };
+      InputEvent::Gesture(gesture) => {
         self.input_state.handle_gesture(gesture);
         handler.on_gesture(self, gesture);
       },
-      WindowEvent::Touch(touch) => {
-        self.input_state.handle_touch(touch);
-        handler.on_touch(self, touch);
-      },
-      WindowEvent::AxisMotion { device_id, axis, value } => {
-        let axis_motion = AxisMotion { device_id, axis, value };
+      InputEvent::AxisMotion(axis_motion) => {
         self.input_state.handle_axis_motion(axis_motion);
         handler.on_axis_motion(self, axis_motion);
       },
-      WindowEvent::ThemeChanged(..) => ()
-    }
+      InputEvent::Ime(event) => {
+        self.input_state.handle_ime(&event);
+        handler.on_text_input(self, event);
+      },
+      InputEvent::Gamepad(gamepad_event) => {
+        self.input_state.handle_gamepad_event(gamepad_event.clone(), &self.gamepad_config.get());
+        match gamepad_event {
+          GamepadEvent::Connected { id, .. } => handler.on_gamepad_connected(self, id),
+          GamepadEvent::Disconnected { id } => handler.on_gamepad_disconnected(self, id),
+          GamepadEvent::Button { id, button, state } => handler.on_gamepad_button(self, id, button, state),
+          GamepadEvent::Axis { id, axis, value } => handler.on_gamepad_axis(self, id, axis, value)
+        };
+      },
+      InputEvent::Focused(focused_state) => {
+        if !focused_state {
+          // the cursor has not necessarily moved, so its position is kept, gamepads remain connected,
+          // and the IME stays enabled until it is explicitly disabled
+          let gamepads = self.input_state.gamepads.iter()
+            .map(|(&id, gamepad_state)| (id, GamepadState::new(gamepad_state.name().to_owned())))
+            .collect();
+          self.input_state = InputState {
+            cursor_pos: self.input_state.cursor_pos,
+            last_cursor_pos: self.input_state.last_cursor_pos,
+            cursor_inside: self.input_state.cursor_inside,
+            gamepads,
+            ime_enabled: self.input_state.ime_enabled,
+            ..InputState::default()
+          };
+          if let Some(gesture_recognizer) = &mut self.gesture_recognizer {
+            gesture_recognizer.reset();
+          };
+        };
+
+        // relative mouse mode is released while unfocused, so that the cursor can be used elsewhere
+        if self.relative_mouse_mode.get() {
+          self.apply_relative_mouse_mode(focused_state);
+        };
+        handler.on_focus_changed(self, focused_state);
+      }
+    };
   }
 }
 
-#[derive(Debug)]
-pub struct Application<W: HasWindow, H: EventHandler<W, T>, T: 'static = ()> {
-  handler: Option<H>,
-  window_state: WindowState<W>,
-  phantom_data: PhantomData<T>
+/// Determines how often an [`Application`] runs frames and redraws its windows.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RedrawMode {
+  /// Redraws every window as often as possible, usually limited by vsync.
+  #[default]
+  Continuous,
+  /// Waits for events, only redrawing windows for which [`WindowState::request_redraw`] was called,
+  /// or which the platform asks to be redrawn.
+  OnDemand,
+  /// Redraws every window at the given number of frames per second, waiting in between frames.
+  /// The rate should be positive and finite. Any other rate, or one whose frame interval does not fit in a [`Duration`]
+  /// or is shorter than a nanosecond, redraws as often as possible like [`RedrawMode::Continuous`].
+  TargetFps(f64)
 }
 
-impl<W, H, T: 'static> Application<W, H, T>
-where W: HasWindow, H: EventHandler<W, T> {
-  pub fn new(window: W, handler: H) -> Self {
-    Application {
-      handler: Some(handler),
-      window_state: WindowState::new(window.into()),
-      phantom_data: PhantomData
+impl RedrawMode {
+  /// The frame interval used while every window is occluded or minimized, unless the mode is [`RedrawMode::OnDemand`].
+  pub const THROTTLED_FRAME_INTERVAL: Duration = Duration::from_millis(100);
+  /// The interval at which gamepads are polled while waiting for events, if a [`GamepadSource`] is set.
+  pub const GAMEPAD_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+  fn frame_interval(self, throttled: bool) -> Option<Duration> {
+    let frame_interval = match self {
+      RedrawMode::Continuous => None,
+      RedrawMode::OnDemand => return None,
+      RedrawMode::TargetFps(fps) => Duration::try_from_secs_f64(fps.recip()).ok()
+        .filter(|frame_interval| !frame_interval.is_zero())
+    };
+
+    if throttled {
+      Some(frame_interval.map_or(Self::THROTTLED_FRAME_INTERVAL, |frame_interval| {
+        frame_interval.max(Self::THROTTLED_FRAME_INTERVAL)
+      }))
+    } else {
+      frame_interval
     }
   }
+}
 
-  pub fn run(&mut self, event_loop: EventLoop<T>) -> Result<(), EventLoopError> {
-    event_loop.run_app(self)
+/// Tracks the timing of rendered frames, where each frame begins just before [`EventHandler::update`] is called.
+/// Frames which do not render, such as those run for input while in [`RedrawMode::OnDemand`], are not counted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameTiming {
+  first_frame_instant: Option<Instant>,
+  frame_instant: Option<Instant>,
+  delta_time: Duration,
+  elapsed: Duration,
+  frame_count: u64,
+  history: VecDeque<Duration>,
+  history_len: usize
+}
+
+impl FrameTiming {
+  /// The number of frame times kept for computing statistics by default.
+  pub const DEFAULT_HISTORY_LEN: usize = 120;
+
+  pub fn new(history_len: usize) -> Self {
+    FrameTiming {
+      first_frame_instant: None,
+      frame_instant: None,
+      delta_time: Duration::ZERO,
+      elapsed: Duration::ZERO,
+      frame_count: 0,
+      history: VecDeque::with_capacity(history_len),
+      history_len
+    }
   }
 
+  /// The time between the start of the previous frame and the start of the current frame.
+  /// This is zero during the first frame.
   #[inline]
-  fn decompose_mut(&mut self) -> (&mut H, &mut WindowState<W>) {
-    (self.handler.as_mut().unwrap_unreachable(), &mut self.window_state)
+  pub fn delta_time(&self) -> Duration {
+    self.delta_time
   }
+
+  /// The time between the start of the first frame and the start of the current frame.
+  #[inline]
+  pub fn elapsed(&self) -> Duration {
+    self.elapsed
+  }
+
+  /// The number of frames that have been started, including the current frame.
+  #[inline]
+  pub fn frame_count(&self) -> u64 {
+    self.frame_count
+  }
+
+  /// The instant that the current frame started at.
+  #[inline]
+  pub fn frame_instant(&self) -> Option<Instant> {
+    self.frame_instant
+  }
+
+  /// Returns the most recent frame times, oldest first.
+  pub fn frame_times(&self) -> impl Iterator<Item = Duration> + '_ {
+    self.history.iter().copied()
+  }
+
+  /// The average frame time over the most recent frames.
+  pub fn average_frame_time(&self) -> Duration {
+    if self.history.is_empty() {
+      Duration::ZERO
+    } else {
+      self.history.iter().sum::<Duration>() / self.history.len() as u32
+    }
+  }
+
+  /// The number of frames per second, averaged over the most recent frames.
+  pub fn fps(&self) -> f64 {
+    let average_frame_time = self.average_frame_time();
+    if average_frame_time.is_zero() { 0.0 } else { average_frame_time.as_secs_f64().recip() }
+  }
+
+  /// The shortest frame time among the most recent frames.
+  pub fn min_frame_time(&self) -> Duration {
+    self.history.iter().copied().min().unwrap_or(Duration::ZERO)
+  }
+
+  /// The longest frame time among the most recent frames.
+  pub fn max_frame_time(&self) -> Duration {
+    self.history.iter().copied().max().unwrap_or(Duration::ZERO)
+  }
+
+  /// The frame time at the given percentile (from `0.0` to `100.0`) among the most recent frames,
+  /// using the nearest-rank method. For example, the 99th percentile is the frame time that
+  /// only 1% of recent frames took longer than.
+  pub fn frame_time_percentile(&self, percentile: f64) -> Duration {
+    if self.history.is_empty() { return Duration::ZERO };
+
+    let mut frame_times = self.history.iter().copied().collect::<Vec<Duration>>();
+    frame_times.sort_unstable();
+
+    let rank = (percentile.clamp(0.0, 100.0) / 100.0 * frame_times.len() as f64).ceil() as usize;
+    frame_times[rank.saturating_sub(1)]
+  }
+
+  pub(crate) fn begin_frame(&mut self, now: Instant) {
+    let first_frame_instant = *self.first_frame_instant.get_or_insert(now);
+    if let Some(frame_instant) = self.frame_instant.replace(now) {
+      self.delta_time = now.duration_since(frame_instant);
+      if self.history_len > 0 {
+        if self.history.len() >= self.history_len {
+          self.history.pop_front();
+        };
+
+        self.history.push_back(self.delta_time);
+      };
+    };
+
+    self.elapsed = now.duration_since(first_frame_instant);
+    self.frame_count += 1;
+  }
+}
+
+impl Default for FrameTiming {
+  #[inline]
+  fn default() -> Self {
+    FrameTiming::new(Self::DEFAULT_HISTORY_LEN)
+  }
+}
+
+/// Configures an [`Application`] to call [`EventHandler::update`] at a constant rate,
+/// independently of how often frames are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FixedTimestep {
+  /// The constant amount of time simulated by each tick.
+  pub tick_duration: Duration,
+  /// The maximum number of ticks that may be run in a single frame when catching up.
+  /// Any further elapsed ticks are dropped and reported through [`EventHandler::on_ticks_dropped`].
+  pub max_ticks_per_frame: u32
+}
+
+impl FixedTimestep {
+  pub const DEFAULT_MAX_TICKS_PER_FRAME: u32 = 8;
+
+  pub const fn new(tick_duration: Duration) -> Self {
+    FixedTimestep { tick_duration, max_ticks_per_frame: Self::DEFAULT_MAX_TICKS_PER_FRAME }
+  }
+
+  /// Creates a fixed timestep which runs the given number of ticks per second.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the tick rate is not positive and finite, or if its tick duration would not fit in a [`Duration`]
+  /// or would be shorter than a nanosecond.
+  pub fn from_tick_rate(ticks_per_second: f64) -> Self {
+    assert!(ticks_per_second > 0.0 && ticks_per_second.is_finite(), "tick rate must be positive and finite, got {ticks_per_second}");
+    let tick_duration = Duration::try_from_secs_f64(ticks_per_second.recip()).ok()
+      .filter(|tick_duration| !tick_duration.is_zero())
+      .unwrap_or_else(|| panic!("tick rate {ticks_per_second} has no representable tick duration"));
+    Self::new(tick_duration)
+  }
+
+  pub const fn with_max_ticks_per_frame(mut self, max_ticks_per_frame: u32) -> Self {
+    self.max_ticks_per_frame = max_ticks_per_frame;
+    self
+  }
+}
+
+/// The state of a [`FixedTimestep`] as of the current tick or frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimestepState {
+  fixed_timestep: FixedTimestep,
+  accumulator: Duration,
+  alpha: f64,
+  ticks: u64,
+  ticks_this_frame: u32,
+  ticks_dropped: u32,
+  ticks_dropped_total: u64
+}
+
+impl TimestepState {
+  pub(crate) fn new(fixed_timestep: FixedTimestep) -> Self {
+    TimestepState {
+      fixed_timestep,
+      accumulator: Duration::ZERO,
+      alpha: 0.0,
+      ticks: 0,
+      ticks_this_frame: 0,
+      ticks_dropped: 0,
+      ticks_dropped_total: 0
+    }
+  }
+
+  #[inline]
+  pub fn fixed_timestep(&self) -> FixedTimestep {
+    self.fixed_timestep
+  }
+
+  /// The constant delta time of each tick.
+  #[inline]
+  pub fn dt(&self) -> Duration {
+    self.fixed_timestep.tick_duration
+  }
+
+  /// How far between the last tick and the next tick the current frame is, from `0.0` to `1.0`.
+  /// This should be used when rendering to interpolate between the previous and current simulation states.
+  #[inline]
+  pub fn alpha(&self) -> f64 {
+    self.alpha
+  }
+
+  /// The total number of ticks that have been run.
+  #[inline]
+  pub fn ticks(&self) -> u64 {
+    self.ticks
+  }
+
+  /// The number of ticks that have been run during the current frame.
+  #[inline]
+  pub fn ticks_this_frame(&self) -> u32 {
+    self.ticks_this_frame
+  }
+
+  /// The number of ticks that were dropped during the current frame.
+  #[inline]
+  pub fn ticks_dropped(&self) -> u32 {
+    self.ticks_dropped
+  }
+
+  /// The total number of ticks that have been dropped.
+  #[inline]
+  pub fn ticks_dropped_total(&self) -> u64 {
+    self.ticks_dropped_total
+  }
+
+  /// Starts a new frame, adding the time elapsed since the last frame to the accumulator.
+  pub(crate) fn begin_frame(&mut self, elapsed: Duration) {
+    self.accumulator += elapsed;
+    self.ticks_this_frame = 0;
+    self.ticks_dropped = 0;
+  }
+
+  /// Consumes one tick from the accumulator, returning whether a tick should be run.
+  /// Once the per-frame tick limit has been reached, all remaining whole ticks are dropped instead.
+  pub(crate) fn try_tick(&mut self) -> bool {
+    let tick_duration = self.fixed_timestep.tick_duration;
+    if tick_duration.is_zero() || self.accumulator < tick_duration {
+      return false;
+    };
+
+    if self.ticks_this_frame >= self.fixed_timestep.max_ticks_per_frame {
+      let ticks_dropped = u32::try_from(self.accumulator.as_nanos() / tick_duration.as_nanos()).unwrap_or(u32::MAX);
+      self.accumulator -= tick_duration * ticks_dropped;
+      self.ticks_dropped = self.ticks_dropped.saturating_add(ticks_dropped);
+      self.ticks_dropped_total += ticks_dropped as u64;
+      return false;
+    };
+
+    self.accumulator -= tick_duration;
+    self.ticks += 1;
+    self.ticks_this_frame += 1;
+    true
+  }
+
+  /// Finishes running ticks for the current frame, computing the interpolation alpha.
+  pub(crate) fn end_frame(&mut self) {
+    let tick_duration = self.fixed_timestep.tick_duration;
+    self.alpha = if tick_duration.is_zero() {
+      1.0
+    } else {
+      self.accumulator.as_secs_f64() / tick_duration.as_secs_f64()
+    };
+  }
+}
+
+#[derive(Debug)]
+pub struct Application<W: HasWindow, H: EventHandler<W, T>, T: 'static = ()> {
+  handler: Option<H>,
+  initialized: bool,
+  window_states: Vec<WindowState<W>>,
+  window_requests: WindowRequests,
+  timestep: Option<TimestepState>,
+  gesture_config: Option<GestureConfig>,
+  gamepad_source: Option<Box<dyn GamepadSource>>,
+  redraw_mode: RedrawMode,
+  frame_history_len: usize,
+  last_frame_instant: Option<Instant>,
+  next_frame_instant: Option<Instant>,
+  #[cfg(feature = "serde")]
+  input_recorder: Option<InputRecorder>,
+  #[cfg(feature = "serde")]
+  input_replay: Option<InputReplay>,
+  phantom_data: PhantomData<T>
+}
+
+impl<W, H, T: 'static> Application<W, H, T>
+where W: HasWindow, H: EventHandler<W, T> {
+  pub fn new(window: W, handler: H) -> Self {
+    let window_requests = WindowRequests::default();
+    Application {
+      handler: Some(handler),
+      initialized: false,
+      window_states: vec![WindowState::new(window.into(), window_requests.clone(), None, FrameTiming::DEFAULT_HISTORY_LEN)],
+      window_requests,
+      timestep: None,
+      gesture_config: None,
+      gamepad_source: None,
+      redraw_mode: RedrawMode::default(),
+      frame_history_len: FrameTiming::DEFAULT_HISTORY_LEN,
+      last_frame_instant: None,
+      next_frame_instant: None,
+      #[cfg(feature = "serde")]
+      input_recorder: None,
+      #[cfg(feature = "serde")]
+      input_replay: None,
+      phantom_data: PhantomData
+    }
+  }
+
+  /// Configures the application to call [`EventHandler::update`] at a fixed rate.
+  /// Passing `None` restores the default behavior of calling it once per frame.
+  pub fn with_fixed_timestep(mut self, fixed_timestep: Option<FixedTimestep>) -> Self {
+    self.timestep = fixed_timestep.map(TimestepState::new);
+    for window_state in self.window_states.iter_mut() {
+      window_state.timestep = self.timestep;
+    };
+
+    self
+  }
+
+  /// Configures the application to recognize gestures from touch and mouse input in every window,
+  /// delivering them through [`EventHandler::on_gesture`]. See [`GestureRecognizer`].
+  pub fn with_gesture_recognition(mut self, gesture_config: Option<GestureConfig>) -> Self {
+    self.gesture_config = gesture_config;
+    for window_state in self.window_states.iter_mut() {
+      window_state.set_gesture_config(gesture_config);
+    };
+
+    self
+  }
+
+  /// Configures the application to poll the given source for gamepad input once per frame,
+  /// delivering it to the primary window. Gamepads cannot wake the event loop, so while waiting for events,
+  /// frames are run at least every [`RedrawMode::GAMEPAD_POLL_INTERVAL`] to poll them. See the [`gamepad`][crate::gamepad] module.
+  pub fn with_gamepad_source(mut self, gamepad_source: Option<Box<dyn GamepadSource>>) -> Self {
+    self.gamepad_source = gamepad_source;
+    self
+  }
+
+  /// Configures how often the application runs frames and redraws its windows.
+  /// While every window is occluded or minimized, frames are additionally throttled.
+  pub fn with_redraw_mode(mut self, redraw_mode: RedrawMode) -> Self {
+    self.redraw_mode = redraw_mode;
+    self
+  }
+
+  /// Configures the number of frame times each window's [`FrameTiming`] keeps for computing statistics.
+  pub fn with_frame_history_len(mut self, frame_history_len: usize) -> Self {
+    self.frame_history_len = frame_history_len;
+    for window_state in self.window_states.iter_mut() {
+      window_state.frame_timing = FrameTiming::new(frame_history_len);
+    };
+
+    self
+  }
+
+  /// Configures the application to record the input of the primary window. See [`InputRecorder`].
+  #[cfg(feature = "serde")]
+  pub fn with_input_recorder(mut self, input_recorder: Option<InputRecorder>) -> Self {
+    self.input_recorder = input_recorder;
+    self
+  }
+
+  /// Configures the application to replay recorded input into the primary window. See [`InputReplay`].
+  #[cfg(feature = "serde")]
+  pub fn with_input_replay(mut self, input_replay: Option<InputReplay>) -> Self {
+    self.input_replay = input_replay;
+    self
+  }
+
+  pub fn run(&mut self, event_loop: EventLoop<T>) -> Result<(), EventLoopError> {
+    event_loop.run_app(self)
+  }
+
+  /// Returns the event handler, or `None` if the application has exited.
+  #[inline]
+  pub fn handler(&self) -> Option<&H> {
+    self.handler.as_ref()
+  }
+
+  /// Returns the event handler mutably, or `None` if the application has exited.
+  #[inline]
+  pub fn handler_mut(&mut self) -> Option<&mut H> {
+    self.handler.as_mut()
+  }
+
+  /// Returns the state of the primary window, which is the oldest window that is still open.
+  #[inline]
+  pub fn primary_window_state(&self) -> Option<&WindowState<W>> {
+    self.window_states.first()
+  }
+
+  /// Returns the state of the window with the given ID, if it is open.
+  pub fn window_state(&self, window_id: WindowId) -> Option<&WindowState<W>> {
+    self.window_states.iter().find(|window_state| window_state.id() == window_id)
+  }
+
+  /// Returns the states of all open windows, in the order they were opened.
+  #[inline]
+  pub fn window_states(&self) -> &[WindowState<W>] {
+    &self.window_states
+  }
+
+  #[inline]
+  fn decompose_mut(&mut self) -> Option<(&mut H, &mut WindowState<W>)> {
+    Some((self.handler.as_mut().unwrap_unreachable(), self.window_states.first_mut()?))
+  }
+
+  #[inline]
+  fn decompose_mut_for(&mut self, window_id: WindowId) -> Option<(&mut H, &mut WindowState<W>)> {
+    let window_state = self.window_states.iter_mut().find(|window_state| window_state.id() == window_id)?;
+    Some((self.handler.as_mut().unwrap_unreachable(), window_state))
+  }
+
+  /// Records input received by the primary window, returning whether it should be ignored because input is being replayed into it.
+  #[allow(unused)]
+  fn intercept_input_event(&mut self, window_id: WindowId, input_event: &InputEvent) -> bool {
+    #[cfg(feature = "serde")]
+    if self.window_states.first().is_some_and(|window_state| window_state.id() == window_id) {
+      if self.input_replay.as_ref().is_some_and(|input_replay| !input_replay.is_finished()) {
+        return true;
+      };
+
+      if let Some(input_recorder) = &mut self.input_recorder {
+        input_recorder.record_event(input_event.clone());
+      };
+    };
+
+    false
+  }
+
+  /// Determines whether a frame is due at the given instant, along with the control flow to wait with afterwards.
+  fn pace_frame(&mut self, now: Instant) -> (bool, ControlFlow) {
+    let throttled = !self.window_states.is_empty()
+      && self.window_states.iter().all(WindowState::is_hidden);
+    let Some(frame_interval) = self.redraw_mode.frame_interval(throttled) else {
+      self.next_frame_instant = None;
+      let control_flow = match self.gamepad_source {
+        Some(..) => ControlFlow::WaitUntil(now + RedrawMode::GAMEPAD_POLL_INTERVAL),
+        None => ControlFlow::Wait
+      };
+
+      return (true, control_flow);
+    };
+
+    let frame_instant = *self.next_frame_instant.get_or_insert(now);
+    if now < frame_instant {
+      return (false, ControlFlow::WaitUntil(frame_instant));
+    };
+
+    // if frames have fallen behind, schedule the next frame relative to now instead of trying to catch up
+    let next_frame_instant = Some(frame_instant + frame_interval)
+      .filter(|&next_frame_instant| next_frame_instant > now)
+      .unwrap_or(now + frame_interval);
+    self.next_frame_instant = Some(next_frame_instant);
+    (true, ControlFlow::WaitUntil(next_frame_instant))
+  }
+
+  /// Delivers polled and replayed input, begins a frame in every window and updates the primary window.
+  /// Returns `false` if the handler wants the application to exit.
+  fn run_frame(&mut self, now: Instant) -> bool {
+    let simulated_frame_duration = self.handler.as_ref().unwrap_unreachable().simulated_frame_duration();
+    let frame_instant = match (simulated_frame_duration, self.last_frame_instant) {
+      (Some(frame_duration), Some(last_frame_instant)) => last_frame_instant + frame_duration,
+      _ => now
+    };
+
+    // replayed frames start at the same time relative to each other as when they were recorded
+    #[cfg(feature = "serde")]
+    let replayed_frame = self.input_replay.as_mut().and_then(|input_replay| input_replay.next_frame(now));
+    #[cfg(feature = "serde")]
+    let frame_instant = replayed_frame.as_ref().map_or(frame_instant, |&(frame_instant, _)| frame_instant);
+
+    let elapsed = self.last_frame_instant.replace(frame_instant)
+      .map_or(Duration::ZERO, |last_frame_instant| frame_instant.duration_since(last_frame_instant));
+
+    if let Some(gamepad_source) = &mut self.gamepad_source && let Some(window_id) = self.window_states.first().map(WindowState::id) {
+      let mut gamepad_events = Vec::new();
+      gamepad_source.poll(&mut gamepad_events);
+      for gamepad_event in gamepad_events {
+        let input_event = InputEvent::Gamepad(gamepad_event);
+        if !self.intercept_input_event(window_id, &input_event) && let Some((handler, window_state)) = self.decompose_mut() {
+          window_state.handle_input_event(handler, input_event);
+        };
+      };
+    };
+
+    let handler = self.handler.as_mut().unwrap_unreachable();
+    // replayed input is delivered before the frame begins, just like real input
+    #[cfg(feature = "serde")]
+    if let Some((_, events)) = replayed_frame && let Some(window_state) = self.window_states.first_mut() {
+      for event in events {
+        window_state.handle_input_event(handler, event);
+      };
+    };
+
+    for window_state in self.window_states.iter_mut() {
+      // frame timing only counts rendered frames, so that frames run just for input in on-demand mode don't skew it
+      let rendered = !window_state.is_hidden()
+        && (self.redraw_mode != RedrawMode::OnDemand || window_state.redraw_requested.get());
+      window_state.begin_frame(frame_instant, rendered);
+      window_state.recognize_gestures(handler);
+    };
+
+    #[cfg(feature = "serde")]
+    if let Some(input_recorder) = &mut self.input_recorder {
+      input_recorder.record_frame(frame_instant);
+    };
+
+    if let Some(window_state) = self.window_states.first_mut() {
+      run_update(handler, window_state, &mut self.timestep, elapsed);
+      if handler.should_exit(window_state) {
+        return false;
+      };
+    };
+
+    for window_state in self.window_states.iter_mut() {
+      window_state.timestep = self.timestep;
+    };
+
+    true
+  }
+
+  /// Requests a redraw of every window that should be rendered, then clears the input received during the frame.
+  fn end_frame(&mut self) {
+    let frame_instant = self.last_frame_instant;
+    for window_state in self.window_states.iter_mut() {
+      if !window_state.is_hidden() {
+        let redraw = window_state.redraw_requested.take() || self.redraw_mode != RedrawMode::OnDemand;
+        // redraws requested during the frame, or by newly opened windows, only begin their frame timing now
+        if redraw && let Some(frame_instant) = frame_instant && window_state.frame_timing.frame_instant() != Some(frame_instant) {
+          window_state.frame_timing.begin_frame(frame_instant);
+        };
+
+        if redraw && let Some(window) = window_state.try_window() {
+          window.request_redraw();
+        };
+      };
+
+      window_state.reset();
+    };
+  }
+
+  fn process_window_requests(&mut self, event_loop: &ActiveEventLoop) {
+    let handler = self.handler.as_mut().unwrap_unreachable();
+    loop {
+      let window_requests = self.window_requests.take();
+      if window_requests.is_empty() { break };
+
+      for window_request in window_requests {
+        match window_request {
+          WindowRequest::Open(window_attributes) => match handler.create_window(event_loop, *window_attributes) {
+            Ok(window_holder) => {
+              let mut window_state = WindowState::new(window_holder, self.window_requests.clone(), self.timestep, self.frame_history_len);
+              window_state.set_gesture_config(self.gesture_config);
+              self.window_states.push(window_state);
+              handler.on_window_opened(self.window_states.last().unwrap_unreachable());
+            },
+            Err(error) => handler.on_window_open_failed(error)
+          },
+          WindowRequest::Close(window_id) => {
+            if let Some(index) = self.window_states.iter().position(|window_state| window_state.id() == window_id) {
+              let window_state = self.window_states.remove(index);
+              handler.on_window_closed(&window_state);
+            };
+          }
+        };
+      };
+    };
+
+    if self.window_states.is_empty() {
+      event_loop.exit();
+    };
+  }
+}
+
+/// Runs [`EventHandler::update`] for a single frame, once per elapsed tick if a fixed timestep is in use.
+pub(crate) fn run_update<W, H, T>(handler: &mut H, window_state: &mut WindowState<W>, timestep: &mut Option<TimestepState>, elapsed: Duration)
+where W: HasWindow, H: EventHandler<W, T> {
+  if let Some(timestep) = timestep {
+    timestep.begin_frame(elapsed);
+    while timestep.try_tick() {
+      window_state.timestep = Some(*timestep);
+      handler.update(window_state);
+    };
+
+    timestep.end_frame();
+    window_state.timestep = Some(*timestep);
+    if timestep.ticks_dropped > 0 {
+      handler.on_ticks_dropped(window_state, timestep.ticks_dropped);
+    };
+  } else {
+    handler.update(window_state);
+  };
 }
 
 macro_rules! application_handler_functions {
   (let $decomposed:pat, $event_loop:ident; $(fn $name:ident($($arg:ident: $Arg:ty),* $(,)?) $block:block)*) => ($(
     fn $name(&mut self, #[allow(unused)] $event_loop: &ActiveEventLoop, $(#[allow(unused)] $arg: $Arg),*) {
       #[allow(unused)]
-      let $decomposed = self.decompose_mut();
-      $block
+      if let Some($decomposed) = self.decompose_mut() $block
+      self.process_window_requests($event_loop);
     }
   )*);
 }
@@ -781,47 +2665,377 @@ where W: HasWindow, H: EventHandler<W, T> {
   application_handler_functions!{
     let (handler, window_state), event_loop;
 
-    fn new_events(start_cause: StartCause) {
-      window_state.reset();
+    fn suspended() {
+      handler.on_suspended(window_state);
     }
 
-    fn resumed() {
-      if !replace(&mut window_state.initialized, true) {
+    fn user_event(event: T) {
+      handler.on_user_event(window_state, event);
+    }
+  }
+
+  fn device_event(&mut self, event_loop: &ActiveEventLoop, device_id: DeviceId, event: DeviceEvent) {
+    // device input is attributed to whichever window is focused
+    if let Some(input_event) = InputEvent::from_device_event(device_id, &event)
+      && let Some(window_id) = self.window_states.iter().find(|window_state| window_state.is_focused()).map(WindowState::id)
+      && !self.intercept_input_event(window_id, &input_event)
+      && let Some((handler, window_state)) = self.decompose_mut_for(window_id) {
+      window_state.handle_input_event(handler, input_event);
+    };
+
+    if let Some((handler, window_state)) = self.decompose_mut() {
+      handler.on_device_event(window_state, device_id, event);
+    };
+    self.process_window_requests(event_loop);
+  }
+
+  fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+    let initialized = replace(&mut self.initialized, true);
+    if let Some((handler, window_state)) = self.decompose_mut() {
+      if !initialized {
         handler.init(window_state);
       };
       handler.on_resumed(window_state);
-    }
+    };
+    self.process_window_requests(event_loop);
+  }
 
-    fn suspended() {
-      handler.on_suspended(window_state);
+  fn window_event(&mut self, event_loop: &ActiveEventLoop, window_id: WindowId, event: WindowEvent) {
+    if let Some(input_event) = InputEvent::from_window_event(&event) && self.intercept_input_event(window_id, &input_event) {
+      return;
+    };
+
+    if let Some((handler, window_state)) = self.decompose_mut_for(window_id) {
+      handler.on_window_event(window_state, event, event_loop);
+    };
+    self.process_window_requests(event_loop);
+  }
+
+  fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+    let now = Instant::now();
+    // input received while a frame is not due is kept until the next frame that runs
+    let (frame_due, control_flow) = self.pace_frame(now);
+    event_loop.set_control_flow(control_flow);
+    if !frame_due { return };
+
+    #[cfg(feature = "serde")]
+    if let Some(input_replay) = &self.input_replay && input_replay.exit_when_finished() && input_replay.is_finished() {
+      event_loop.exit();
+      return;
+    };
+
+    if !self.run_frame(now) {
+      event_loop.exit();
+      return;
+    };
+
+    self.process_window_requests(event_loop);
+    self.end_frame();
+  }
+
+  #[allow(unused)]
+  fn exiting(&mut self, event_loop: &ActiveEventLoop) {
+    #[cfg(feature = "serde")]
+    if let Some(input_recorder) = &mut self.input_recorder {
+      input_recorder.finish();
+    };
+
+    self.handler.take().unwrap_unreachable().on_exited();
+  }
+}
+
+
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use crate::gamepad::FakeGamepadSource;
+
+  use super::*;
+
+  /// A handler which ignores every callback, for tests which only inspect a window's [`InputState`].
+  #[derive(Debug, Default)]
+  pub(crate) struct NullHandler;
+
+  impl EventHandler<Window> for NullHandler {
+    fn update(&mut self, _: &WindowState<Window>) {}
+    fn render(&mut self, _: &WindowState<Window>) {}
+  }
+
+  pub(crate) fn headless_window_state() -> WindowState<Window> {
+    WindowState::new_headless(PhysicalSize::new(640, 480), 1.0, None, FrameTiming::DEFAULT_HISTORY_LEN)
+  }
+
+  pub(crate) fn key_event(key_code: KeyCode, state: ElementState) -> InputEvent {
+    InputEvent::Keyboard {
+      device_id: DeviceId::dummy(),
+      physical_key: PhysicalKey::Code(key_code),
+      logical_key: LogicalKey::Unidentified(winit::keyboard::NativeKey::Unidentified),
+      text: None,
+      location: KeyLocation::Standard,
+      state,
+      repeat: false
     }
+  }
 
-    fn window_event(window_id: WindowId, event: WindowEvent) {
-      if window_state.window_holder.get_window().id() == window_id {
-        handler.on_window_event(window_state, event, event_loop);
-      };
+  #[derive(Default)]
+  struct TestHandler {
+    updates: Vec<Vec<KeyCode>>
+  }
+
+  impl EventHandler<Rc<Window>> for TestHandler {
+    fn update(&mut self, window_state: &WindowState<Rc<Window>>) {
+      let pressed = window_state.input().key_actions().iter()
+        .filter(|key_action| key_action.state == KeyActionState::Pressed)
+        .filter_map(|key_action| match key_action.physical_key {
+          PhysicalKey::Code(key_code) => Some(key_code),
+          PhysicalKey::Unidentified(..) => None
+        })
+        .collect();
+      self.updates.push(pressed);
     }
 
-    fn user_event(event: T) {
-      handler.on_user_event(window_state, event);
+    fn render(&mut self, _: &WindowState<Rc<Window>>) {}
+  }
+
+  fn headless_application(redraw_mode: RedrawMode) -> Application<Rc<Window>, TestHandler> {
+    let window_requests = WindowRequests::default();
+    let window_state = WindowState::new_headless(PhysicalSize::new(640, 480), 1.0, None, FrameTiming::DEFAULT_HISTORY_LEN);
+    Application {
+      handler: Some(TestHandler::default()),
+      initialized: true,
+      window_states: vec![window_state],
+      window_requests,
+      timestep: None,
+      gesture_config: None,
+      gamepad_source: None,
+      redraw_mode,
+      frame_history_len: FrameTiming::DEFAULT_HISTORY_LEN,
+      last_frame_instant: None,
+      next_frame_instant: None,
+      #[cfg(feature = "serde")]
+      input_recorder: None,
+      #[cfg(feature = "serde")]
+      input_replay: None,
+      phantom_data: PhantomData
     }
+  }
 
-    fn device_event(device_id: DeviceId, event: DeviceEvent) {
-      handler.on_device_event(window_state, device_id, event);
+  fn press(application: &mut Application<Rc<Window>, TestHandler>, key_code: KeyCode) {
+    let (handler, window_state) = application.decompose_mut().unwrap();
+    window_state.handle_input_event(handler, key_event(key_code, ElementState::Pressed));
+  }
+
+  /// Runs a loop iteration the way [`Application::about_to_wait`] does, returning whether a frame ran.
+  fn iterate(application: &mut Application<Rc<Window>, TestHandler>, now: Instant) -> bool {
+    let (frame_due, _) = application.pace_frame(now);
+    if frame_due {
+      assert!(application.run_frame(now));
+      application.end_frame();
+    };
+
+    frame_due
+  }
+
+  #[test]
+  fn input_is_kept_across_skipped_iterations() {
+    let mut application = headless_application(RedrawMode::TargetFps(10.0));
+    let start = Instant::now();
+    assert!(iterate(&mut application, start));
+
+    press(&mut application, KeyCode::KeyA);
+    assert!(!iterate(&mut application, start + Duration::from_millis(20)));
+    press(&mut application, KeyCode::KeyB);
+    assert!(iterate(&mut application, start + Duration::from_millis(100)));
+    assert!(iterate(&mut application, start + Duration::from_millis(200)));
+
+    let updates = &application.handler().unwrap().updates;
+    assert_eq!(updates, &[vec![], vec![KeyCode::KeyA, KeyCode::KeyB], vec![]]);
+  }
+
+  #[test]
+  fn target_fps_outside_the_valid_range_redraws_continuously() {
+    assert_eq!(RedrawMode::TargetFps(50.0).frame_interval(false), Some(Duration::from_millis(20)));
+    for fps in [0.0, -1.0, f64::NAN, f64::INFINITY, 1e-300, 1e300] {
+      assert_eq!(RedrawMode::TargetFps(fps).frame_interval(false), None, "{fps}");
+      assert_eq!(RedrawMode::TargetFps(fps).frame_interval(true), Some(RedrawMode::THROTTLED_FRAME_INTERVAL), "{fps}");
+    };
+  }
+
+  #[test]
+  fn input_is_reset_after_each_frame() {
+    let mut application = headless_application(RedrawMode::Continuous);
+    let start = Instant::now();
+    press(&mut application, KeyCode::KeyA);
+    assert!(iterate(&mut application, start));
+    assert!(application.primary_window_state().unwrap().input().key_actions().is_empty());
+    assert!(iterate(&mut application, start + Duration::from_millis(16)));
+
+    let updates = &application.handler().unwrap().updates;
+    assert_eq!(updates, &[vec![KeyCode::KeyA], vec![]]);
+  }
+
+  #[derive(Default)]
+  struct TickHandler {
+    updates: u32,
+    ticks_dropped: Vec<u32>
+  }
+
+  impl EventHandler<Window> for TickHandler {
+    fn update(&mut self, _: &WindowState<Window>) {
+      self.updates += 1;
     }
 
-    fn about_to_wait() {
-      handler.update(window_state);
-      if handler.should_exit(window_state) {
-        event_loop.exit();
-      } else {
-        window_state.window().request_redraw();
-      };
+    fn render(&mut self, _: &WindowState<Window>) {}
+
+    fn on_ticks_dropped(&mut self, _: &WindowState<Window>, count: u32) {
+      self.ticks_dropped.push(count);
     }
   }
 
-  #[allow(unused)]
-  fn exiting(&mut self, event_loop: &ActiveEventLoop) {
-    self.handler.take().unwrap_unreachable().on_exited();
+  /// Runs the updates of a single frame, returning the number of updates that ran.
+  fn tick_frame(handler: &mut TickHandler, timestep: &mut Option<TimestepState>, elapsed: Duration) -> u32 {
+    let mut window_state = headless_window_state();
+    let updates = handler.updates;
+    run_update(handler, &mut window_state, timestep, elapsed);
+    assert_eq!(window_state.timestep, *timestep);
+    handler.updates - updates
+  }
+
+  #[test]
+  fn fixed_timestep_catches_up_and_interpolates() {
+    let mut handler = TickHandler::default();
+    let mut timestep = Some(TimestepState::new(FixedTimestep::new(Duration::from_millis(10))));
+    assert_eq!(tick_frame(&mut handler, &mut timestep, Duration::ZERO), 0);
+    assert_eq!(timestep.unwrap().alpha(), 0.0);
+
+    assert_eq!(tick_frame(&mut handler, &mut timestep, Duration::from_millis(25)), 2);
+    let state = timestep.unwrap();
+    assert_eq!((state.ticks(), state.ticks_this_frame()), (2, 2));
+    assert!((state.alpha() - 0.5).abs() < 1e-9);
+
+    // the leftover half tick carries over into the next frame
+    assert_eq!(tick_frame(&mut handler, &mut timestep, Duration::from_millis(5)), 1);
+    assert!(timestep.unwrap().alpha().abs() < 1e-9);
+    assert!(handler.ticks_dropped.is_empty());
+  }
+
+  #[test]
+  fn fixed_timestep_drops_ticks_beyond_the_limit() {
+    let mut handler = TickHandler::default();
+    let fixed_timestep = FixedTimestep::new(Duration::from_millis(10)).with_max_ticks_per_frame(3);
+    let mut timestep = Some(TimestepState::new(fixed_timestep));
+    assert_eq!(tick_frame(&mut handler, &mut timestep, Duration::from_millis(55)), 3);
+    let state = timestep.unwrap();
+    assert_eq!((state.ticks_dropped(), state.ticks_dropped_total()), (2, 2));
+    assert!((state.alpha() - 0.5).abs() < 1e-9);
+    assert_eq!(handler.ticks_dropped, [2]);
+
+    assert_eq!(tick_frame(&mut handler, &mut timestep, Duration::from_millis(10)), 1);
+    let state = timestep.unwrap();
+    assert_eq!((state.ticks(), state.ticks_dropped(), state.ticks_dropped_total()), (4, 0, 2));
+    assert_eq!(handler.ticks_dropped, [2]);
+  }
+
+  #[test]
+  fn fixed_timestep_saturates_huge_backlogs() {
+    let fixed_timestep = FixedTimestep::new(Duration::from_nanos(1)).with_max_ticks_per_frame(0);
+    let mut state = TimestepState::new(fixed_timestep);
+    state.begin_frame(Duration::from_secs(10));
+    assert!(!state.try_tick());
+    assert_eq!(state.ticks_dropped(), u32::MAX);
+    assert_eq!(state.ticks_dropped_total(), u32::MAX as u64);
+  }
+
+  #[test]
+  fn fixed_timesteps_from_tick_rates() {
+    assert_eq!(FixedTimestep::from_tick_rate(50.0).tick_duration, Duration::from_millis(20));
+    for tick_rate in [0.0, -1.0, f64::NAN, f64::INFINITY, 1e-300, 1e300] {
+      assert!(std::panic::catch_unwind(|| FixedTimestep::from_tick_rate(tick_rate)).is_err(), "{tick_rate} was accepted");
+    };
+  }
+
+  #[test]
+  fn frame_timing_percentiles_use_recent_frames() {
+    let mut frame_timing = FrameTiming::new(4);
+    let start = Instant::now();
+    let mut now = start;
+    frame_timing.begin_frame(now);
+    assert_eq!(frame_timing.frame_time_percentile(50.0), Duration::ZERO);
+    for millis in [10, 20, 30, 40, 50] {
+      now += Duration::from_millis(millis);
+      frame_timing.begin_frame(now);
+    };
+
+    // only the four most recent frame times are kept
+    let millis = |percentile| frame_timing.frame_time_percentile(percentile).as_millis();
+    assert_eq!([millis(0.0), millis(25.0), millis(50.0), millis(75.0), millis(99.0), millis(100.0)], [20, 20, 30, 40, 50, 50]);
+    assert_eq!(millis(-10.0), 20);
+    assert_eq!(millis(200.0), 50);
+
+    assert_eq!(frame_timing.frame_count(), 6);
+    assert_eq!(frame_timing.elapsed(), now - start);
+    assert_eq!(frame_timing.delta_time(), Duration::from_millis(50));
+    assert_eq!(frame_timing.average_frame_time(), Duration::from_millis(35));
+    assert_eq!((frame_timing.min_frame_time(), frame_timing.max_frame_time()), (Duration::from_millis(20), Duration::from_millis(50)));
+  }
+
+  #[test]
+  fn releases_without_a_press_are_not_clicks() {
+    let mut window_state = headless_window_state();
+    let mouse_input = |state| InputEvent::MouseInput { device_id: DeviceId::dummy(), state, button: MouseButton::Left };
+    window_state.handle_input_event(&mut NullHandler, mouse_input(ElementState::Pressed));
+    window_state.handle_input_event(&mut NullHandler, mouse_input(ElementState::Released));
+    assert!(window_state.input().was_clicked(MouseButton::Left));
+
+    window_state.reset();
+    window_state.handle_input_event(&mut NullHandler, mouse_input(ElementState::Released));
+    assert!(window_state.input().button_events().is_empty());
+  }
+
+  #[test]
+  fn gamepads_are_polled_while_waiting_for_events() {
+    let mut application = headless_application(RedrawMode::OnDemand);
+    let start = Instant::now();
+    assert_eq!(application.pace_frame(start), (true, ControlFlow::Wait));
+
+    let gamepad_source = FakeGamepadSource::new();
+    application.gamepad_source = Some(Box::new(gamepad_source.clone()));
+    let now = start + Duration::from_millis(100);
+    assert_eq!(application.pace_frame(now), (true, ControlFlow::WaitUntil(now + RedrawMode::GAMEPAD_POLL_INTERVAL)));
+
+    gamepad_source.connect(GamepadId(0), "Gamepad");
+    assert!(iterate(&mut application, now));
+    let window_state = application.primary_window_state().unwrap();
+    assert_eq!(window_state.input().gamepad(GamepadId(0)).map(GamepadState::name), Some("Gamepad"));
+  }
+
+  #[test]
+  fn key_text_is_ignored_while_the_ime_is_enabled() {
+    let mut window_state = headless_window_state();
+    let key_event = |text: &str| InputEvent::Keyboard {
+      device_id: DeviceId::dummy(),
+      physical_key: PhysicalKey::Code(KeyCode::KeyA),
+      logical_key: LogicalKey::Character(text.into()),
+      text: Some(text.into()),
+      location: KeyLocation::Standard,
+      state: ElementState::Pressed,
+      repeat: false
+    };
+
+    window_state.handle_input_event(&mut NullHandler, key_event("a"));
+    window_state.handle_input_event(&mut NullHandler, InputEvent::Ime(Ime::Enabled));
+    window_state.handle_input_event(&mut NullHandler, key_event("b"));
+    window_state.handle_input_event(&mut NullHandler, InputEvent::Ime(Ime::Commit("b".to_owned())));
+    window_state.handle_input_event(&mut NullHandler, InputEvent::Ime(Ime::Disabled));
+    window_state.handle_input_event(&mut NullHandler, key_event("c"));
+    assert_eq!(window_state.input().text(), "abc");
+
+    // losing focus does not disable the IME
+    window_state.handle_input_event(&mut NullHandler, InputEvent::Ime(Ime::Enabled));
+    window_state.handle_input_event(&mut NullHandler, InputEvent::Focused(false));
+    window_state.handle_input_event(&mut NullHandler, InputEvent::Focused(true));
+    window_state.handle_input_event(&mut NullHandler, key_event("d"));
+    window_state.handle_input_event(&mut NullHandler, InputEvent::Ime(Ime::Commit("d".to_owned())));
+    assert_eq!(window_state.input().text(), "d");
   }
 }